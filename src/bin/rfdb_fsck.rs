@@ -0,0 +1,111 @@
+//! rfdb-fsck - offline consistency check for RFDB databases
+//!
+//! Validates segment headers, column sizes, string table offsets, checksums,
+//! duplicate node ids, metadata JSON and dangling edges. With `--repair`
+//! rewrites segments without the broken rows (originals kept as *.bak),
+//! finishing or discarding an interrupted compaction first.
+//! Unreadable files, column checksum mismatches and a broken metadata.json
+//! are reported but never rewritten.
+//!
+//! Usage:
+//!   rfdb-fsck /path/to/graph.rfdb [--repair] [--max-issues N]
+//!
+//! Exit codes (as in e2fsck):
+//!   0 - no problems, 1 - problems repaired, 4 - problems left, 8 - operational error
+
+use rfdb::graph::normalize_db_path;
+use rfdb::storage::fsck::{self, FsckReport};
+
+const EXIT_CLEAN: i32 = 0;
+const EXIT_REPAIRED: i32 = 1;
+const EXIT_UNCORRECTED: i32 = 4;
+const EXIT_ERROR: i32 = 8;
+
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+fn print_report(report: &FsckReport, max_issues: usize) {
+    println!("nodes.bin: {} rows, edges.bin: {} rows", report.node_rows, report.edge_rows);
+
+    for issue in report.issues.iter().take(max_issues) {
+        match issue.row {
+            Some(row) => println!("  {} row {}: [{:?}] {}", issue.file, row, issue.kind, issue.message),
+            None => println!("  {}: [{:?}] {}", issue.file, issue.kind, issue.message),
+        }
+    }
+    if report.issues.len() > max_issues {
+        println!("  ... and {} more", report.issues.len() - max_issues);
+    }
+
+    let (nodes, edges) = report.rows_to_drop();
+    println!("{} problem(s), repair would drop {} node row(s) and {} edge row(s)",
+        report.issues.len(), nodes, edges);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 2 || args[1].starts_with("--") {
+        eprintln!("Usage: rfdb-fsck <db-path> [--repair] [--max-issues <n>]");
+        eprintln!();
+        eprintln!("Arguments:");
+        eprintln!("  <db-path>      Path to graph database directory");
+        eprintln!("  --repair       Rewrite segments without broken rows (keeps *.bak)");
+        eprintln!("  --max-issues   Number of problems to print (default: 50)");
+        std::process::exit(EXIT_ERROR);
+    }
+
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")))
+        .with_writer(std::io::stderr)
+        .try_init();
+
+    let db_path = normalize_db_path(&args[1]);
+    let repair = args.iter().any(|a| a == "--repair");
+    let max_issues = arg_value(&args, "--max-issues")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(50);
+
+    println!("Checking {}", db_path.display());
+    let report = match fsck::check(&db_path) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("rfdb-fsck: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    print_report(&report, max_issues);
+
+    if report.is_clean() {
+        println!("Database is clean");
+        std::process::exit(EXIT_CLEAN);
+    }
+
+    if !repair {
+        println!("Run with --repair to rewrite segments without the broken rows");
+        std::process::exit(EXIT_UNCORRECTED);
+    }
+
+    if let Some(issue) = report.blocking_issue() {
+        eprintln!("rfdb-fsck: {} cannot be repaired ({:?}), restore from backup or re-analyze",
+            issue.file, issue.kind);
+        std::process::exit(EXIT_UNCORRECTED);
+    }
+
+    match fsck::repair(&db_path) {
+        Ok(stats) => {
+            println!("Repaired: kept {} nodes ({} dropped), {} edges ({} dropped)",
+                stats.nodes_kept, stats.nodes_dropped, stats.edges_kept, stats.edges_dropped);
+            std::process::exit(EXIT_REPAIRED);
+        }
+        Err(e) => {
+            eprintln!("rfdb-fsck: repair failed: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! rfdb-neo4j-import - offline migration of `neo4j-admin import` CSV files
+//!
+//! Creates a new database from node/relationship CSV files with typed headers
+//! (`:ID(Group)`, `:LABEL`, `:START_ID`, `:END_ID`, `:TYPE`, `name:int`, ...).
+//!
+//! Usage:
+//!   rfdb-neo4j-import <db-path> --nodes [Label;Label=]file.csv ... \
+//!       --relationships [TYPE=]file.csv ... [--delimiter ,] [--array-delimiter ;]
+//!       [--id-mode hashed|computed] [--version main] [--skip-bad-relationships]
+
+use rfdb::graph::{GraphEngine, GraphStore, Neo4jCsvImport, normalize_db_path};
+
+fn usage() -> ! {
+    eprintln!("Usage: rfdb-neo4j-import <db-path> --nodes [Label=]<file.csv> ... --relationships [TYPE=]<file.csv> ...");
+    eprintln!();
+    eprintln!("Arguments:");
+    eprintln!("  <db-path>                   New database directory (must not contain segments)");
+    eprintln!("  --nodes [L1;L2=]<file>      Node CSV, optional labels for every row (repeatable)");
+    eprintln!("  --relationships [T=]<file>  Relationship CSV, optional default type (repeatable)");
+    eprintln!("  --delimiter <c>             Field delimiter (default: ,)");
+    eprintln!("  --array-delimiter <c>       Label/array delimiter (default: ;)");
+    eprintln!("  --id-mode <hashed|computed> Node ids: hash of Group:id, or compute_node_id (default: hashed)");
+    eprintln!("  --version <v>               Version of imported records (default: main)");
+    eprintln!("  --skip-bad-relationships    Skip relationships with unknown endpoints");
+    std::process::exit(1);
+}
+
+/// `Label=file.csv` -> (Some("Label"), "file.csv")
+fn split_source(arg: &str) -> (Option<&str>, &str) {
+    match arg.split_once('=') {
+        Some((prefix, path)) if !prefix.is_empty() => (Some(prefix), path),
+        _ => (None, arg),
+    }
+}
+
+fn single_char(flag: &str, value: &str) -> char {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => {
+            eprintln!("rfdb-neo4j-import: {} expects a single character", flag);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 || args[1].starts_with("--") {
+        usage();
+    }
+
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")))
+        .with_writer(std::io::stderr)
+        .try_init();
+
+    let db_path = normalize_db_path(&args[1]);
+    let mut import = Neo4jCsvImport::new();
+    let mut rest = args[2..].iter();
+    while let Some(flag) = rest.next() {
+        if flag == "--skip-bad-relationships" {
+            import = import.skip_bad_relationships(true);
+            continue;
+        }
+        let Some(value) = rest.next() else { usage() };
+        import = match flag.as_str() {
+            "--nodes" => match split_source(value) {
+                (Some(labels), path) => import.nodes_with_labels(path, labels.split(';').map(str::to_string).collect()),
+                (None, path) => import.nodes(path),
+            },
+            "--relationships" => match split_source(value) {
+                (Some(edge_type), path) => import.relationships_with_type(path, edge_type),
+                (None, path) => import.relationships(path),
+            },
+            "--delimiter" => {
+                let c = single_char(flag, value);
+                if !c.is_ascii() {
+                    eprintln!("rfdb-neo4j-import: --delimiter must be ASCII");
+                    std::process::exit(1);
+                }
+                import.delimiter(c as u8)
+            }
+            "--array-delimiter" => import.array_delimiter(single_char(flag, value)),
+            "--id-mode" => match value.parse() {
+                Ok(mode) => import.id_mode(mode),
+                Err(e) => {
+                    eprintln!("rfdb-neo4j-import: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            "--version" => import.version(value.as_str()),
+            _ => usage(),
+        };
+    }
+
+    if db_path.join("nodes.bin").exists() || db_path.join("edges.bin").exists() {
+        eprintln!("rfdb-neo4j-import: {} already contains a graph", db_path.display());
+        std::process::exit(1);
+    }
+
+    let result = GraphEngine::create(&db_path).and_then(|mut engine| {
+        let stats = engine.import_neo4j_csv(&import)?;
+        engine.flush()?;
+        Ok(stats)
+    });
+    match result {
+        Ok(stats) => {
+            println!("Imported {} nodes, {} relationships into {}", stats.nodes, stats.relationships, db_path.display());
+            if stats.skipped_relationships > 0 {
+                println!("Skipped {} relationship(s) with unknown endpoints", stats.skipped_relationships);
+            }
+        }
+        Err(e) => {
+            eprintln!("rfdb-neo4j-import: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -4,25 +4,28 @@
 //! Multiple clients can connect and share the same graph.
 //!
 //! Usage:
-//!   rfdb-server /path/to/graph.rfdb [--socket /tmp/rfdb.sock]
+//!   rfdb-server /path/to/graph.rfdb [--socket /tmp/rfdb.sock] [--read-only]
 //!
 //! Protocol:
 //!   Request:  [4-byte length BE] [MessagePack payload]
 //!   Response: [4-byte length BE] [MessagePack payload]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{Read, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
 
 // Import from library
-use rfdb::graph::{GraphEngine, GraphStore};
+use rfdb::graph::{GraphEngine, GraphStore, CycleComponent, DeadCodeQuery, EndpointRule, ExportFilter, GraphPath, OrphanQuery, PathQuery, SubgraphQuery};
 use rfdb::storage::{NodeRecord, EdgeRecord, AttrQuery};
 use rfdb::datalog::{parse_program, parse_atom, Evaluator};
+use rfdb::GraphError;
 
 // ============================================================================
 // Wire Protocol Types
@@ -80,6 +83,96 @@ pub enum Request {
         #[serde(default)]
         backward: bool,
     },
+    /// Shortest path with nodes and edges; direction "outgoing" (default) | "incoming" | "both"
+    ShortestPath {
+        src: String,
+        dst: String,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default)]
+        direction: Option<String>,
+    },
+    /// Simple paths from any of sourceIds to any of targetIds (bounded)
+    AllPaths {
+        #[serde(rename = "sourceIds")]
+        source_ids: Vec<String>,
+        #[serde(rename = "targetIds")]
+        target_ids: Vec<String>,
+        #[serde(default, rename = "maxLength")]
+        max_length: Option<usize>,
+        #[serde(default, rename = "maxResults")]
+        max_results: Option<usize>,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default, rename = "excludeNodeTypes")]
+        exclude_node_types: Vec<String>,
+        #[serde(default)]
+        direction: Option<String>,
+    },
+    /// Strongly connected components with cycles (circular imports, mutual recursion)
+    CycleComponents {
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default, rename = "withCycles")]
+        with_cycles: bool,
+    },
+    /// Topological order with longest-path layers; cycles are reported, not fatal
+    TopologicalSort {
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+    },
+    /// Immediate dominators from root (post: post-dominators towards root as exit)
+    Dominators {
+        root: String,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default)]
+        post: bool,
+        /// Only these nodes in the result (default: all reachable)
+        #[serde(default, rename = "nodeIds")]
+        node_ids: Option<Vec<String>>,
+    },
+    /// Weakly connected components (islands), largest first
+    WeaklyConnectedComponents {
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        /// Skip components smaller than this (default: 1)
+        #[serde(default, rename = "minSize")]
+        min_size: Option<usize>,
+    },
+    /// Nodes without incoming edges of edgeTypes, except entry-point types
+    Orphans {
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default, rename = "nodeTypes")]
+        node_types: Vec<String>,
+        #[serde(default, rename = "excludeTypes")]
+        exclude_types: Vec<String>,
+    },
+    /// FUNCTION/METHOD/CLASS nodes unreachable from entry points, grouped by file
+    DeadCode {
+        #[serde(default, rename = "entryTypes")]
+        entry_types: Vec<String>,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default, rename = "entryNames")]
+        entry_names: Option<Vec<String>>,
+        #[serde(default, rename = "targetTypes")]
+        target_types: Option<Vec<String>>,
+        #[serde(default, rename = "exportedAreEntries")]
+        exported_are_entries: Option<bool>,
+        #[serde(default, rename = "endpointsAreEntries")]
+        endpoints_are_entries: Option<bool>,
+    },
+    /// Nodes affected by edits to files (backward reachability), grouped by type
+    Impact {
+        files: Vec<String>,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        /// Default: unlimited
+        #[serde(default, rename = "maxDepth")]
+        max_depth: Option<u32>,
+    },
     Dfs {
         #[serde(rename = "startIds")]
         start_ids: Vec<String>,
@@ -111,6 +204,40 @@ pub enum Request {
     // Control
     Flush,
     Compact,
+    Verify,
+    Backup { path: String },
+    Restore { path: String },
+    GetFormatVersion,
+    UpgradeFormat,
+    /// "none" | "lz4"; existing segments are rewritten immediately
+    SetCompression { compression: String },
+    /// Streamed as `JsonlChunk` frames of up to `chunkSize` lines; the last has `done: true`
+    ExportJsonl {
+        #[serde(default)]
+        version: Option<String>,
+        #[serde(default, rename = "nodeType")]
+        node_type: Option<String>,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Option<Vec<String>>,
+        #[serde(default, rename = "includeDeleted")]
+        include_deleted: bool,
+        #[serde(default, rename = "chunkSize")]
+        chunk_size: Option<usize>,
+    },
+    /// Lines of an `ExportJsonl` dump; send large dumps as several requests
+    ImportJsonl { lines: Vec<String> },
+    /// Subgraph around `startIds` as "graphml" or "dot" text
+    ExportSubgraph {
+        #[serde(rename = "startIds")]
+        start_ids: Vec<String>,
+        #[serde(rename = "maxDepth")]
+        max_depth: u32,
+        #[serde(default, rename = "edgeTypes")]
+        edge_types: Vec<String>,
+        #[serde(default)]
+        backward: bool,
+        format: String,
+    },
     Clear,
     Ping,
     Shutdown,
@@ -130,10 +257,74 @@ pub enum Request {
 
     // Node utility
     IsEndpoint { id: String },
+    GetEndpointRules,
+    SetEndpointRules { rules: Vec<WireEndpointRule> },
+    AddEndpointRule { rule: WireEndpointRule },
     GetNodeIdentifier { id: String },
     UpdateNodeVersion { id: String, version: String },
 }
 
+impl Request {
+    /// Command name for logging (without payload)
+    fn name(&self) -> &'static str {
+        match self {
+            Request::AddNodes { .. } => "addNodes",
+            Request::AddEdges { .. } => "addEdges",
+            Request::DeleteNode { .. } => "deleteNode",
+            Request::DeleteEdge { .. } => "deleteEdge",
+            Request::GetNode { .. } => "getNode",
+            Request::NodeExists { .. } => "nodeExists",
+            Request::FindByType { .. } => "findByType",
+            Request::FindByAttr { .. } => "findByAttr",
+            Request::Neighbors { .. } => "neighbors",
+            Request::Bfs { .. } => "bfs",
+            Request::Reachability { .. } => "reachability",
+            Request::ShortestPath { .. } => "shortestPath",
+            Request::AllPaths { .. } => "allPaths",
+            Request::CycleComponents { .. } => "cycleComponents",
+            Request::TopologicalSort { .. } => "topologicalSort",
+            Request::Dominators { .. } => "dominators",
+            Request::WeaklyConnectedComponents { .. } => "weaklyConnectedComponents",
+            Request::Orphans { .. } => "orphans",
+            Request::DeadCode { .. } => "deadCode",
+            Request::Impact { .. } => "impact",
+            Request::Dfs { .. } => "dfs",
+            Request::GetOutgoingEdges { .. } => "getOutgoingEdges",
+            Request::GetIncomingEdges { .. } => "getIncomingEdges",
+            Request::NodeCount => "nodeCount",
+            Request::EdgeCount => "edgeCount",
+            Request::CountNodesByType { .. } => "countNodesByType",
+            Request::CountEdgesByType { .. } => "countEdgesByType",
+            Request::Flush => "flush",
+            Request::Compact => "compact",
+            Request::Verify => "verify",
+            Request::Backup { .. } => "backup",
+            Request::Restore { .. } => "restore",
+            Request::GetFormatVersion => "getFormatVersion",
+            Request::UpgradeFormat => "upgradeFormat",
+            Request::SetCompression { .. } => "setCompression",
+            Request::ExportJsonl { .. } => "exportJsonl",
+            Request::ImportJsonl { .. } => "importJsonl",
+            Request::ExportSubgraph { .. } => "exportSubgraph",
+            Request::Clear => "clear",
+            Request::Ping => "ping",
+            Request::Shutdown => "shutdown",
+            Request::GetAllEdges => "getAllEdges",
+            Request::QueryNodes { .. } => "queryNodes",
+            Request::CheckGuarantee { .. } => "checkGuarantee",
+            Request::DatalogLoadRules { .. } => "datalogLoadRules",
+            Request::DatalogClearRules => "datalogClearRules",
+            Request::DatalogQuery { .. } => "datalogQuery",
+            Request::IsEndpoint { .. } => "isEndpoint",
+            Request::GetEndpointRules => "getEndpointRules",
+            Request::SetEndpointRules { .. } => "setEndpointRules",
+            Request::AddEndpointRule { .. } => "addEndpointRule",
+            Request::GetNodeIdentifier { .. } => "getNodeIdentifier",
+            Request::UpdateNodeVersion { .. } => "updateNodeVersion",
+        }
+    }
+}
+
 /// Response to client
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
@@ -151,6 +342,31 @@ pub enum Response {
     Violations { violations: Vec<WireViolation> },
     Identifier { identifier: Option<String> },
     DatalogResults { results: Vec<WireViolation> },
+    EndpointRules { rules: Vec<WireEndpointRule> },
+    JsonlChunk { lines: Vec<String>, done: bool },
+    Text { text: String },
+    Path { path: Option<WirePath> },
+    Paths { paths: Vec<WirePath> },
+    Components { components: Vec<WireComponent> },
+    Dominators { idom: HashMap<String, String> },
+    DeadCode {
+        #[serde(rename = "entryPoints")]
+        entry_points: usize,
+        files: BTreeMap<String, Vec<WireNode>>,
+    },
+    Impact {
+        changed: Vec<String>,
+        #[serde(rename = "byType")]
+        by_type: BTreeMap<String, Vec<WireNode>>,
+        #[serde(rename = "testFiles")]
+        test_files: Vec<String>,
+    },
+    TopoOrder {
+        order: Vec<String>,
+        layers: HashMap<String, usize>,
+        unordered: Vec<String>,
+        cycles: Vec<WireComponent>,
+    },
 }
 
 /// Violation from guarantee check
@@ -189,6 +405,21 @@ pub struct WireEdge {
     pub metadata: Option<String>,
 }
 
+/// Path for wire protocol: edges[i] connects nodes[i] and nodes[i + 1]
+#[derive(Debug, Serialize)]
+pub struct WirePath {
+    pub nodes: Vec<String>,
+    pub edges: Vec<WireEdge>,
+}
+
+/// Strongly connected component; cycle is cycle[0] -> ... -> cycle[0]
+#[derive(Debug, Serialize)]
+pub struct WireComponent {
+    pub nodes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<Vec<String>>,
+}
+
 /// Attribute query for wire protocol
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -199,6 +430,19 @@ pub struct WireAttrQuery {
     pub exported: Option<bool>,
 }
 
+/// Endpoint rule for wire protocol
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WireEndpointRule {
+    /// Node type pattern, supports wildcard ("grpc:*")
+    pub node_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported: Option<bool>,
+    /// Metadata conditions as JSON object string ({"object":"redis"})
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+}
+
 // ============================================================================
 // ID Conversion (string <-> u128)
 // ============================================================================
@@ -258,6 +502,45 @@ fn wire_edge_to_record(edge: WireEdge) -> EdgeRecord {
     }
 }
 
+fn wire_rule_to_endpoint_rule(rule: WireEndpointRule) -> std::result::Result<EndpointRule, String> {
+    let metadata = match rule.metadata {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid endpoint rule metadata: {}", e))?,
+        None => HashMap::new(),
+    };
+    Ok(EndpointRule {
+        node_type: rule.node_type,
+        exported: rule.exported,
+        metadata,
+    })
+}
+
+fn endpoint_rule_to_wire(rule: &EndpointRule) -> WireEndpointRule {
+    WireEndpointRule {
+        node_type: rule.node_type.clone(),
+        exported: rule.exported,
+        metadata: if rule.metadata.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&rule.metadata).ok()
+        },
+    }
+}
+
+fn path_to_wire(path: &GraphPath) -> WirePath {
+    WirePath {
+        nodes: path.nodes.iter().map(|&id| id_to_string(id)).collect(),
+        edges: path.edges.iter().map(record_to_wire_edge).collect(),
+    }
+}
+
+fn component_to_wire(component: CycleComponent) -> WireComponent {
+    WireComponent {
+        nodes: component.nodes.into_iter().map(id_to_string).collect(),
+        cycle: component.cycle.map(|cycle| cycle.into_iter().map(id_to_string).collect()),
+    }
+}
+
 fn record_to_wire_edge(record: &EdgeRecord) -> WireEdge {
     WireEdge {
         src: id_to_string(record.src),
@@ -271,24 +554,44 @@ fn record_to_wire_edge(record: &EdgeRecord) -> WireEdge {
 // Request Handler
 // ============================================================================
 
+/// Error response for a write request on a read-only database
+///
+/// GraphStore write methods on a read-only engine are silently skipped,
+/// so the client has to be told explicitly that nothing was stored.
+fn reject_read_only(engine: &GraphEngine, operation: &'static str) -> Option<Response> {
+    engine.is_read_only().then(|| Response::Error { error: GraphError::ReadOnly(operation).to_string() })
+}
+
 fn handle_request(engine: &mut GraphEngine, request: Request) -> Response {
     match request {
         // Write operations
         Request::AddNodes { nodes } => {
+            if let Some(response) = reject_read_only(engine, "addNodes") {
+                return response;
+            }
             let records: Vec<NodeRecord> = nodes.into_iter().map(wire_node_to_record).collect();
             engine.add_nodes(records);
             Response::Ok { ok: true }
         }
         Request::AddEdges { edges, skip_validation } => {
+            if let Some(response) = reject_read_only(engine, "addEdges") {
+                return response;
+            }
             let records: Vec<EdgeRecord> = edges.into_iter().map(wire_edge_to_record).collect();
             engine.add_edges(records, skip_validation);
             Response::Ok { ok: true }
         }
         Request::DeleteNode { id } => {
+            if let Some(response) = reject_read_only(engine, "deleteNode") {
+                return response;
+            }
             engine.delete_node(string_to_id(&id));
             Response::Ok { ok: true }
         }
         Request::DeleteEdge { src, dst, edge_type } => {
+            if let Some(response) = reject_read_only(engine, "deleteEdge") {
+                return response;
+            }
             engine.delete_edge(string_to_id(&src), string_to_id(&dst), &edge_type);
             Response::Ok { ok: true }
         }
@@ -351,6 +654,117 @@ fn handle_request(engine: &mut GraphEngine, request: Request) -> Response {
                 .collect();
             Response::Ids { ids }
         }
+        Request::ShortestPath { src, dst, edge_types, direction } => {
+            let direction = match direction.as_deref().map(str::parse).transpose() {
+                Ok(direction) => direction.unwrap_or_default(),
+                Err(e) => return Response::Error { error: format!("{}", e) },
+            };
+            let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+            let path = engine.shortest_path(string_to_id(&src), string_to_id(&dst), &edge_types_refs, direction);
+            Response::Path { path: path.map(|p| path_to_wire(&p)) }
+        }
+        Request::AllPaths { source_ids, target_ids, max_length, max_results, edge_types, exclude_node_types, direction } => {
+            let direction = match direction.as_deref().map(str::parse).transpose() {
+                Ok(direction) => direction.unwrap_or_default(),
+                Err(e) => return Response::Error { error: format!("{}", e) },
+            };
+            let defaults = PathQuery::default();
+            let query = PathQuery::new(
+                max_length.unwrap_or(defaults.max_length),
+                max_results.unwrap_or(defaults.max_results),
+            )
+                .edge_types(edge_types)
+                .exclude_node_types(exclude_node_types)
+                .direction(direction);
+            let sources: Vec<u128> = source_ids.iter().map(|s| string_to_id(s)).collect();
+            let targets: Vec<u128> = target_ids.iter().map(|s| string_to_id(s)).collect();
+            let paths = engine.all_paths(&sources, &targets, &query);
+            Response::Paths { paths: paths.iter().map(path_to_wire).collect() }
+        }
+        Request::CycleComponents { edge_types, with_cycles } => {
+            let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+            let components = engine.cycle_components(&edge_types_refs, with_cycles)
+                .into_iter()
+                .map(component_to_wire)
+                .collect();
+            Response::Components { components }
+        }
+        Request::TopologicalSort { edge_types } => {
+            let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+            let topo = engine.topological_sort(&edge_types_refs);
+            Response::TopoOrder {
+                order: topo.order.into_iter().map(id_to_string).collect(),
+                layers: topo.layers.into_iter().map(|(id, layer)| (id_to_string(id), layer)).collect(),
+                unordered: topo.unordered.into_iter().map(id_to_string).collect(),
+                cycles: topo.cycles.into_iter().map(component_to_wire).collect(),
+            }
+        }
+        Request::Dominators { root, edge_types, post, node_ids } => {
+            let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+            let root = string_to_id(&root);
+            let dom = if post {
+                engine.post_dominators(root, &edge_types_refs)
+            } else {
+                engine.dominators(root, &edge_types_refs)
+            };
+            let idom = match node_ids {
+                Some(ids) => ids.into_iter()
+                    .filter_map(|id| dom.immediate_dominator(string_to_id(&id)).map(|d| (id, id_to_string(d))))
+                    .collect(),
+                None => dom.idoms().iter().map(|(&id, &d)| (id_to_string(id), id_to_string(d))).collect(),
+            };
+            Response::Dominators { idom }
+        }
+        Request::WeaklyConnectedComponents { edge_types, min_size } => {
+            let components = engine.weakly_connected_components(&edge_types)
+                .into_iter()
+                .filter(|nodes| nodes.len() >= min_size.unwrap_or(1))
+                .map(|nodes| WireComponent { nodes: nodes.into_iter().map(id_to_string).collect(), cycle: None })
+                .collect();
+            Response::Components { components }
+        }
+        Request::Orphans { edge_types, node_types, exclude_types } => {
+            let query = OrphanQuery::new()
+                .edge_types(edge_types)
+                .node_types(node_types)
+                .exclude_types(exclude_types);
+            let ids = engine.orphans(&query).into_iter().map(id_to_string).collect();
+            Response::Ids { ids }
+        }
+        Request::DeadCode { entry_types, edge_types, entry_names, target_types, exported_are_entries, endpoints_are_entries } => {
+            let mut query = DeadCodeQuery::new(entry_types, edge_types);
+            if let Some(names) = entry_names {
+                query = query.entry_names(names);
+            }
+            if let Some(types) = target_types {
+                query = query.target_types(types);
+            }
+            if let Some(enabled) = exported_are_entries {
+                query = query.exported_are_entries(enabled);
+            }
+            if let Some(enabled) = endpoints_are_entries {
+                query = query.endpoints_are_entries(enabled);
+            }
+            let report = engine.dead_code(&query);
+            Response::DeadCode {
+                entry_points: report.entry_points,
+                files: report.files.into_iter()
+                    .map(|(file, nodes)| (file, nodes.iter().map(record_to_wire_node).collect()))
+                    .collect(),
+            }
+        }
+        Request::Impact { files, edge_types, max_depth } => {
+            let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+            let max_depth = max_depth.map_or(usize::MAX, |d| d as usize);
+            let report = engine.impact(&files, &edge_types_refs, max_depth);
+            Response::Impact {
+                changed: report.changed.into_iter().map(id_to_string).collect(),
+                by_type: report.by_type.into_iter()
+                    .map(|(node_type, nodes)| (node_type, nodes.iter().map(record_to_wire_node).collect()))
+                    .collect(),
+                test_files: report.test_files,
+            }
+        }
         Request::Dfs { start_ids, max_depth, edge_types } => {
             let start: Vec<u128> = start_ids.iter().map(|s| string_to_id(s)).collect();
             let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
@@ -411,7 +825,65 @@ fn handle_request(engine: &mut GraphEngine, request: Request) -> Response {
                 Err(e) => Response::Error { error: e.to_string() },
             }
         }
+        Request::Verify => {
+            match engine.verify() {
+                Ok(()) => Response::Ok { ok: true },
+                Err(e) => Response::Error { error: e.to_string() },
+            }
+        }
+        // Выполняется в handle_client (backup) под read lock и сюда не доходит
+        Request::Backup { .. } => Response::Error { error: "backup is only served over a client stream".to_string() },
+        Request::Restore { path } => {
+            match engine.restore(&path) {
+                Ok(()) => Response::Ok { ok: true },
+                Err(e) => Response::Error { error: e.to_string() },
+            }
+        }
+        Request::GetFormatVersion => {
+            Response::Count { count: engine.format_version() as u32 }
+        }
+        Request::SetCompression { compression } => {
+            match compression.parse().and_then(|c| engine.set_compression(c)) {
+                Ok(()) => Response::Ok { ok: true },
+                Err(e) => Response::Error { error: e.to_string() },
+            }
+        }
+        Request::UpgradeFormat => {
+            // value: true если segments были переписаны
+            match engine.upgrade_format() {
+                Ok(upgraded) => Response::Bool { value: upgraded },
+                Err(e) => Response::Error { error: e.to_string() },
+            }
+        }
+        // Стримится в handle_client (stream_export) и сюда не доходит
+        Request::ExportJsonl { .. } => Response::Error { error: "exportJsonl is only served over a client stream".to_string() },
+        Request::ImportJsonl { lines } => {
+            match engine.import_jsonl(lines.join("\n").as_bytes()) {
+                Ok(stats) => Response::Counts {
+                    counts: HashMap::from([("nodes".to_string(), stats.nodes), ("edges".to_string(), stats.edges)]),
+                },
+                Err(e) => Response::Error { error: e.to_string() },
+            }
+        }
+        Request::ExportSubgraph { start_ids, max_depth, edge_types, backward, format } => {
+            let format = match format.parse() {
+                Ok(format) => format,
+                Err(e) => return Response::Error { error: format!("{}", e) },
+            };
+            let start: Vec<u128> = start_ids.iter().map(|s| string_to_id(s)).collect();
+            let query = SubgraphQuery::new(start, max_depth as usize)
+                .edge_types(edge_types)
+                .backward(backward);
+            let mut out = Vec::new();
+            match engine.export_subgraph(&mut out, &query, format) {
+                Ok(()) => Response::Text { text: String::from_utf8_lossy(&out).into_owned() },
+                Err(e) => Response::Error { error: e.to_string() },
+            }
+        }
         Request::Clear => {
+            if let Some(response) = reject_read_only(engine, "clear") {
+                return response;
+            }
             engine.clear();
             Response::Ok { ok: true }
         }
@@ -477,6 +949,31 @@ fn handle_request(engine: &mut GraphEngine, request: Request) -> Response {
         Request::IsEndpoint { id } => {
             Response::Bool { value: engine.is_endpoint(string_to_id(&id)) }
         }
+        Request::GetEndpointRules => {
+            let rules = engine.endpoint_rules().iter().map(endpoint_rule_to_wire).collect();
+            Response::EndpointRules { rules }
+        }
+        Request::SetEndpointRules { rules } => {
+            let rules: std::result::Result<Vec<EndpointRule>, String> = rules.into_iter()
+                .map(wire_rule_to_endpoint_rule)
+                .collect();
+            match rules {
+                Ok(rules) => match engine.set_endpoint_rules(rules) {
+                    Ok(()) => Response::Ok { ok: true },
+                    Err(e) => Response::Error { error: e.to_string() },
+                },
+                Err(e) => Response::Error { error: e },
+            }
+        }
+        Request::AddEndpointRule { rule } => {
+            match wire_rule_to_endpoint_rule(rule) {
+                Ok(rule) => match engine.add_endpoint_rule(rule) {
+                    Ok(()) => Response::Ok { ok: true },
+                    Err(e) => Response::Error { error: e.to_string() },
+                },
+                Err(e) => Response::Error { error: e },
+            }
+        }
         Request::GetNodeIdentifier { id } => {
             let node = engine.get_node(string_to_id(&id));
             let identifier = node.and_then(|n| {
@@ -608,23 +1105,96 @@ fn write_message(stream: &mut UnixStream, data: &[u8]) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Lines per `JsonlChunk` frame when the client does not set `chunkSize`
+const DEFAULT_JSONL_CHUNK: usize = 10_000;
+
+/// `io::Write` that cuts JSONL output into lines and sends full chunks as frames
+struct JsonlChunkWriter<'a> {
+    stream: &'a mut UnixStream,
+    chunk_size: usize,
+    partial: Vec<u8>,
+    lines: Vec<String>,
+}
+
+impl JsonlChunkWriter<'_> {
+    fn send_chunk(&mut self) -> std::io::Result<()> {
+        let response = Response::JsonlChunk { lines: std::mem::take(&mut self.lines), done: false };
+        let bytes = rmp_serde::to_vec_named(&response)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        write_message(self.stream, &bytes)
+    }
+}
+
+impl Write for JsonlChunkWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            if byte != b'\n' {
+                self.partial.push(byte);
+                continue;
+            }
+            let line = String::from_utf8(std::mem::take(&mut self.partial))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            self.lines.push(line);
+            if self.lines.len() >= self.chunk_size {
+                self.send_chunk()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stream `ExportJsonl` as `JsonlChunk` frames; returns the final (done) frame
+fn stream_export(engine: &GraphEngine, filter: &ExportFilter, chunk_size: usize, stream: &mut UnixStream) -> Response {
+    let mut writer = JsonlChunkWriter { stream, chunk_size: chunk_size.max(1), partial: Vec::new(), lines: Vec::new() };
+    match engine.export_jsonl(&mut writer, filter) {
+        Ok(stats) => {
+            tracing::debug!(nodes = stats.nodes, edges = stats.edges, "Export streamed");
+            Response::JsonlChunk { lines: writer.lines, done: true }
+        }
+        Err(e) => Response::Error { error: e.to_string() },
+    }
+}
+
+/// Backup without holding the write lock during the copy
+///
+/// The delta is flushed under a short write lock; the checkpoint itself runs
+/// under the read lock and, with an empty delta, only hard-links the segments.
+fn backup(engine: &std::sync::RwLock<GraphEngine>, path: &str) -> Response {
+    {
+        let mut engine_guard = engine.write().unwrap();
+        if !engine_guard.is_read_only() {
+            if let Err(e) = engine_guard.flush() {
+                return Response::Error { error: e.to_string() };
+            }
+        }
+    }
+    match engine.read().unwrap().checkpoint(path) {
+        Ok(()) => Response::Ok { ok: true },
+        Err(e) => Response::Error { error: e.to_string() },
+    }
+}
+
 fn handle_client(
     mut stream: UnixStream,
     engine: Arc<std::sync::RwLock<GraphEngine>>,
     client_id: usize,
 ) {
-    eprintln!("[rfdb-server] Client {} connected", client_id);
+    tracing::info!(client_id, "Client connected");
 
     loop {
         // Read request
         let msg = match read_message(&mut stream) {
             Ok(Some(msg)) => msg,
             Ok(None) => {
-                eprintln!("[rfdb-server] Client {} disconnected", client_id);
+                tracing::info!(client_id, "Client disconnected");
                 break;
             }
             Err(e) => {
-                eprintln!("[rfdb-server] Client {} read error: {}", client_id, e);
+                tracing::warn!(client_id, "Read error: {}", e);
                 break;
             }
         };
@@ -633,6 +1203,7 @@ fn handle_client(
         let request: Request = match rmp_serde::from_slice(&msg) {
             Ok(req) => req,
             Err(e) => {
+                tracing::warn!(client_id, "Invalid request: {}", e);
                 let response = Response::Error { error: format!("Invalid request: {}", e) };
                 let resp_bytes = rmp_serde::to_vec(&response).unwrap();
                 let _ = write_message(&mut stream, &resp_bytes);
@@ -643,28 +1214,42 @@ fn handle_client(
         // Check for shutdown
         let is_shutdown = matches!(request, Request::Shutdown);
 
-        // Handle request
-        let response = {
-            let mut engine_guard = engine.write().unwrap();
-            handle_request(&mut engine_guard, request)
+        let span = tracing::info_span!("request", client_id, cmd = request.name());
+        let _enter = span.enter();
+        let start = Instant::now();
+
+        // Handle request (export streams its chunks itself, the last one is the response).
+        // Export and backup only read the graph, so they don't block other readers.
+        let response = match request {
+            Request::ExportJsonl { version, node_type, edge_types, include_deleted, chunk_size } => {
+                let filter = ExportFilter { version, node_type, edge_types, include_deleted };
+                stream_export(&engine.read().unwrap(), &filter, chunk_size.unwrap_or(DEFAULT_JSONL_CHUNK), &mut stream)
+            }
+            Request::Backup { path } => backup(&engine, &path),
+            request => handle_request(&mut engine.write().unwrap(), request),
         };
 
+        if let Response::Error { error } = &response {
+            tracing::warn!("Request failed: {}", error);
+        }
+        tracing::debug!(elapsed_ms = start.elapsed().as_millis() as u64, "Request handled");
+
         // Serialize and send response (use to_vec_named for proper field names)
         let resp_bytes = match rmp_serde::to_vec_named(&response) {
             Ok(bytes) => bytes,
             Err(e) => {
-                eprintln!("[rfdb-server] Serialize error: {}", e);
+                tracing::error!("Serialize error: {}", e);
                 continue;
             }
         };
 
         if let Err(e) = write_message(&mut stream, &resp_bytes) {
-            eprintln!("[rfdb-server] Client {} write error: {}", client_id, e);
+            tracing::warn!(client_id, "Write error: {}", e);
             break;
         }
 
         if is_shutdown {
-            eprintln!("[rfdb-server] Shutdown requested by client {}", client_id);
+            tracing::info!(client_id, "Shutdown requested");
             std::process::exit(0);
         }
     }
@@ -674,66 +1259,119 @@ fn handle_client(
 // Main
 // ============================================================================
 
+/// Get value of `--flag <value>` CLI argument
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+/// Install global tracing subscriber writing to stderr
+///
+/// Level priority: --log-level > RUST_LOG > info
+fn init_logging(level: Option<&str>, format: &str) {
+    let filter = match level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match format {
+        "json" => builder.json().init(),
+        "text" => builder.init(),
+        other => {
+            builder.init();
+            tracing::warn!("Unknown log format {:?}, using text", other);
+        }
+    }
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
     if args.len() < 2 {
-        eprintln!("Usage: rfdb-server <db-path> [--socket <socket-path>]");
-        eprintln!("");
+        eprintln!("Usage: rfdb-server <db-path> [--socket <socket-path>] [--log-level <level>] [--log-format <text|json>] [--read-only]");
+        eprintln!();
         eprintln!("Arguments:");
         eprintln!("  <db-path>      Path to graph database directory");
         eprintln!("  --socket       Unix socket path (default: /tmp/rfdb.sock)");
+        eprintln!("  --log-level    Log filter: error, warn, info, debug, trace or RUST_LOG syntax");
+        eprintln!("                 (default: RUST_LOG env or info)");
+        eprintln!("  --log-format   Log output format: text or json (default: text)");
+        eprintln!("  --read-only    Open existing database without taking the writer lock");
         std::process::exit(1);
     }
 
     let db_path = PathBuf::from(&args[1]);
-    let socket_path = args.iter()
-        .position(|a| a == "--socket")
-        .and_then(|i| args.get(i + 1))
-        .map(|s| s.as_str())
-        .unwrap_or("/tmp/rfdb.sock");
+    let socket_path = arg_value(&args, "--socket").unwrap_or("/tmp/rfdb.sock");
 
-    // Remove stale socket file
-    let _ = std::fs::remove_file(socket_path);
+    init_logging(arg_value(&args, "--log-level"), arg_value(&args, "--log-format").unwrap_or("text"));
+
+    let read_only = args.iter().any(|a| a == "--read-only");
 
     // Open or create database
-    eprintln!("[rfdb-server] Opening database: {:?}", db_path);
-    let engine = if db_path.join("nodes.bin").exists() {
-        GraphEngine::open(&db_path).expect("Failed to open database")
+    tracing::info!(path = ?db_path, read_only, "Opening database");
+    let opened = if read_only {
+        GraphEngine::open_read_only(&db_path)
+    } else if db_path.join("nodes.bin").exists() {
+        GraphEngine::open(&db_path)
     } else {
-        GraphEngine::create(&db_path).expect("Failed to create database")
+        GraphEngine::create(&db_path)
     };
+    let engine = match opened {
+        Ok(engine) => engine,
+        Err(e @ rfdb::GraphError::Locked { .. }) => {
+            tracing::error!("{}", e);
+            tracing::error!("Another rfdb-server is probably running on this database; use --read-only to open it for reading");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            tracing::error!("Failed to open database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Remove stale socket file (only after we own the database)
+    let _ = std::fs::remove_file(socket_path);
     let engine = Arc::new(std::sync::RwLock::new(engine));
 
-    eprintln!("[rfdb-server] Database opened: {} nodes, {} edges",
+    tracing::info!("Database opened: {} nodes, {} edges",
         engine.read().unwrap().node_count(),
         engine.read().unwrap().edge_count());
 
     // Bind Unix socket
     let listener = UnixListener::bind(socket_path).expect("Failed to bind socket");
-    eprintln!("[rfdb-server] Listening on {}", socket_path);
+    tracing::info!("Listening on {}", socket_path);
 
     // Set up signal handler for graceful shutdown
     let engine_for_signal = Arc::clone(&engine);
     let socket_path_for_signal = socket_path.to_string();
-    let mut signals = signal_hook::iterator::Signals::new(&[
+    let mut signals = signal_hook::iterator::Signals::new([
         signal_hook::consts::SIGINT,
         signal_hook::consts::SIGTERM,
     ]).expect("Failed to register signal handlers");
 
     thread::spawn(move || {
-        for sig in signals.forever() {
-            eprintln!("[rfdb-server] Received signal {}, flushing...", sig);
+        if let Some(sig) = signals.forever().next() {
+            tracing::info!("Received signal {}, flushing...", sig);
 
             if let Ok(mut guard) = engine_for_signal.write() {
-                match guard.flush() {
-                    Ok(()) => eprintln!("[rfdb-server] Flush complete"),
-                    Err(e) => eprintln!("[rfdb-server] Flush failed: {}", e),
+                if guard.is_read_only() {
+                    tracing::info!("Read-only database, nothing to flush");
+                } else {
+                    match guard.flush() {
+                        Ok(()) => tracing::info!("Flush complete"),
+                        Err(e) => tracing::error!("Flush failed: {}", e),
+                    }
                 }
             }
 
             let _ = std::fs::remove_file(&socket_path_for_signal);
-            eprintln!("[rfdb-server] Exiting");
+            tracing::info!("Exiting");
             std::process::exit(0);
         }
     });
@@ -750,7 +1388,7 @@ fn main() {
                 });
             }
             Err(e) => {
-                eprintln!("[rfdb-server] Accept error: {}", e);
+                tracing::error!("Accept error: {}", e);
             }
         }
     }
@@ -2,7 +2,8 @@
 //!
 //! Evaluates Datalog queries against a GraphEngine.
 
-use std::collections::HashMap;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
 use crate::graph::{GraphStore, GraphEngine};
 use crate::datalog::types::*;
 
@@ -94,6 +95,8 @@ impl Bindings {
 pub struct Evaluator<'a> {
     engine: &'a GraphEngine,
     rules: HashMap<String, Vec<Rule>>,
+    /// in_cycle: edge type -> ноды на циклах
+    cycle_nodes: RefCell<HashMap<Option<String>, HashSet<u128>>>,
 }
 
 impl<'a> Evaluator<'a> {
@@ -102,6 +105,7 @@ impl<'a> Evaluator<'a> {
         Evaluator {
             engine,
             rules: HashMap::new(),
+            cycle_nodes: RefCell::new(HashMap::new()),
         }
     }
 
@@ -120,7 +124,10 @@ impl<'a> Evaluator<'a> {
 
     /// Query for all bindings satisfying an atom
     pub fn query(&self, goal: &Atom) -> Vec<Bindings> {
-        self.eval_atom(goal)
+        let _span = tracing::debug_span!("datalog_query", predicate = goal.predicate()).entered();
+        let results = self.eval_atom(goal);
+        tracing::debug!("{} results", results.len());
+        results
     }
 
     /// Evaluate an atom (built-in or derived)
@@ -134,6 +141,7 @@ impl<'a> Evaluator<'a> {
             "neq" => self.eval_neq(atom),
             "starts_with" => self.eval_starts_with(atom),
             "not_starts_with" => self.eval_not_starts_with(atom),
+            "in_cycle" => self.eval_in_cycle(atom),
             _ => self.eval_derived(atom),
         }
     }
@@ -231,7 +239,7 @@ impl<'a> Evaluator<'a> {
 
                 let edges = self.engine.get_outgoing_edges(
                     src_id,
-                    edge_types.as_ref().map(|v| v.as_slice()),
+                    edge_types.as_deref(),
                 );
 
                 edges
@@ -296,7 +304,7 @@ impl<'a> Evaluator<'a> {
 
                 let edges = self.engine.get_incoming_edges(
                     dst_id,
-                    edge_types.as_ref().map(|v| v.as_slice()),
+                    edge_types.as_deref(),
                 );
 
                 edges
@@ -444,10 +452,8 @@ impl<'a> Evaluator<'a> {
                     Err(_) => return vec![],
                 };
 
-                // BFS with all edge types, max depth 100
-                let reachable = self.engine.bfs(&[src_id], 100, &[]);
-
-                if reachable.contains(&dst_id) {
+                // Bidirectional BFS with all edge types, max depth 100
+                if self.engine.is_reachable(src_id, dst_id, &[], 100) {
                     vec![Bindings::new()]
                 } else {
                     vec![]
@@ -522,6 +528,57 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    /// Evaluate in_cycle(X) / in_cycle(X, "EDGE_TYPE") - node lies on a cycle
+    fn eval_in_cycle(&self, atom: &Atom) -> Vec<Bindings> {
+        let args = atom.args();
+        if args.is_empty() {
+            return vec![];
+        }
+
+        let edge_type = match args.get(1) {
+            Some(Term::Const(edge_type)) => Some(edge_type.as_str()),
+            Some(_) => return vec![],
+            None => None,
+        };
+        let cycle_nodes = self.cycle_nodes(edge_type);
+
+        match &args[0] {
+            Term::Const(id_str) => match id_str.parse::<u128>() {
+                Ok(id) if cycle_nodes.contains(&id) => vec![Bindings::new()],
+                _ => vec![],
+            },
+            Term::Var(var) => {
+                let mut ids: Vec<u128> = cycle_nodes.iter().copied().collect();
+                ids.sort_unstable();
+                ids.into_iter()
+                    .map(|id| {
+                        let mut b = Bindings::new();
+                        b.set(var, Value::Id(id));
+                        b
+                    })
+                    .collect()
+            }
+            Term::Wildcard => {
+                if cycle_nodes.is_empty() {
+                    vec![]
+                } else {
+                    vec![Bindings::new()]
+                }
+            }
+        }
+    }
+
+    /// Ноды на циклах по `edge_type` (None - все рёбра); SCC считаются один раз на evaluator
+    fn cycle_nodes(&self, edge_type: Option<&str>) -> Ref<'_, HashSet<u128>> {
+        let key = edge_type.map(str::to_string);
+        if !self.cycle_nodes.borrow().contains_key(&key) {
+            let edge_types: Vec<&str> = edge_type.into_iter().collect();
+            let nodes = self.engine.cycle_nodes(&edge_types);
+            self.cycle_nodes.borrow_mut().insert(key.clone(), nodes);
+        }
+        Ref::map(self.cycle_nodes.borrow(), |cache| &cache[&key])
+    }
+
     /// Evaluate starts_with(X, Prefix) - string prefix check
     fn eval_starts_with(&self, atom: &Atom) -> Vec<Bindings> {
         let args = atom.args();
@@ -5,7 +5,7 @@
 //! - Query statistics (nodes visited, edges traversed, etc.)
 //! - Execution timing (profiling)
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 use serde::{Serialize, Deserialize};
 
@@ -30,6 +30,8 @@ pub struct QueryStats {
     pub incoming_edge_calls: usize,
     /// Number of BFS calls
     pub bfs_calls: usize,
+    /// Number of strongly connected component computations (in_cycle)
+    pub scc_calls: usize,
     /// Total results produced
     pub total_results: usize,
     /// Number of rule evaluations
@@ -105,6 +107,8 @@ pub struct EvaluatorExplain<'a> {
     predicate_times: HashMap<String, Duration>,
     /// Query start time
     query_start: Option<Instant>,
+    /// in_cycle: edge type -> ноды на циклах, SCC считаются один раз
+    cycle_nodes: HashMap<Option<String>, HashSet<u128>>,
 }
 
 impl<'a> EvaluatorExplain<'a> {
@@ -119,6 +123,7 @@ impl<'a> EvaluatorExplain<'a> {
             step_counter: 0,
             predicate_times: HashMap::new(),
             query_start: None,
+            cycle_nodes: HashMap::new(),
         }
     }
 
@@ -137,6 +142,7 @@ impl<'a> EvaluatorExplain<'a> {
 
     /// Query for all bindings satisfying an atom, with explain and profiling
     pub fn query(&mut self, goal: &Atom) -> QueryResult {
+        let _span = tracing::debug_span!("datalog_query", predicate = goal.predicate(), explain = self.explain_mode).entered();
         self.query_start = Some(Instant::now());
         self.stats = QueryStats::new();
         self.explain_steps.clear();
@@ -219,6 +225,7 @@ impl<'a> EvaluatorExplain<'a> {
             "neq" => self.eval_neq(atom),
             "starts_with" => self.eval_starts_with(atom),
             "not_starts_with" => self.eval_not_starts_with(atom),
+            "in_cycle" => self.eval_in_cycle(atom),
             _ => self.eval_derived(atom),
         };
 
@@ -330,7 +337,7 @@ impl<'a> EvaluatorExplain<'a> {
                 self.stats.outgoing_edge_calls += 1;
                 let edges = self.engine.get_outgoing_edges(
                     src_id,
-                    edge_types.as_ref().map(|v| v.as_slice()),
+                    edge_types.as_deref(),
                 );
                 self.stats.edges_traversed += edges.len();
 
@@ -393,7 +400,7 @@ impl<'a> EvaluatorExplain<'a> {
                 self.stats.incoming_edge_calls += 1;
                 let edges = self.engine.get_incoming_edges(
                     dst_id,
-                    edge_types.as_ref().map(|v| v.as_slice()),
+                    edge_types.as_deref(),
                 );
                 self.stats.edges_traversed += edges.len();
 
@@ -529,10 +536,9 @@ impl<'a> EvaluatorExplain<'a> {
                 };
 
                 self.stats.bfs_calls += 1;
-                let reachable = self.engine.bfs(&[src_id], 100, &[]);
-                self.stats.nodes_visited += reachable.len();
-
-                if reachable.contains(&dst_id) {
+                let (reachable, visited) = self.engine.reachable_visiting(src_id, dst_id, &[], 100);
+                self.stats.nodes_visited += visited;
+                if reachable {
                     vec![Bindings::new()]
                 } else {
                     vec![]
@@ -602,6 +608,52 @@ impl<'a> EvaluatorExplain<'a> {
         }
     }
 
+    /// Evaluate in_cycle(X) / in_cycle(X, "EDGE_TYPE") - node lies on a cycle
+    fn eval_in_cycle(&mut self, atom: &Atom) -> Vec<Bindings> {
+        let args = atom.args();
+        if args.is_empty() {
+            return vec![];
+        }
+
+        let key = match args.get(1) {
+            Some(Term::Const(edge_type)) => Some(edge_type.clone()),
+            Some(_) => return vec![],
+            None => None,
+        };
+        if !self.cycle_nodes.contains_key(&key) {
+            self.stats.scc_calls += 1;
+            let edge_types: Vec<&str> = key.as_deref().into_iter().collect();
+            let nodes = self.engine.cycle_nodes(&edge_types);
+            self.cycle_nodes.insert(key.clone(), nodes);
+        }
+        let cycle_nodes = &self.cycle_nodes[&key];
+
+        match &args[0] {
+            Term::Const(id_str) => match id_str.parse::<u128>() {
+                Ok(id) if cycle_nodes.contains(&id) => vec![Bindings::new()],
+                _ => vec![],
+            },
+            Term::Var(var) => {
+                let mut ids: Vec<u128> = cycle_nodes.iter().copied().collect();
+                ids.sort_unstable();
+                ids.into_iter()
+                    .map(|id| {
+                        let mut b = Bindings::new();
+                        b.set(var, Value::Id(id));
+                        b
+                    })
+                    .collect()
+            }
+            Term::Wildcard => {
+                if cycle_nodes.is_empty() {
+                    vec![]
+                } else {
+                    vec![Bindings::new()]
+                }
+            }
+        }
+    }
+
     /// Evaluate starts_with(X, Prefix)
     fn eval_starts_with(&mut self, atom: &Atom) -> Vec<Bindings> {
         let args = atom.args();
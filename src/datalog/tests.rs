@@ -72,8 +72,8 @@ mod atom_tests {
         ]);
         let vars = atom.variables();
         assert_eq!(vars.len(), 2);
-        assert!(vars.contains(&"X".to_string()));
-        assert!(vars.contains(&"Y".to_string()));
+        assert!(vars.contains("X"));
+        assert!(vars.contains("Y"));
     }
 
     #[test]
@@ -520,6 +520,47 @@ mod eval_tests {
         assert_eq!(results.len(), 0); // no path
     }
 
+    #[test]
+    fn test_explain_path_counts_visited_nodes() {
+        let engine = setup_test_graph();
+        let mut evaluator = EvaluatorExplain::new(&engine, false);
+
+        let result = evaluator.query(&parse_atom("path(\"1\", \"2\")").unwrap());
+        assert_eq!(result.bindings.len(), 1);
+        assert_eq!(result.stats.bfs_calls, 1);
+        assert!(result.stats.nodes_visited >= 2, "visited {}", result.stats.nodes_visited);
+    }
+
+    #[test]
+    fn test_eval_in_cycle() {
+        let mut engine = setup_test_graph();
+        // 2 -> 1 замыкает цикл 1 -> 4 -> 2 -> 1 (IMPORTS, не CALLS)
+        engine.add_edges(vec![EdgeRecord {
+            src: 2,
+            dst: 1,
+            edge_type: Some("IMPORTS".to_string()),
+            version: "main".into(),
+            metadata: None,
+            deleted: false,
+        }], false);
+        let evaluator = Evaluator::new(&engine);
+
+        let results = evaluator.eval_atom(&parse_atom("in_cycle(X)").unwrap());
+        let ids: std::collections::HashSet<_> = results.iter().filter_map(|b| b.get("X")).cloned().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(ids, [Value::Id(1), Value::Id(2), Value::Id(4)].into_iter().collect());
+
+        assert_eq!(evaluator.eval_atom(&parse_atom("in_cycle(\"4\")").unwrap()).len(), 1);
+        assert_eq!(evaluator.eval_atom(&parse_atom("in_cycle(\"3\")").unwrap()).len(), 0);
+        assert_eq!(evaluator.eval_atom(&parse_atom("in_cycle(X, \"CALLS\")").unwrap()).len(), 0);
+
+        // В explain SCC считаются отдельно от BFS для path
+        let mut explain = EvaluatorExplain::new(&engine, false);
+        let result = explain.query(&parse_atom("in_cycle(X)").unwrap());
+        assert_eq!(result.bindings.len(), 3);
+        assert_eq!((result.stats.scc_calls, result.stats.bfs_calls), (1, 0));
+    }
+
     #[test]
     fn test_eval_rule_simple() {
         let engine = setup_test_graph();
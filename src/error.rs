@@ -27,9 +27,18 @@ pub enum GraphError {
     #[error("Invalid file format: {0}")]
     InvalidFormat(String),
 
+    #[error("Segment was written against strings.bin {expected:016x}, found {found:016x}")]
+    DictionaryMismatch { expected: u64, found: u64 },
+
     #[error("Compaction error: {0}")]
     Compaction(String),
 
     #[error("Delta log overflow (>{0} entries)")]
     DeltaLogOverflow(usize),
+
+    #[error("Database {path:?} is locked by another writer{}", owner_pid.map(|p| format!(" (pid {})", p)).unwrap_or_default())]
+    Locked { path: std::path::PathBuf, owner_pid: Option<u32> },
+
+    #[error("Database is opened read-only: {0} is not allowed")]
+    ReadOnly(&'static str),
 }
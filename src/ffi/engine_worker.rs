@@ -364,7 +364,7 @@ impl Drop for EngineHandle {
 
 /// Worker loop - processes commands sequentially
 fn worker_loop(mut engine: RustGraphEngine, command_rx: Receiver<Command>) {
-    eprintln!("[EngineWorker] Started");
+    tracing::info!("EngineWorker started");
 
     while let Ok(command) = command_rx.recv() {
         match command {
@@ -462,14 +462,16 @@ fn worker_loop(mut engine: RustGraphEngine, command_rx: Receiver<Command>) {
                 let _ = response_tx.send(engine.compact());
             }
             Command::Shutdown => {
-                eprintln!("[EngineWorker] Shutting down, flushing...");
-                let _ = engine.flush();
+                tracing::info!("EngineWorker shutting down, flushing");
+                if let Err(e) = engine.flush() {
+                    tracing::error!("EngineWorker shutdown flush failed: {}", e);
+                }
                 break;
             }
         }
     }
 
-    eprintln!("[EngineWorker] Stopped");
+    tracing::info!("EngineWorker stopped");
 }
 
 /// Execute a Datalog query with explain support
@@ -6,19 +6,42 @@ use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use std::path::PathBuf;
 use std::env;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Once, RwLock};
 
-use crate::graph::{GraphStore, GraphEngine as RustGraphEngine, compute_node_id, string_id_to_u128};
+use crate::graph::{GraphStore, GraphEngine as RustGraphEngine, EndpointRule, ExportFilter, GraphPath, PathQuery, SubgraphQuery, compute_node_id, string_id_to_u128};
 use crate::storage::{NodeRecord, EdgeRecord, AttrQuery};
 use crate::datalog::{Evaluator, parse_program, parse_atom, Rule};
+use crate::error::GraphError;
 
-// Debug logging macro - enabled via NAVI_DEBUG=1
-macro_rules! debug_log {
-    ($($arg:tt)*) => {
-        if env::var("NAVI_DEBUG").is_ok() {
-            eprintln!("[RUST DEBUG] {}", format!($($arg)*));
-        }
-    };
+/// Fail a write call on a read-only graph
+///
+/// GraphStore write methods on a read-only engine are silently skipped,
+/// so JS has to get an exception instead of a lost write.
+fn ensure_writable(engine: &RustGraphEngine, operation: &'static str) -> Result<()> {
+    if engine.is_read_only() {
+        return Err(Error::from_reason(GraphError::ReadOnly(operation).to_string()));
+    }
+    Ok(())
+}
+
+/// Install stderr tracing subscriber once per process
+///
+/// Filter comes from RUST_LOG; NAVI_DEBUG=1 enables debug level.
+/// Does nothing if the host already installed a global subscriber.
+fn init_logging() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let filter = if env::var("NAVI_DEBUG").is_ok() {
+            tracing_subscriber::EnvFilter::new("debug")
+        } else {
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn"))
+        };
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .try_init();
+    });
 }
 
 /// JavaScript representation of NodeRecord
@@ -61,6 +84,35 @@ pub struct JsEdgeRecord {
     pub metadata: Option<String>,
 }
 
+/// Path in the graph: edges[i] connects nodes[i] and nodes[i + 1]
+#[napi(object)]
+pub struct JsPath {
+    /// Node IDs as decimal strings
+    pub nodes: Vec<String>,
+    pub edges: Vec<JsEdgeRecord>,
+}
+
+/// Strongly connected component with a cycle
+#[napi(object)]
+pub struct JsCycleComponent {
+    /// Node IDs as decimal strings, sorted
+    pub nodes: Vec<String>,
+    /// Example cycle: cycle[0] -> ... -> cycle[0] (only with withCycles)
+    pub cycle: Option<Vec<String>>,
+}
+
+/// Limits for allPaths (defaults: maxLength 10, maxResults 1000, outgoing)
+#[napi(object)]
+pub struct JsPathQuery {
+    pub max_length: Option<u32>,
+    pub max_results: Option<u32>,
+    pub edge_types: Option<Vec<String>>,
+    /// Node types paths must not pass through. Supports wildcard: "http:*"
+    pub exclude_node_types: Option<Vec<String>>,
+    /// "outgoing" | "incoming" | "both"
+    pub direction: Option<String>,
+}
+
 /// JavaScript representation of AttrQuery
 #[napi(object)]
 pub struct JsAttrQuery {
@@ -72,6 +124,17 @@ pub struct JsAttrQuery {
     pub name: Option<String>,
 }
 
+/// JavaScript representation of EndpointRule
+#[napi(object)]
+pub struct JsEndpointRule {
+    /// Node type pattern. Supports wildcard: "grpc:*"
+    pub node_type: String,
+    /// Required value of `exported` flag (any if omitted)
+    pub exported: Option<bool>,
+    /// Metadata conditions as JSON object string (e.g., '{"object":"redis"}')
+    pub metadata: Option<String>,
+}
+
 /// Query result with cursor
 #[napi(object)]
 pub struct JsQueryResult {
@@ -112,7 +175,8 @@ impl GraphEngine {
     /// If DB exists - opens it, otherwise creates new
     #[napi(constructor)]
     pub fn new(path: String) -> Result<Self> {
-        debug_log!("NAPI GraphEngine::new() called with path: {}", path);
+        init_logging();
+        tracing::debug!("NAPI GraphEngine::new() called with path: {}", path);
         let path_buf = PathBuf::from(&path);
 
         // Check if DB exists (nodes.bin or edges.bin)
@@ -120,18 +184,18 @@ impl GraphEngine {
         let edges_exists = path_buf.join("edges.bin").exists();
 
         let engine = if nodes_exists || edges_exists {
-            debug_log!("  Existing DB found, opening...");
+            tracing::debug!("  Existing DB found, opening...");
             // Open existing DB
             RustGraphEngine::open(path_buf)
                 .map_err(|e| Error::from_reason(format!("Failed to open graph: {}", e)))?
         } else {
-            debug_log!("  No existing DB, creating new...");
+            tracing::debug!("  No existing DB, creating new...");
             // Create new DB
             RustGraphEngine::create(path_buf)
                 .map_err(|e| Error::from_reason(format!("Failed to create graph: {}", e)))?
         };
 
-        debug_log!("  GraphEngine created successfully (thread-safe)");
+        tracing::debug!("  GraphEngine created successfully (thread-safe)");
         Ok(Self {
             engine: Arc::new(RwLock::new(engine)),
             datalog_rules: Arc::new(RwLock::new(Vec::new()))
@@ -141,6 +205,7 @@ impl GraphEngine {
     /// Open existing graph
     #[napi(factory)]
     pub fn open(path: String) -> Result<Self> {
+        init_logging();
         let engine = RustGraphEngine::open(PathBuf::from(path))
             .map_err(|e| Error::from_reason(format!("Failed to open graph: {}", e)))?;
 
@@ -150,6 +215,25 @@ impl GraphEngine {
         })
     }
 
+    /// Open existing graph for reading only (no writer lock, flush is an error)
+    #[napi(factory)]
+    pub fn open_read_only(path: String) -> Result<Self> {
+        init_logging();
+        let engine = RustGraphEngine::open_read_only(PathBuf::from(path))
+            .map_err(|e| Error::from_reason(format!("Failed to open graph: {}", e)))?;
+
+        Ok(Self {
+            engine: Arc::new(RwLock::new(engine)),
+            datalog_rules: Arc::new(RwLock::new(Vec::new()))
+        })
+    }
+
+    /// Is this graph opened read-only
+    #[napi]
+    pub fn is_read_only(&self) -> bool {
+        self.engine.read().unwrap().is_read_only()
+    }
+
     /// Add nodes to graph
     /// NOTE: Uses &self (not &mut self) to allow concurrent calls from JS.
     /// Thread safety is provided by internal Arc<RwLock<>>.
@@ -171,7 +255,9 @@ impl GraphEngine {
             }
         }).collect();
 
-        self.engine.write().unwrap().add_nodes(rust_nodes);
+        let mut engine = self.engine.write().unwrap();
+        ensure_writable(&engine, "addNodes")?;
+        engine.add_nodes(rust_nodes);
         Ok(())
     }
 
@@ -181,7 +267,7 @@ impl GraphEngine {
     #[napi]
     pub fn add_edges(&self, edges: Vec<JsEdgeRecord>, skip_validation: Option<bool>) -> Result<()> {
         let rust_edges: Vec<EdgeRecord> = edges.into_iter().map(|e| {
-            debug_log!("add_edges: received edge_type={:?}, metadata={:?}", e.edge_type, e.metadata);
+            tracing::debug!("add_edges: received edge_type={:?}, metadata={:?}", e.edge_type, e.metadata);
             EdgeRecord {
                 src: js_bigint_to_u128(&e.src),
                 dst: js_bigint_to_u128(&e.dst),
@@ -192,24 +278,32 @@ impl GraphEngine {
             }
         }).collect();
 
-        self.engine.write().unwrap().add_edges(rust_edges, skip_validation.unwrap_or(false));
+        let mut engine = self.engine.write().unwrap();
+        ensure_writable(&engine, "addEdges")?;
+        engine.add_edges(rust_edges, skip_validation.unwrap_or(false));
         Ok(())
     }
 
     /// Delete node
     #[napi]
-    pub fn delete_node(&self, id: String) {
-        self.engine.write().unwrap().delete_node(parse_string_id(&id));
+    pub fn delete_node(&self, id: String) -> Result<()> {
+        let mut engine = self.engine.write().unwrap();
+        ensure_writable(&engine, "deleteNode")?;
+        engine.delete_node(parse_string_id(&id));
+        Ok(())
     }
 
     /// Delete edge
     #[napi]
-    pub fn delete_edge(&self, src: String, dst: String, edge_type: String) {
-        self.engine.write().unwrap().delete_edge(
+    pub fn delete_edge(&self, src: String, dst: String, edge_type: String) -> Result<()> {
+        let mut engine = self.engine.write().unwrap();
+        ensure_writable(&engine, "deleteEdge")?;
+        engine.delete_edge(
             parse_string_id(&src),
             parse_string_id(&dst),
             &edge_type
         );
+        Ok(())
     }
 
     /// Get node by ID
@@ -298,6 +392,63 @@ impl GraphEngine {
             .collect()
     }
 
+    /// Shortest path src -> dst (null if unreachable)
+    /// direction: "outgoing" (default) | "incoming" | "both"
+    #[napi]
+    pub fn shortest_path(&self, src: String, dst: String, edge_types: Option<Vec<String>>, direction: Option<String>) -> Result<Option<JsPath>> {
+        let direction = direction.as_deref().map(str::parse).transpose()
+            .map_err(|e| Error::from_reason(format!("{}", e)))?
+            .unwrap_or_default();
+        let edge_types = edge_types.unwrap_or_default();
+        let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+
+        let path = self.engine.read().unwrap()
+            .shortest_path(parse_string_id(&src), parse_string_id(&dst), &edge_types_refs, direction);
+        Ok(path.map(path_to_js))
+    }
+
+    /// All simple paths from any source to any target, bounded by query limits
+    #[napi]
+    pub fn all_paths(&self, source_ids: Vec<String>, target_ids: Vec<String>, query: Option<JsPathQuery>) -> Result<Vec<JsPath>> {
+        let rust_query = match query {
+            None => PathQuery::default(),
+            Some(q) => {
+                let defaults = PathQuery::default();
+                let direction = q.direction.as_deref().map(str::parse).transpose()
+                    .map_err(|e| Error::from_reason(format!("{}", e)))?
+                    .unwrap_or_default();
+                PathQuery::new(
+                    q.max_length.map_or(defaults.max_length, |n| n as usize),
+                    q.max_results.map_or(defaults.max_results, |n| n as usize),
+                )
+                    .edge_types(q.edge_types.unwrap_or_default())
+                    .exclude_node_types(q.exclude_node_types.unwrap_or_default())
+                    .direction(direction)
+            }
+        };
+        let sources: Vec<u128> = source_ids.iter().map(|s| parse_string_id(s)).collect();
+        let targets: Vec<u128> = target_ids.iter().map(|s| parse_string_id(s)).collect();
+
+        let paths = self.engine.read().unwrap().all_paths(&sources, &targets, &rust_query);
+        Ok(paths.into_iter().map(path_to_js).collect())
+    }
+
+    /// Strongly connected components with cycles (circular imports, mutual recursion)
+    #[napi]
+    pub fn cycle_components(&self, edge_types: Option<Vec<String>>, with_cycles: Option<bool>) -> Vec<JsCycleComponent> {
+        let edge_types = edge_types.unwrap_or_default();
+        let edge_types_refs: Vec<&str> = edge_types.iter().map(|s| s.as_str()).collect();
+
+        self.engine.read().unwrap()
+            .cycle_components(&edge_types_refs, with_cycles.unwrap_or(false))
+            .into_iter()
+            .map(|c| JsCycleComponent {
+                nodes: c.nodes.iter().map(|id| id.to_string()).collect(),
+                cycle: c.cycle.map(|cycle| cycle.iter().map(|id| id.to_string()).collect()),
+            })
+            .collect()
+    }
+
     /// Depth-first search (DFS)
     /// TODO: Implement DFS in GraphEngine
     #[napi]
@@ -317,7 +468,7 @@ impl GraphEngine {
     #[napi]
     pub fn flush(&self) -> Result<()> {
         let mut engine = self.engine.write().unwrap();
-        eprintln!("[RUST FLUSH] Explicit flush requested, {} ops pending", engine.ops_since_flush);
+        tracing::debug!("Explicit flush requested, {} ops pending", engine.ops_since_flush);
         engine.flush()
             .map_err(|e| Error::from_reason(format!("Flush failed: {}", e)))
     }
@@ -326,7 +477,11 @@ impl GraphEngine {
     #[napi]
     pub fn close(&self) -> Result<()> {
         let mut engine = self.engine.write().unwrap();
-        eprintln!("[RUST CLOSE] Closing database, flushing {} ops", engine.ops_since_flush);
+        // Nothing to flush on a read-only database (flush would return GraphError::ReadOnly)
+        if engine.is_read_only() {
+            return Ok(());
+        }
+        tracing::debug!("Closing database, flushing {} ops", engine.ops_since_flush);
         engine.flush()
             .map_err(|e| Error::from_reason(format!("Close flush failed: {}", e)))
     }
@@ -338,6 +493,89 @@ impl GraphEngine {
             .map_err(|e| Error::from_reason(format!("Compaction failed: {}", e)))
     }
 
+    /// Write a consistent snapshot of the graph into a new directory
+    #[napi]
+    pub fn checkpoint(&self, dest: String) -> Result<()> {
+        self.engine.read().unwrap().checkpoint(PathBuf::from(dest))
+            .map_err(|e| Error::from_reason(format!("Checkpoint failed: {}", e)))
+    }
+
+    /// Replace the graph with a checkpoint (unflushed changes are discarded)
+    #[napi]
+    pub fn restore(&self, src: String) -> Result<()> {
+        self.engine.write().unwrap().restore(PathBuf::from(src))
+            .map_err(|e| Error::from_reason(format!("Restore failed: {}", e)))
+    }
+
+    /// On-disk segment format version
+    #[napi]
+    pub fn format_version(&self) -> u32 {
+        self.engine.read().unwrap().format_version() as u32
+    }
+
+    /// Rewrite segments of an older format; returns true if anything was rewritten
+    #[napi]
+    pub fn upgrade_format(&self) -> Result<bool> {
+        self.engine.write().unwrap().upgrade_format()
+            .map_err(|e| Error::from_reason(format!("Format upgrade failed: {}", e)))
+    }
+
+    /// Set segment compression ("none" | "lz4") and rewrite segments with it
+    #[napi]
+    pub fn set_compression(&self, compression: String) -> Result<()> {
+        let compression = compression.parse()
+            .map_err(|e| Error::from_reason(format!("{}", e)))?;
+        self.engine.write().unwrap().set_compression(compression)
+            .map_err(|e| Error::from_reason(format!("Set compression failed: {}", e)))
+    }
+
+    /// Export the graph to a JSON Lines file; returns [nodes, edges] written
+    #[napi]
+    pub fn export_jsonl(&self, path: String, node_type: Option<String>, include_deleted: Option<bool>) -> Result<Vec<u32>> {
+        let file = std::fs::File::create(&path)
+            .map_err(|e| Error::from_reason(format!("Export failed: {}", e)))?;
+        let filter = ExportFilter {
+            node_type,
+            include_deleted: include_deleted.unwrap_or(false),
+            ..ExportFilter::default()
+        };
+        let stats = self.engine.read().unwrap().export_jsonl(std::io::BufWriter::new(file), &filter)
+            .map_err(|e| Error::from_reason(format!("Export failed: {}", e)))?;
+        Ok(vec![stats.nodes as u32, stats.edges as u32])
+    }
+
+    /// Import a JSON Lines dump written by exportJsonl; returns [nodes, edges] read
+    #[napi]
+    pub fn import_jsonl(&self, path: String) -> Result<Vec<u32>> {
+        let file = std::fs::File::open(&path)
+            .map_err(|e| Error::from_reason(format!("Import failed: {}", e)))?;
+        let stats = self.engine.write().unwrap().import_jsonl(std::io::BufReader::new(file))
+            .map_err(|e| Error::from_reason(format!("Import failed: {}", e)))?;
+        Ok(vec![stats.nodes as u32, stats.edges as u32])
+    }
+
+    /// Render the subgraph around start_ids as GraphML or DOT ("graphml" | "dot")
+    #[napi]
+    pub fn export_subgraph(&self, start_ids: Vec<String>, max_depth: u32, edge_types: Vec<String>, backward: Option<bool>, format: String) -> Result<String> {
+        let format = format.parse()
+            .map_err(|e| Error::from_reason(format!("{}", e)))?;
+        let rust_ids: Vec<u128> = start_ids.iter().map(|s| parse_string_id(s)).collect();
+        let query = SubgraphQuery::new(rust_ids, max_depth as usize)
+            .edge_types(edge_types)
+            .backward(backward.unwrap_or(false));
+        let mut out = Vec::new();
+        self.engine.read().unwrap().export_subgraph(&mut out, &query, format)
+            .map_err(|e| Error::from_reason(format!("Export subgraph failed: {}", e)))?;
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+
+    /// Verify segment checksums and offsets (throws on corruption)
+    #[napi]
+    pub fn verify(&self) -> Result<()> {
+        self.engine.read().unwrap().verify()
+            .map_err(|e| Error::from_reason(format!("Verification failed: {}", e)))
+    }
+
     /// Node count
     #[napi]
     pub fn node_count(&self) -> u32 {
@@ -356,6 +594,42 @@ impl GraphEngine {
         self.engine.read().unwrap().is_endpoint(parse_string_id(&id))
     }
 
+    /// Get registered endpoint rules
+    #[napi]
+    pub fn get_endpoint_rules(&self) -> Vec<JsEndpointRule> {
+        self.engine.read().unwrap().endpoint_rules().iter().map(|rule| {
+            JsEndpointRule {
+                node_type: rule.node_type.clone(),
+                exported: rule.exported,
+                metadata: if rule.metadata.is_empty() {
+                    None
+                } else {
+                    serde_json::to_string(&rule.metadata).ok()
+                },
+            }
+        }).collect()
+    }
+
+    /// Replace endpoint rules (persisted in metadata.json)
+    #[napi]
+    pub fn set_endpoint_rules(&self, rules: Vec<JsEndpointRule>) -> Result<()> {
+        let rust_rules = rules.into_iter().map(|r| {
+            let metadata = match r.metadata {
+                Some(json) => serde_json::from_str(&json)
+                    .map_err(|e| Error::from_reason(format!("Invalid endpoint rule metadata: {}", e)))?,
+                None => std::collections::HashMap::new(),
+            };
+            Ok(EndpointRule {
+                node_type: r.node_type,
+                exported: r.exported,
+                metadata,
+            })
+        }).collect::<Result<Vec<EndpointRule>>>()?;
+
+        self.engine.write().unwrap().set_endpoint_rules(rust_rules)
+            .map_err(|e| Error::from_reason(format!("Failed to save endpoint rules: {}", e)))
+    }
+
     /// Get outgoing edges from node
     #[napi]
     pub fn get_outgoing_edges(&self, id: String, edge_types: Option<Vec<String>>) -> Vec<JsEdgeRecord> {
@@ -374,7 +648,7 @@ impl GraphEngine {
         );
 
         edges.into_iter().map(|e| {
-            debug_log!("get_outgoing_edges: returning edge_type={:?}, metadata={:?}", e.edge_type, e.metadata);
+            tracing::debug!("get_outgoing_edges: returning edge_type={:?}, metadata={:?}", e.edge_type, e.metadata);
             JsEdgeRecord {
                 src: BigInt::from(e.src),
                 dst: BigInt::from(e.dst),
@@ -475,7 +749,7 @@ impl GraphEngine {
         let mut rules = self.datalog_rules.write().unwrap();
         rules.extend(program.rules().iter().cloned());
 
-        debug_log!("datalog_load_rules: loaded {} rules, total {}", count, rules.len());
+        tracing::debug!("datalog_load_rules: loaded {} rules, total {}", count, rules.len());
         Ok(count as u32)
     }
 
@@ -508,7 +782,7 @@ impl GraphEngine {
 
         let results = evaluator.query(&atom);
 
-        debug_log!("datalog_query: {} results for '{}'", results.len(), query);
+        tracing::debug!("datalog_query: {} results for '{}'", results.len(), query);
 
         Ok(results
             .into_iter()
@@ -553,7 +827,7 @@ impl GraphEngine {
 
         let results = evaluator.query(&violation_query);
 
-        debug_log!("check_guarantee: {} violations", results.len());
+        tracing::debug!("check_guarantee: {} violations", results.len());
 
         Ok(results
             .into_iter()
@@ -581,7 +855,7 @@ impl GraphEngine {
     pub fn query_next_node(&self, query: JsAttrQuery, cursor: Option<u32>) -> JsQueryResult {
         // Only log on first call (cursor=None) to avoid flooding stderr
         if cursor.is_none() {
-            debug_log!("NAPI query_next_node: node_type={:?}", query.node_type);
+            tracing::debug!("NAPI query_next_node: node_type={:?}", query.node_type);
         }
 
         // Convert JS query to Rust AttrQuery
@@ -700,6 +974,20 @@ fn js_bigint_to_u128(bigint: &BigInt) -> u128 {
     (high << 64) | low
 }
 
+/// Convert GraphPath to JS object (ids as decimal strings)
+fn path_to_js(path: GraphPath) -> JsPath {
+    JsPath {
+        nodes: path.nodes.iter().map(|id| id.to_string()).collect(),
+        edges: path.edges.into_iter().map(|e| JsEdgeRecord {
+            src: BigInt::from(e.src),
+            dst: BigInt::from(e.dst),
+            edge_type: e.edge_type,
+            version: e.version,
+            metadata: e.metadata,
+        }).collect(),
+    }
+}
+
 /// Parse string ID to u128
 ///
 /// ID can be:
@@ -0,0 +1,492 @@
+//! Ранжирование и структура графа: PageRank, степени, betweenness,
+//! слабо связные компоненты и ноды-сироты
+//!
+//! Алгоритмы работают на компактной копии графа (индексы вместо u128 id),
+//! собранной из id нод segment + delta и adjacency; тяжёлые части параллелятся через rayon.
+//! Рёбра на несуществующие ноды не учитываются.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use rayon::prelude::*;
+use serde::Serialize;
+use crate::error::{GraphError, Result};
+use super::{GraphEngine, GraphStore};
+use super::traversal::type_matches;
+
+/// Граф в индексах: `ids[i]` - нода, `out[i]` / `inc[i]` - её соседи
+struct IndexedGraph {
+    ids: Vec<u128>,
+    node_types: Vec<Option<String>>,
+    out: Vec<Vec<usize>>,
+    inc: Vec<Vec<usize>>,
+}
+
+impl IndexedGraph {
+    fn build(engine: &GraphEngine, edge_types: &[String]) -> Self {
+        let (ids, node_types): (Vec<u128>, Vec<Option<String>>) = engine.live_nodes().iter()
+            .map(|n| (n.id(), n.node_type().map(str::to_string)))
+            .unzip();
+        let index: HashMap<u128, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let edge_types: Vec<&str> = edge_types.iter().map(String::as_str).collect();
+
+        // Соседи из adjacency: рёбра и ноды не копируются целиком
+        let out: Vec<Vec<usize>> = ids.iter()
+            .map(|&id| {
+                let mut targets: Vec<usize> = engine.neighbors(id, &edge_types).into_iter()
+                    .filter_map(|dst| index.get(&dst).copied())
+                    .collect();
+                targets.sort_unstable();
+                targets
+            })
+            .collect();
+        let mut inc = vec![Vec::new(); ids.len()];
+        for (src, targets) in out.iter().enumerate() {
+            for &dst in targets {
+                inc[dst].push(src);
+            }
+        }
+
+        Self { ids, node_types, out, inc }
+    }
+
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn scores(&self, values: Vec<f64>) -> HashMap<u128, f64> {
+        self.ids.iter().copied().zip(values).collect()
+    }
+}
+
+/// Параметры PageRank
+#[derive(Debug, Clone)]
+pub struct PageRankConfig {
+    pub damping: f64,
+    pub max_iterations: usize,
+    /// Остановка, когда сумма изменений рангов за итерацию меньше tolerance
+    pub tolerance: f64,
+    /// Типы рёбер (пусто - все)
+    pub edge_types: Vec<String>,
+}
+
+impl Default for PageRankConfig {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            max_iterations: 100,
+            tolerance: 1e-6,
+            edge_types: Vec::new(),
+        }
+    }
+}
+
+impl PageRankConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn edge_types(mut self, types: Vec<String>) -> Self {
+        self.edge_types = types;
+        self
+    }
+}
+
+/// Параметры betweenness centrality
+#[derive(Debug, Clone, Default)]
+pub struct BetweennessConfig {
+    /// Сколько нод-источников взять (0 - все, точный результат)
+    pub samples: usize,
+    /// Seed выбора источников: одинаковый seed - одинаковый результат
+    pub seed: u64,
+    /// Типы рёбер (пусто - все)
+    pub edge_types: Vec<String>,
+}
+
+impl BetweennessConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn edge_types(mut self, types: Vec<String>) -> Self {
+        self.edge_types = types;
+        self
+    }
+}
+
+/// Входящая и исходящая степень ноды
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NodeDegree {
+    pub in_degree: usize,
+    pub out_degree: usize,
+}
+
+/// Распределения степеней: степень -> сколько нод её имеют
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DegreeDistribution {
+    pub in_degree: BTreeMap<usize, usize>,
+    pub out_degree: BTreeMap<usize, usize>,
+}
+
+/// Какие ноды считать сиротами
+#[derive(Debug, Clone, Default)]
+pub struct OrphanQuery {
+    /// Входящие рёбра каких типов учитывать (пусто - все)
+    pub edge_types: Vec<String>,
+    /// Проверять только ноды этих типов (пусто - все), "http:*" - wildcard
+    pub node_types: Vec<String>,
+    /// Точки входа: ноды этих типов сиротами не считаются
+    pub exclude_types: Vec<String>,
+}
+
+impl OrphanQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn edge_types(mut self, types: Vec<String>) -> Self {
+        self.edge_types = types;
+        self
+    }
+
+    pub fn node_types(mut self, types: Vec<String>) -> Self {
+        self.node_types = types;
+        self
+    }
+
+    pub fn exclude_types(mut self, types: Vec<String>) -> Self {
+        self.exclude_types = types;
+        self
+    }
+}
+
+/// Корень компоненты в union-find (со сжатием пути)
+fn find_root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+/// Вклад одного источника в betweenness (Brandes)
+fn accumulate_betweenness(graph: &IndexedGraph, source: usize, centrality: &mut [f64]) {
+    let n = graph.len();
+    let mut order = Vec::new();
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut paths = vec![0.0f64; n];
+    let mut distance = vec![usize::MAX; n];
+    paths[source] = 1.0;
+    distance[source] = 0;
+
+    let mut queue = VecDeque::from([source]);
+    while let Some(v) = queue.pop_front() {
+        order.push(v);
+        for &w in &graph.out[v] {
+            if distance[w] == usize::MAX {
+                distance[w] = distance[v] + 1;
+                queue.push_back(w);
+            }
+            if distance[w] == distance[v] + 1 {
+                paths[w] += paths[v];
+                predecessors[w].push(v);
+            }
+        }
+    }
+
+    let mut dependency = vec![0.0f64; n];
+    for &w in order.iter().rev() {
+        for &v in &predecessors[w] {
+            dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+        }
+        if w != source {
+            centrality[w] += dependency[w];
+        }
+    }
+}
+
+/// Выбрать `k` разных индексов из `0..n` (частичный Fisher-Yates на splitmix64)
+fn sample_indices(n: usize, k: usize, seed: u64) -> Vec<usize> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    let mut indices: Vec<usize> = (0..n).collect();
+    for i in 0..k {
+        let j = i + (next() % (n - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices.truncate(k);
+    indices
+}
+
+impl GraphEngine {
+    /// PageRank по рёбрам `config.edge_types`; сумма рангов = 1
+    ///
+    /// Ранг нод без исходящих рёбер равномерно раздаётся всем нодам.
+    pub fn pagerank(&self, config: &PageRankConfig) -> HashMap<u128, f64> {
+        let _span = tracing::info_span!("pagerank").entered();
+        let graph = IndexedGraph::build(self, &config.edge_types);
+        let n = graph.len();
+        if n == 0 {
+            return HashMap::new();
+        }
+
+        let d = config.damping;
+        let mut rank = vec![1.0 / n as f64; n];
+        for iteration in 0..config.max_iterations {
+            let dangling: f64 = (0..n).into_par_iter()
+                .filter(|&i| graph.out[i].is_empty())
+                .map(|i| rank[i])
+                .sum();
+            let base = (1.0 - d) / n as f64 + d * dangling / n as f64;
+            let next: Vec<f64> = (0..n).into_par_iter()
+                .map(|v| base + d * graph.inc[v].iter().map(|&u| rank[u] / graph.out[u].len() as f64).sum::<f64>())
+                .collect();
+            let change: f64 = next.par_iter().zip(rank.par_iter()).map(|(a, b)| (a - b).abs()).sum();
+            rank = next;
+            if change < config.tolerance {
+                tracing::debug!(iterations = iteration + 1, "pagerank converged");
+                break;
+            }
+        }
+        graph.scores(rank)
+    }
+
+    /// Входящая/исходящая степень каждой живой ноды по рёбрам `edge_types`
+    pub fn node_degrees(&self, edge_types: &[String]) -> HashMap<u128, NodeDegree> {
+        let graph = IndexedGraph::build(self, edge_types);
+        (0..graph.len())
+            .map(|i| (graph.ids[i], NodeDegree { in_degree: graph.inc[i].len(), out_degree: graph.out[i].len() }))
+            .collect()
+    }
+
+    /// Гистограммы входящих и исходящих степеней
+    pub fn degree_distribution(&self, edge_types: &[String]) -> DegreeDistribution {
+        let mut distribution = DegreeDistribution::default();
+        for degree in self.node_degrees(edge_types).into_values() {
+            *distribution.in_degree.entry(degree.in_degree).or_default() += 1;
+            *distribution.out_degree.entry(degree.out_degree).or_default() += 1;
+        }
+        distribution
+    }
+
+    /// Betweenness centrality (Brandes, направленные рёбра без весов)
+    ///
+    /// С `samples` > 0 считается по случайной выборке источников и
+    /// масштабируется на n / samples - оценка для больших графов.
+    pub fn betweenness(&self, config: &BetweennessConfig) -> HashMap<u128, f64> {
+        let _span = tracing::info_span!("betweenness", samples = config.samples).entered();
+        let graph = IndexedGraph::build(self, &config.edge_types);
+        let n = graph.len();
+        let sources = if config.samples == 0 || config.samples >= n {
+            (0..n).collect()
+        } else {
+            sample_indices(n, config.samples, config.seed)
+        };
+        let scale = if sources.is_empty() { 0.0 } else { n as f64 / sources.len() as f64 };
+
+        let centrality = sources.par_iter()
+            .fold(|| vec![0.0f64; n], |mut acc, &source| {
+                accumulate_betweenness(&graph, source, &mut acc);
+                acc
+            })
+            .reduce(|| vec![0.0f64; n], |mut a, b| {
+                a.iter_mut().zip(b).for_each(|(x, y)| *x += y);
+                a
+            });
+        graph.scores(centrality.into_iter().map(|c| c * scale).collect())
+    }
+
+    /// Слабо связные компоненты (направление рёбер не учитывается)
+    ///
+    /// Каждая живая нода попадает ровно в одну компоненту, одиночные ноды -
+    /// в компоненты размера 1. Компоненты отсортированы от больших к меньшим,
+    /// ноды внутри - по id.
+    pub fn weakly_connected_components(&self, edge_types: &[String]) -> Vec<Vec<u128>> {
+        let graph = IndexedGraph::build(self, edge_types);
+        let mut parent: Vec<usize> = (0..graph.len()).collect();
+        for (src, targets) in graph.out.iter().enumerate() {
+            for &dst in targets {
+                let (a, b) = (find_root(&mut parent, src), find_root(&mut parent, dst));
+                if a != b {
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<u128>> = HashMap::new();
+        for i in 0..graph.len() {
+            let root = find_root(&mut parent, i);
+            groups.entry(root).or_default().push(graph.ids[i]);
+        }
+        // ids в snapshot отсортированы, значит и ноды в каждой группе
+        let mut components: Vec<Vec<u128>> = groups.into_values().collect();
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        components
+    }
+
+    /// Ноды без входящих рёбер `query.edge_types` (петли не считаются)
+    ///
+    /// Точки входа (`query.exclude_types`) исключаются. Результат отсортирован по id.
+    pub fn orphans(&self, query: &OrphanQuery) -> Vec<u128> {
+        let graph = IndexedGraph::build(self, &query.edge_types);
+        let matches_any = |patterns: &[String], node_type: &Option<String>| {
+            node_type.as_deref().is_some_and(|t| patterns.iter().any(|p| type_matches(p, t)))
+        };
+
+        (0..graph.len())
+            .filter(|&i| graph.inc[i].iter().all(|&src| src == i))
+            .filter(|&i| query.node_types.is_empty() || matches_any(&query.node_types, &graph.node_types[i]))
+            .filter(|&i| !matches_any(&query.exclude_types, &graph.node_types[i]))
+            .map(|i| graph.ids[i])
+            .collect()
+    }
+
+    /// Записать значения в metadata нод под ключом `key`, остальные поля сохраняются
+    ///
+    /// Ноды, которых нет или чья metadata не JSON-объект, пропускаются.
+    /// Возвращает число обновлённых нод; на read-only базе — `GraphError::ReadOnly`.
+    pub fn write_node_metrics<T: Serialize>(&mut self, key: &str, values: &HashMap<u128, T>) -> Result<usize> {
+        if self.is_read_only() {
+            return Err(GraphError::ReadOnly("write_node_metrics"));
+        }
+        let ids: HashSet<u128> = values.keys().copied().collect();
+        let mut updated = Vec::new();
+        for mut node in self.get_nodes(&ids) {
+            let value = &values[&node.id];
+            let mut metadata = match node.metadata.as_deref() {
+                None | Some("") => serde_json::Map::new(),
+                Some(json) => match serde_json::from_str(json) {
+                    Ok(serde_json::Value::Object(map)) => map,
+                    _ => continue,
+                },
+            };
+            metadata.insert(key.to_string(), serde_json::to_value(value)?);
+            node.metadata = Some(serde_json::Value::Object(metadata).to_string());
+            updated.push(node);
+        }
+
+        let count = updated.len();
+        self.add_nodes(updated);
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::NodeRecord;
+    use crate::test_support::{edge, node, test_engine};
+    use tempfile::TempDir;
+
+    /// MODULE-ноды `m1..=m<nodes>` (у m1 metadata `owner`) и IMPORTS-рёбра
+    fn module_graph(dir: &TempDir, nodes: u128, edges: &[(u128, u128)]) -> GraphEngine {
+        test_engine(
+            dir,
+            (1..=nodes).map(|id| NodeRecord {
+                metadata: (id == 1).then(|| r#"{"owner":"core"}"#.to_string()),
+                ..node(id, "MODULE", &format!("m{}", id))
+            }).collect(),
+            edges.iter().map(|&(src, dst)| edge(src, dst, "IMPORTS")).collect(),
+        )
+    }
+
+    #[test]
+    fn test_pagerank_and_degrees() {
+        let dir = TempDir::new().unwrap();
+        // Все импортируют 1, 1 импортирует 2
+        let engine = module_graph(&dir, 5, &[(2, 1), (3, 1), (4, 1), (5, 1), (1, 2)]);
+
+        let ranks = engine.pagerank(&PageRankConfig::new());
+        assert_eq!(ranks.len(), 5);
+        assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-6);
+        assert!(ranks[&1] > ranks[&2] && ranks[&2] > ranks[&3]);
+        assert!((ranks[&3] - ranks[&5]).abs() < 1e-12);
+
+        let degrees = engine.node_degrees(&[]);
+        assert_eq!(degrees[&1], NodeDegree { in_degree: 4, out_degree: 1 });
+        let distribution = engine.degree_distribution(&["IMPORTS".to_string()]);
+        assert_eq!(distribution.in_degree, BTreeMap::from([(0, 3), (1, 1), (4, 1)]));
+        assert_eq!(distribution.out_degree, BTreeMap::from([(1, 5)]));
+        assert!(engine.degree_distribution(&["CALLS".to_string()]).in_degree == BTreeMap::from([(0, 5)]));
+    }
+
+    #[test]
+    fn test_weakly_connected_components_and_orphans() {
+        let dir = TempDir::new().unwrap();
+        // {1 -> 2 <- 3}, {4 -> 5}, 6 один, у 7 только петля
+        let mut engine = module_graph(&dir, 7, &[(1, 2), (3, 2), (4, 5), (7, 7)]);
+        engine.add_nodes(vec![NodeRecord {
+            node_type: Some("SERVICE".to_string()),
+            ..engine.get_node(3).unwrap()
+        }]);
+
+        let components = engine.weakly_connected_components(&[]);
+        assert_eq!(components, vec![vec![1, 2, 3], vec![4, 5], vec![6], vec![7]]);
+        assert_eq!(engine.weakly_connected_components(&["CALLS".to_string()]).len(), 7);
+
+        assert_eq!(engine.orphans(&OrphanQuery::new()), vec![1, 3, 4, 6, 7]);
+        let query = OrphanQuery::new()
+            .edge_types(vec!["IMPORTS".to_string()])
+            .exclude_types(vec!["SERVICE".to_string()]);
+        assert_eq!(engine.orphans(&query), vec![1, 4, 6, 7]);
+        let query = OrphanQuery::new().node_types(vec!["SERV*".to_string()]);
+        assert_eq!(engine.orphans(&query), vec![3]);
+    }
+
+    #[test]
+    fn test_betweenness_and_write_back() {
+        let dir = TempDir::new().unwrap();
+        // 1 -> 2 -> 3 -> 4 и 1 -> 5 -> 4: кратчайшие 1-3 через 2, 2-4 через 3, 1-4 через 5
+        let mut engine = module_graph(&dir, 5, &[(1, 2), (2, 3), (3, 4), (1, 5), (5, 4)]);
+
+        let exact = engine.betweenness(&BetweennessConfig::new());
+        assert_eq!(exact[&1], 0.0);
+        assert_eq!(exact[&2], 1.0);
+        assert_eq!(exact[&3], 1.0);
+        assert_eq!(exact[&4], 0.0);
+        assert_eq!(exact[&5], 1.0);
+
+        // Выборка детерминирована и масштабируется на n / samples
+        let sampled = engine.betweenness(&BetweennessConfig::new().samples(2).seed(7));
+        assert_eq!(sampled, engine.betweenness(&BetweennessConfig::new().samples(2).seed(7)));
+        assert!(sampled.values().all(|&c| (c / 2.5).fract() == 0.0));
+
+        let written = engine.write_node_metrics("betweenness", &exact).unwrap();
+        assert_eq!(written, 5);
+        let node = engine.get_node(1).unwrap();
+        let metadata: serde_json::Value = serde_json::from_str(node.metadata.as_deref().unwrap()).unwrap();
+        assert_eq!(metadata["owner"], "core");
+        assert_eq!(metadata["betweenness"], 0.0);
+        let node = engine.get_node(2).unwrap();
+        assert_eq!(node.metadata.as_deref(), Some(r#"{"betweenness":1.0}"#));
+    }
+}
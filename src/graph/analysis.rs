@@ -0,0 +1,283 @@
+//! Анализ кода поверх reachability
+//!
+//! - мёртвый код: точки входа - ноды нужных типов и имён, exported ноды и
+//!   endpoints (`is_endpoint`); всё, что из них достижимо, живое;
+//! - impact: кого затрагивает правка файлов (обратная reachability).
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use crate::storage::{AttrQuery, NodeRecord};
+use super::{GraphEngine, GraphStore};
+use super::engine::NodeView;
+use super::traversal::type_matches;
+
+/// Параметры поиска мёртвого кода
+#[derive(Debug, Clone)]
+pub struct DeadCodeQuery {
+    /// Типы точек входа, "http:*" - wildcard
+    pub entry_types: Vec<String>,
+    /// Имена точек входа (по умолчанию "main")
+    pub entry_names: Vec<String>,
+    /// Рёбра, по которым код считается используемым (пусто - все)
+    pub edge_types: Vec<String>,
+    /// Какие ноды проверять (по умолчанию FUNCTION, METHOD, CLASS)
+    pub target_types: Vec<String>,
+    /// Exported ноды - точки входа (по умолчанию да)
+    pub exported_are_entries: bool,
+    /// Ноды, подходящие под endpoint rules - точки входа (по умолчанию да)
+    pub endpoints_are_entries: bool,
+}
+
+impl DeadCodeQuery {
+    pub fn new(entry_types: Vec<String>, edge_types: Vec<String>) -> Self {
+        Self {
+            entry_types,
+            entry_names: vec!["main".to_string()],
+            edge_types,
+            target_types: vec!["FUNCTION".to_string(), "METHOD".to_string(), "CLASS".to_string()],
+            exported_are_entries: true,
+            endpoints_are_entries: true,
+        }
+    }
+
+    pub fn entry_names(mut self, names: Vec<String>) -> Self {
+        self.entry_names = names;
+        self
+    }
+
+    pub fn target_types(mut self, types: Vec<String>) -> Self {
+        self.target_types = types;
+        self
+    }
+
+    pub fn exported_are_entries(mut self, enabled: bool) -> Self {
+        self.exported_are_entries = enabled;
+        self
+    }
+
+    pub fn endpoints_are_entries(mut self, enabled: bool) -> Self {
+        self.endpoints_are_entries = enabled;
+        self
+    }
+}
+
+/// Мёртвый код, сгруппированный по файлам
+#[derive(Debug, Clone, Default)]
+pub struct DeadCodeReport {
+    /// Сколько точек входа найдено
+    pub entry_points: usize,
+    /// file -> недостижимые ноды (по id); ноды без файла - под ключом ""
+    pub files: BTreeMap<String, Vec<NodeRecord>>,
+}
+
+impl DeadCodeReport {
+    /// Общее число мёртвых нод
+    pub fn len(&self) -> usize {
+        self.files.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Подстроки пути, по которым файл считается тестом
+pub const TEST_FILE_PATTERNS: &[&str] = &[".test.", ".spec.", "/test/", "/tests/", "__tests__/", "_test."];
+
+/// Что затрагивает правка набора файлов
+#[derive(Debug, Clone, Default)]
+pub struct ImpactReport {
+    /// Ноды из изменённых файлов
+    pub changed: Vec<u128>,
+    /// Затронутые ноды (без `changed`) по типу, внутри - по id; без типа - "UNKNOWN"
+    pub by_type: BTreeMap<String, Vec<NodeRecord>>,
+    /// Файлы тестов (`TEST_FILE_PATTERNS`) с затронутыми нодами
+    pub test_files: Vec<String>,
+}
+
+impl ImpactReport {
+    /// Общее число затронутых нод
+    pub fn len(&self) -> usize {
+        self.by_type.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_type.is_empty()
+    }
+}
+
+fn matches_any(patterns: &[String], node_type: Option<&str>) -> bool {
+    node_type.is_some_and(|t| patterns.iter().any(|p| type_matches(p, t)))
+}
+
+impl GraphEngine {
+    /// Ноды `query.target_types`, недостижимые из точек входа по `query.edge_types`
+    pub fn dead_code(&self, query: &DeadCodeQuery) -> DeadCodeReport {
+        let _span = tracing::info_span!("dead_code").entered();
+        // Только колонки type/name/exported/metadata, записи собираются лишь для мёртвых нод
+        let nodes = self.live_nodes();
+
+        let is_entry = |node: &NodeView| {
+            matches_any(&query.entry_types, node.node_type())
+                || node.name().is_some_and(|name| query.entry_names.iter().any(|n| n == name))
+                || (query.exported_are_entries && node.exported())
+                || (query.endpoints_are_entries && self.endpoint_rules().iter()
+                    .any(|rule| rule.matches_parts(node.node_type(), node.exported(), node.metadata())))
+        };
+        let entries: Vec<u128> = nodes.iter().filter(|n| is_entry(n)).map(|n| n.id()).collect();
+
+        let edge_types: Vec<&str> = query.edge_types.iter().map(|s| s.as_str()).collect();
+        let reachable: HashSet<u128> = self.reachability(&entries, usize::MAX, &edge_types, false)
+            .into_iter()
+            .collect();
+
+        let dead: HashSet<u128> = nodes.iter()
+            .filter(|n| !reachable.contains(&n.id()) && matches_any(&query.target_types, n.node_type()))
+            .map(|n| n.id())
+            .collect();
+        let mut dead = self.get_nodes(&dead);
+        dead.sort_by_key(|n| n.id);
+
+        let mut report = DeadCodeReport { entry_points: entries.len(), files: BTreeMap::new() };
+        for node in dead {
+            report.files.entry(node.file.clone().unwrap_or_default()).or_default().push(node);
+        }
+        report
+    }
+
+    /// Кого затрагивает правка `files`: обратная reachability от их нод
+    ///
+    /// Идёт по `reverse_adjacency` (кто зовёт/импортирует/зависит) не глубже
+    /// `max_depth` по рёбрам `edge_types` (пусто - все).
+    pub fn impact(&self, files: &[String], edge_types: &[&str], max_depth: usize) -> ImpactReport {
+        let _span = tracing::info_span!("impact", files = files.len()).entered();
+        let mut changed: Vec<u128> = files.iter()
+            .flat_map(|file| self.find_by_attr(&AttrQuery { file: Some(file.clone()), ..AttrQuery::default() }))
+            .collect();
+        changed.sort_unstable();
+        changed.dedup();
+
+        let changed_set: HashSet<u128> = changed.iter().copied().collect();
+        let affected_ids: HashSet<u128> = self.reachability(&changed, max_depth, edge_types, true)
+            .into_iter()
+            .filter(|id| !changed_set.contains(id))
+            .collect();
+        let mut affected = self.get_nodes(&affected_ids);
+        affected.sort_by_key(|n| n.id);
+
+        let mut report = ImpactReport { changed, ..ImpactReport::default() };
+        let mut test_files = BTreeSet::new();
+        for node in affected {
+            if let Some(file) = node.file.as_ref().filter(|f| TEST_FILE_PATTERNS.iter().any(|p| f.contains(p))) {
+                test_files.insert(file.clone());
+            }
+            let node_type = node.node_type.clone().unwrap_or_else(|| "UNKNOWN".to_string());
+            report.by_type.entry(node_type).or_default().push(node);
+        }
+        report.test_files = test_files.into_iter().collect();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, edge};
+    use tempfile::TempDir;
+
+    fn node(id: u128, node_type: &str, name: &str, file: &str, exported: bool) -> NodeRecord {
+        NodeRecord { file: Some(file.to_string()), exported, ..test_support::node(id, node_type, name) }
+    }
+
+    #[test]
+    fn test_dead_code() {
+        let dir = TempDir::new().unwrap();
+        let mut engine = GraphEngine::create(dir.path().join("g.rfdb")).unwrap();
+        engine.add_nodes(vec![
+            node(1, "FUNCTION", "main", "main.js", false),
+            node(2, "FUNCTION", "helper", "util.js", false),
+            node(3, "FUNCTION", "unused", "util.js", false),
+            node(4, "FUNCTION", "api", "api.js", true),
+            node(5, "METHOD", "Cache.get", "cache.js", false),
+            node(6, "http:route", "GET /users", "routes.js", false),
+            node(7, "CLASS", "Users", "users.js", false),
+            node(8, "CLASS", "Legacy", "legacy.js", false),
+            node(9, "VARIABLE", "tmp", "util.js", false),
+        ]);
+        engine.add_edges(vec![
+            edge(1, 2, "CALLS"),
+            edge(4, 5, "CALLS"),
+            edge(6, 7, "CALLS"),
+            // По CONTAINS использование не считается
+            edge(2, 3, "CONTAINS"),
+        ], false);
+
+        let query = DeadCodeQuery::new(vec!["http:*".to_string()], vec!["CALLS".to_string()]);
+        let report = engine.dead_code(&query);
+        assert_eq!(report.entry_points, 3);
+        let dead: Vec<(&str, Vec<u128>)> = report.files.iter()
+            .map(|(file, nodes)| (file.as_str(), nodes.iter().map(|n| n.id).collect()))
+            .collect();
+        assert_eq!(dead, vec![("legacy.js", vec![8]), ("util.js", vec![3])]);
+        assert_eq!(report.len(), 2);
+
+        // Без exported и endpoint rules (среди них exported FUNCTION) api и Cache.get тоже мёртвые
+        let report = engine.dead_code(&query.clone().exported_are_entries(false).endpoints_are_entries(false));
+        assert_eq!(report.len(), 4);
+        assert!(report.files.contains_key("api.js") && report.files.contains_key("cache.js"));
+
+        // Все рёбра: CONTAINS делает unused достижимым
+        let report = engine.dead_code(&DeadCodeQuery::new(vec!["http:*".to_string()], vec![]));
+        assert_eq!(report.files.keys().collect::<Vec<_>>(), vec!["legacy.js"]);
+
+        // После flush колонки читаются из segment - результат тот же
+        engine.flush().unwrap();
+        let report = engine.dead_code(&query);
+        assert_eq!(report.entry_points, 3);
+        let dead: Vec<(&str, Vec<u128>)> = report.files.iter()
+            .map(|(file, nodes)| (file.as_str(), nodes.iter().map(|n| n.id).collect()))
+            .collect();
+        assert_eq!(dead, vec![("legacy.js", vec![8]), ("util.js", vec![3])]);
+    }
+
+    #[test]
+    fn test_impact() {
+        let dir = TempDir::new().unwrap();
+        let mut engine = GraphEngine::create(dir.path().join("g.rfdb")).unwrap();
+        engine.add_nodes(vec![
+            node(1, "FUNCTION", "hash", "src/util.js", false),
+            node(2, "FUNCTION", "login", "src/auth.js", false),
+            node(3, "http:endpoint", "POST /login", "src/routes.js", false),
+            node(4, "SERVICE", "auth-service", "src/auth.js", false),
+            node(5, "FUNCTION", "test login", "test/auth.test.js", false),
+            node(6, "FUNCTION", "unrelated", "src/other.js", false),
+            node(7, "FUNCTION", "slugify", "src/util.js", false),
+        ]);
+        engine.add_edges(vec![
+            edge(2, 1, "CALLS"),
+            edge(3, 2, "CALLS"),
+            edge(4, 3, "CONTAINS"),
+            edge(5, 2, "CALLS"),
+            edge(1, 6, "CALLS"),
+        ], false);
+
+        let report = engine.impact(&["src/util.js".to_string()], &[], 10);
+        assert_eq!(report.changed, vec![1, 7]);
+        let by_type: Vec<(&str, Vec<u128>)> = report.by_type.iter()
+            .map(|(t, nodes)| (t.as_str(), nodes.iter().map(|n| n.id).collect()))
+            .collect();
+        assert_eq!(by_type, vec![("FUNCTION", vec![2, 5]), ("SERVICE", vec![4]), ("http:endpoint", vec![3])]);
+        assert_eq!(report.test_files, vec!["test/auth.test.js"]);
+
+        // Только CALLS и не глубже 2: SERVICE не затронут
+        let report = engine.impact(&["src/util.js".to_string()], &["CALLS"], 2);
+        assert_eq!(report.len(), 3);
+        assert!(!report.by_type.contains_key("SERVICE"));
+
+        assert!(engine.impact(&["src/missing.js".to_string()], &[], 10).is_empty());
+
+        engine.flush().unwrap();
+        let report = engine.impact(&["src/util.js".to_string()], &[], 10);
+        assert_eq!(report.len(), 4);
+        assert_eq!(report.by_type["FUNCTION"].iter().map(|n| n.id).collect::<Vec<_>>(), vec![2, 5]);
+    }
+}
@@ -3,28 +3,21 @@
 use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::env;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::time::{Instant, Duration};
 use sysinfo::{System, RefreshKind, MemoryRefreshKind};
-use crate::storage::{NodeRecord, EdgeRecord, AttrQuery, SegmentWriter, GraphMetadata};
-use crate::storage::delta::{Delta, DeltaLog};
-use crate::storage::segment::{NodesSegment, EdgesSegment};
-use crate::error::Result;
-use super::{GraphStore, traversal};
+use crate::storage::{NodeRecord, EdgeRecord, AttrQuery, SegmentWriter, GraphMetadata, DbLock, Compression, StringDictionary, BulkLoader, BulkLoadStats, normalize_db_path};
+use crate::storage::dictionary::{DictionaryBuilder, DICTIONARY_FILE};
+use crate::storage::writer::{COMPACT_TMP_DIR, InterruptedCompaction, commit_compaction, interrupted_compaction, recover_compaction};
+use crate::storage::delta::Delta;
+use crate::storage::segment::{NodesSegment, EdgesSegment, EdgeIndex, FORMAT_VERSION};
+use crate::error::{GraphError, Result};
+use super::{GraphStore, EndpointRule, traversal};
+use super::traversal::{CycleComponent, Direction, Dominators, GraphPath, PathQuery, TopoOrder};
 
 // Global system info singleton for memory monitoring
 static SYSTEM_INFO: Mutex<Option<System>> = Mutex::new(None);
 
-// Debug logging macro - enabled via NAVI_DEBUG=1
-macro_rules! debug_log {
-    ($($arg:tt)*) => {
-        if env::var("NAVI_DEBUG").is_ok() {
-            eprintln!("[RUST DEBUG] {}", format!($($arg)*));
-        }
-    };
-}
-
 /// Threshold for automatic flush (number of operations)
 /// DISABLED: auto-flush slows down performance with small data volumes
 /// Flush only happens on explicit call or database close
@@ -58,33 +51,69 @@ fn check_memory_usage() -> f32 {
     }
 }
 
-/// Normalize database path ensuring .rfdb extension
-///
-/// Examples:
-/// - `/path/to/db` -> `/path/to/db.rfdb`
-/// - `/path/to/db.db` -> `/path/to/db.rfdb`
-/// - `/path/to/db.rfdb` -> `/path/to/db.rfdb` (unchanged)
-fn normalize_db_path<P: AsRef<Path>>(path: P) -> PathBuf {
-    let path = path.as_ref();
-
-    // If path already has .rfdb extension, return as is
-    if path.extension().and_then(|s| s.to_str()) == Some("rfdb") {
-        return path.to_path_buf();
-    }
-
-    // If there's another extension, replace with .rfdb
-    if path.extension().is_some() {
-        return path.with_extension("rfdb");
-    }
-
-    // If no extension, add .rfdb
-    let mut new_path = path.to_path_buf();
-    let new_filename = format!(
-        "{}.rfdb",
-        path.file_name().and_then(|s| s.to_str()).unwrap_or("db")
-    );
-    new_path.set_file_name(new_filename);
-    new_path
+/// Hard-link `src` to `dst`, falling back to a copy (e.g. across file systems)
+fn link_or_copy(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if fs::hard_link(src, dst).is_err() {
+        fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+
+/// Читаемый идентификатор ноды: `TYPE:name@file`, `TYPE:file`, `TYPE:name` или `TYPE:id`
+pub(crate) fn node_identifier(type_name: &str, name: &str, file_path: &str, id: u128) -> String {
+    if !name.is_empty() && !file_path.is_empty() {
+        format!("{}:{}@{}", type_name, name, file_path)
+    } else if !file_path.is_empty() {
+        format!("{}:{}", type_name, file_path)
+    } else if !name.is_empty() {
+        format!("{}:{}", type_name, name)
+    } else {
+        format!("{}:{}", type_name, id)
+    }
+}
+
+/// Живая нода из segment или delta: колонки читаются по требованию
+pub(crate) enum NodeView<'a> {
+    Segment { id: u128, segment: &'a NodesSegment, idx: usize },
+    Delta(&'a NodeRecord),
+}
+
+impl<'a> NodeView<'a> {
+    pub(crate) fn id(&self) -> u128 {
+        match self {
+            NodeView::Segment { id, .. } => *id,
+            NodeView::Delta(node) => node.id,
+        }
+    }
+
+    pub(crate) fn node_type(&self) -> Option<&'a str> {
+        match self {
+            NodeView::Segment { segment, idx, .. } => segment.get_node_type(*idx),
+            NodeView::Delta(node) => node.node_type.as_deref(),
+        }
+    }
+
+    pub(crate) fn name(&self) -> Option<&'a str> {
+        match self {
+            NodeView::Segment { segment, idx, .. } => segment.get_name(*idx),
+            NodeView::Delta(node) => node.name.as_deref(),
+        }
+    }
+
+    pub(crate) fn exported(&self) -> bool {
+        match self {
+            NodeView::Segment { segment, idx, .. } => segment.get_exported(*idx).unwrap_or(false),
+            NodeView::Delta(node) => node.exported,
+        }
+    }
+
+    pub(crate) fn metadata(&self) -> Option<&'a str> {
+        match self {
+            NodeView::Segment { segment, idx, .. } => segment.get_metadata(*idx),
+            NodeView::Delta(node) => node.metadata.as_deref(),
+        }
+    }
 }
 
 /// Main graph engine with real mmap + delta log
@@ -95,10 +124,7 @@ pub struct GraphEngine {
     nodes_segment: Option<NodesSegment>,
     edges_segment: Option<EdgesSegment>,
 
-    // Delta log for new operations
-    delta_log: DeltaLog,
-
-    // In-memory cache for delta log (for fast access)
+    // In-memory delta: изменения после последнего flush
     delta_nodes: HashMap<u128, NodeRecord>,
     delta_edges: Vec<EdgeRecord>,
 
@@ -121,6 +147,12 @@ pub struct GraphEngine {
     // When a node in segment is deleted but not in delta_nodes,
     // we track it here until next flush
     deleted_segment_ids: HashSet<u128>,
+
+    // Exclusive writer lock, released on drop (None for read-only engines)
+    _lock: Option<DbLock>,
+
+    // Read-only mode: segments are mapped, nothing is ever written
+    read_only: bool,
 }
 
 impl GraphEngine {
@@ -128,15 +160,14 @@ impl GraphEngine {
     pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = normalize_db_path(path);
         fs::create_dir_all(&path)?;
+        let lock = DbLock::acquire(&path)?;
 
-        debug_log!("GraphEngine::create() - path: {:?}", path);
         tracing::info!("Created new graph at {:?}", path);
 
         Ok(Self {
             path,
             nodes_segment: None,
             edges_segment: None,
-            delta_log: DeltaLog::new(),
             delta_nodes: HashMap::new(),
             delta_edges: Vec::new(),
             adjacency: HashMap::new(),
@@ -145,31 +176,81 @@ impl GraphEngine {
             ops_since_flush: 0,
             last_memory_check: None,
             deleted_segment_ids: HashSet::new(),
+            _lock: Some(lock),
+            read_only: false,
         })
     }
 
     /// Open an existing graph
+    ///
+    /// Takes an exclusive lock on the directory; fails with `GraphError::Locked`
+    /// if another writer (process or engine) has it open.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_internal(path, false, None)
+    }
+
+    /// Open an existing graph in read-only mode
+    ///
+    /// Segments are memory-mapped, no lock is taken and nothing is ever written:
+    /// GraphStore write methods are skipped (callers must check `is_read_only()`
+    /// and report the write as failed), everything else returns `GraphError::ReadOnly`.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_internal(path, true, None)
+    }
+
+    /// Finish a `BulkLoader` and open the new database
+    ///
+    /// Adjacency is collected while edges.bin is written, so the segment is
+    /// not scanned a second time on open.
+    pub fn finish_bulk_load(loader: BulkLoader) -> Result<(Self, BulkLoadStats)> {
+        let path = loader.path().to_path_buf();
+        let (stats, index) = loader.finish_indexed()?;
+        let engine = Self::open_internal(path, false, Some(index))?;
+        Ok((engine, stats))
+    }
+
+    /// `index` - готовый индекс рёбер edges.bin (BulkLoader), иначе строится здесь
+    fn open_internal<P: AsRef<Path>>(path: P, read_only: bool, index: Option<EdgeIndex>) -> Result<Self> {
         let path = normalize_db_path(path);
-        debug_log!("GraphEngine::open() - path: {:?}", path);
+        tracing::debug!(?path, read_only, "Opening graph");
+
+        let lock = if read_only {
+            if !path.is_dir() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Database not found: {:?}", path),
+                ).into());
+            }
+            None
+        } else {
+            let lock = DbLock::acquire(&path)?;
+            recover_compaction(&path)?;
+            Some(lock)
+        };
+        if read_only && interrupted_compaction(&path) == Some(InterruptedCompaction::Committed) {
+            return Err(GraphError::InvalidFormat(format!(
+                "Database {:?} has an interrupted compaction; open it for writing or run rfdb-fsck --repair", path
+            )));
+        }
 
-        // Load mmap segments if they exist
+        // Load mmap segments if they exist (v5+ share one strings.bin)
         let nodes_path = path.join("nodes.bin");
         let edges_path = path.join("edges.bin");
+        let strings = StringDictionary::open_if_exists(&path)?.map(Arc::new);
 
         let nodes_segment = if nodes_path.exists() {
-            debug_log!("  Loading nodes segment from {:?}", nodes_path);
-            Some(NodesSegment::open(&nodes_path)?)
+            tracing::debug!(path = ?nodes_path, "Loading nodes segment");
+            Some(NodesSegment::open_with_dictionary(&nodes_path, strings.clone())?)
         } else {
-            debug_log!("  No nodes segment found");
+            tracing::debug!("No nodes segment found");
             None
         };
 
         let edges_segment = if edges_path.exists() {
-            debug_log!("  Loading edges segment from {:?}", edges_path);
-            Some(EdgesSegment::open(&edges_path)?)
+            tracing::debug!(path = ?edges_path, "Loading edges segment");
+            Some(EdgesSegment::open_with_dictionary(&edges_path, strings)?)
         } else {
-            debug_log!("  No edges segment found");
+            tracing::debug!("No edges segment found");
             None
         };
 
@@ -182,66 +263,536 @@ impl GraphEngine {
             GraphMetadata::default()
         };
 
-        // Build adjacency and reverse_adjacency lists from segments
-        let mut adjacency = HashMap::new();
-        let mut reverse_adjacency = HashMap::new();
-        if let Some(ref edges_seg) = edges_segment {
-            for idx in 0..edges_seg.edge_count() {
-                if edges_seg.is_deleted(idx) {
-                    continue;
-                }
-                if let Some(src) = edges_seg.get_src(idx) {
-                    adjacency.entry(src).or_insert_with(Vec::new).push(idx);
-                }
-                if let Some(dst) = edges_seg.get_dst(idx) {
-                    reverse_adjacency.entry(dst).or_insert_with(Vec::new).push(idx);
-                }
-            }
+        // Версию формата определяют сами segments: metadata.json мог остаться от старой версии
+        let mut metadata = metadata;
+        let segment_versions = nodes_segment.iter().map(|s| s.format_version())
+            .chain(edges_segment.iter().map(|s| s.format_version()));
+        metadata.format_version = segment_versions.min().unwrap_or(FORMAT_VERSION);
+        if metadata.format_version < FORMAT_VERSION {
+            tracing::warn!(
+                "Database {:?} uses format version {} (current {}); run upgrade_format to rewrite it",
+                path, metadata.format_version, FORMAT_VERSION
+            );
+        }
+
+        // Build adjacency and reverse_adjacency lists from segments
+        let EdgeIndex { outgoing: adjacency, incoming: reverse_adjacency } = match index {
+            Some(index) => index,
+            None => edges_segment.as_ref().map(EdgeIndex::build).unwrap_or_default(),
+        };
+
+        tracing::info!(
+            "Opened graph at {:?}: {} nodes, {} edges",
+            path,
+            nodes_segment.as_ref().map_or(0, |s| s.node_count()),
+            edges_segment.as_ref().map_or(0, |s| s.edge_count())
+        );
+
+        Ok(Self {
+            path,
+            nodes_segment,
+            edges_segment,
+            delta_nodes: HashMap::new(),
+            delta_edges: Vec::new(),
+            adjacency,
+            reverse_adjacency,
+            metadata,
+            ops_since_flush: 0,
+            last_memory_check: None,
+            deleted_segment_ids: HashSet::new(),
+            _lock: lock,
+            read_only,
+        })
+    }
+
+    /// Whether the engine was opened with `open_read_only`
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Database directory path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Verify on-disk segments: column checksums, string table and offsets
+    ///
+    /// Corruption is reported as `GraphError::InvalidFormat` naming the file.
+    /// Segments written before checksums were introduced only get bounds checks.
+    pub fn verify(&self) -> Result<()> {
+        let in_file = |file: &str, e: GraphError| match e {
+            GraphError::InvalidFormat(msg) => GraphError::InvalidFormat(format!("{}: {}", file, msg)),
+            other => other,
+        };
+
+        if let Some(strings) = StringDictionary::open_if_exists(&self.path)? {
+            strings.verify()?;
+        }
+        if let Some(ref segment) = self.nodes_segment {
+            segment.verify().map_err(|e| in_file("nodes.bin", e))?;
+        }
+        if let Some(ref segment) = self.edges_segment {
+            segment.verify().map_err(|e| in_file("edges.bin", e))?;
+        }
+        Ok(())
+    }
+
+    /// Format version of the on-disk segments (oldest of nodes.bin/edges.bin)
+    pub fn format_version(&self) -> u16 {
+        self.metadata.format_version
+    }
+
+    /// Rewrite segments written by an older rfdb into the current format
+    ///
+    /// Returns `false` if the database is already current. Pending delta is
+    /// flushed as part of the rewrite.
+    pub fn upgrade_format(&mut self) -> Result<bool> {
+        if self.read_only {
+            return Err(GraphError::ReadOnly("upgrade_format"));
+        }
+        let from = self.format_version();
+        if from >= FORMAT_VERSION {
+            return Ok(false);
+        }
+
+        let _span = tracing::info_span!("upgrade_format", from, to = FORMAT_VERSION).entered();
+        let (node_count, edge_count) = self.rewrite_segments(false)?;
+        tracing::info!("Upgraded format {} -> {}: {} nodes, {} edges", from, FORMAT_VERSION, node_count, edge_count);
+        Ok(true)
+    }
+
+    /// Segment compression configured for this database
+    pub fn compression(&self) -> Compression {
+        self.metadata.compression
+    }
+
+    /// Change segment compression and rewrite existing segments with it
+    pub fn set_compression(&mut self, compression: Compression) -> Result<()> {
+        if self.read_only {
+            return Err(GraphError::ReadOnly("set_compression"));
+        }
+        if self.metadata.compression == compression {
+            return Ok(());
+        }
+
+        let _span = tracing::info_span!("set_compression", ?compression).entered();
+        self.metadata.compression = compression;
+        if self.nodes_segment.is_some() || self.edges_segment.is_some() || self.has_delta() {
+            self.rewrite_segments(false)?;
+        } else {
+            SegmentWriter::new(&self.path).write_metadata(&self.metadata)?;
+        }
+        Ok(())
+    }
+
+    /// Write a consistent snapshot of the graph to `dest` (a new database directory)
+    ///
+    /// Needs only `&self`, so readers keep being served. With an empty delta the
+    /// immutable segments are hard-linked (copied if linking fails, e.g. across
+    /// devices); otherwise segment + delta are merged and written into `dest`.
+    pub fn checkpoint<P: AsRef<Path>>(&self, dest: P) -> Result<()> {
+        let dest = normalize_db_path(dest);
+        let _span = tracing::info_span!("checkpoint", ?dest).entered();
+
+        if dest.exists() && fs::read_dir(&dest)?.next().is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("Checkpoint destination is not empty: {:?}", dest),
+            ).into());
+        }
+        fs::create_dir_all(&dest)?;
+
+        if !self.has_delta() {
+            for file in [DICTIONARY_FILE, "nodes.bin", "edges.bin"] {
+                let src = self.path.join(file);
+                if src.exists() {
+                    link_or_copy(&src, &dest.join(file))?;
+                }
+            }
+            SegmentWriter::new(&dest).write_metadata(&self.metadata)?;
+        } else {
+            let (nodes, edges) = self.collect_records();
+            let writer = SegmentWriter::new(&dest).with_compression(self.metadata.compression);
+            writer.write_segments(&nodes, &edges, &mut DictionaryBuilder::new())?;
+            writer.write_metadata(&GraphMetadata {
+                node_count: nodes.len(),
+                edge_count: edges.len(),
+                format_version: FORMAT_VERSION,
+                ..self.metadata.clone()
+            })?;
+        }
+
+        tracing::info!("Checkpoint written to {:?}", dest);
+        Ok(())
+    }
+
+    /// Replace the whole graph with a checkpoint taken by `checkpoint`
+    ///
+    /// Unflushed changes are discarded. The checkpoint is verified before
+    /// anything is replaced and stays untouched afterwards.
+    pub fn restore<P: AsRef<Path>>(&mut self, src: P) -> Result<()> {
+        if self.read_only {
+            return Err(GraphError::ReadOnly("restore"));
+        }
+        let src = normalize_db_path(src);
+        let _span = tracing::info_span!("restore", ?src).entered();
+
+        // Проверяем checkpoint до того как трогать текущую базу
+        let nodes_src = src.join("nodes.bin");
+        let edges_src = src.join("edges.bin");
+        if !nodes_src.exists() && !edges_src.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No checkpoint found at {:?}", src),
+            ).into());
+        }
+        if let Some(strings) = StringDictionary::open_if_exists(&src)? {
+            strings.verify()?;
+        }
+        if nodes_src.exists() {
+            NodesSegment::open(&nodes_src)?.verify()?;
+        }
+        if edges_src.exists() {
+            EdgesSegment::open(&edges_src)?.verify()?;
+        }
+        let metadata = match fs::File::open(src.join("metadata.json")) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(_) => GraphMetadata::default(),
+        };
+
+        self.nodes_segment = None;
+        self.edges_segment = None;
+
+        // Файлы checkpoint подменяются как результат compact: прерванный restore
+        // open доводит до конца (маркер записан) или откатывает. Link безопасен и
+        // для checkpoint: файлы переносятся rename, strings.bin при nlink > 1 копируется
+        let strings_src = src.join(DICTIONARY_FILE);
+        let files = [(&strings_src, DICTIONARY_FILE), (&nodes_src, "nodes.bin"), (&edges_src, "edges.bin")];
+        let tmp_dir = self.path.join(COMPACT_TMP_DIR);
+        let _ = fs::remove_dir_all(&tmp_dir);
+        fs::create_dir_all(&tmp_dir)?;
+        for (src_file, file) in files {
+            if src_file.exists() {
+                link_or_copy(src_file, &tmp_dir.join(file))?;
+            }
+        }
+        commit_compaction(&self.path)?;
+        // Файлов, которых нет в checkpoint, не должно остаться и в базе
+        for (src_file, file) in files {
+            let target = self.path.join(file);
+            if !src_file.exists() && target.exists() {
+                fs::remove_file(&target)?;
+            }
+        }
+
+        self.metadata = metadata;
+        SegmentWriter::new(&self.path).write_metadata(&self.metadata)?;
+
+        self.delta_nodes.clear();
+        self.delta_edges.clear();
+        self.deleted_segment_ids.clear();
+        self.ops_since_flush = 0;
+        self.reload_segments()?;
+
+        tracing::info!("Restored graph from {:?}", src);
+        Ok(())
+    }
+
+    /// Собрать все живые ноды и рёбра (segment + delta) в том виде, как их пишет flush
+    fn collect_records(&self) -> (Vec<NodeRecord>, Vec<EdgeRecord>) {
+        tracing::debug!("Delta has {} nodes before flush", self.delta_nodes.len());
+
+        // Собираем все ноды (segment + delta)
+        let mut all_nodes = Vec::new();
+
+        // Из segment - сохраняем строки чтобы они не потерялись
+        if let Some(ref segment) = self.nodes_segment {
+            for idx in segment.iter_indices() {
+                if !segment.is_deleted(idx) {
+                    if let Some(id) = segment.get_id(idx) {
+                        // Skip nodes that were deleted (tracked in deleted_segment_ids)
+                        if self.deleted_segment_ids.contains(&id) {
+                            continue;
+                        }
+
+                        // Читаем строковые данные из StringTable если есть
+                        let node_type = segment.get_node_type(idx).map(|s| s.to_string());
+                        let name = segment.get_name(idx).map(|s| s.to_string());
+                        let file = segment.get_file_path(idx).map(|s| s.to_string());
+                        let metadata = segment.get_metadata(idx).map(|s| s.to_string());
+                        let version = segment.get_version(idx).unwrap_or("main");
+                        let exported = segment.get_exported(idx).unwrap_or(false);
+
+                        all_nodes.push(NodeRecord {
+                            id,
+                            node_type,
+                            file_id: 0, // Будет пересчитано в writer
+                            name_offset: 0, // Будет пересчитано в writer
+                            version: version.to_string(),
+                            exported,
+                            replaces: segment.get_replaces(idx),
+                            deleted: false,
+                            name,
+                            file,
+                            metadata,
+                        });
+                    }
+                }
+            }
+        }
+
+        let nodes_from_segment = all_nodes.len();
+        tracing::debug!("Collected {} nodes from segment", nodes_from_segment);
+
+        // From delta
+        let mut seen_ids = std::collections::HashSet::new();
+        for node in &all_nodes {
+            seen_ids.insert(node.id);
+        }
+
+        let mut delta_added = 0;
+        let mut delta_duplicates = 0;
+        for node in self.delta_nodes.values() {
+            if !node.deleted {
+                if seen_ids.contains(&node.id) {
+                    tracing::trace!("Duplicate ID {} in flush - delta overwrites segment", node.id);
+                    delta_duplicates += 1;
+                }
+                all_nodes.push(node.clone());
+                delta_added += 1;
+            }
+        }
+
+        tracing::debug!("Added {} nodes from delta ({} duplicates), {} total to write",
+            delta_added, delta_duplicates, all_nodes.len());
+
+        // Собираем все рёбра
+        let mut all_edges = Vec::new();
+
+        // Из segment
+        if let Some(ref segment) = self.edges_segment {
+            for idx in 0..segment.edge_count() {
+                if !segment.is_deleted(idx) {
+                    if let (Some(src), Some(dst)) = (
+                        segment.get_src(idx),
+                        segment.get_dst(idx),
+                    ) {
+                        let edge_type = segment.get_edge_type(idx).map(|s| s.to_string());
+                        let metadata = segment.get_metadata(idx).map(|s| s.to_string());
+                        all_edges.push(EdgeRecord {
+                            src,
+                            dst,
+                            edge_type,
+                            version: segment.get_version(idx).unwrap_or("main").to_string(),
+                            metadata,
+                            deleted: false,
+                        });
+                    }
+                }
+            }
+        }
+
+        // From delta
+        for edge in &self.delta_edges {
+            if !edge.deleted {
+                all_edges.push(edge.clone());
+            }
+        }
+
+        (all_nodes, all_edges)
+    }
+
+    /// Живые ноды (segment + delta) без сборки NodeRecord, отсортированы по id
+    ///
+    /// Колонки читаются лениво через `NodeView`, строки не копируются.
+    /// При дублях в сегменте побеждает последняя строка, delta перекрывает segment.
+    pub(crate) fn live_nodes(&self) -> Vec<NodeView<'_>> {
+        let mut views = Vec::new();
+        if let Some(ref segment) = self.nodes_segment {
+            let mut rows: HashMap<u128, usize> = HashMap::new();
+            for idx in 0..segment.node_count() {
+                if let Some(id) = segment.get_id(idx) {
+                    rows.insert(id, idx);
+                }
+            }
+            views.extend(rows.into_iter()
+                .filter(|(id, idx)| !segment.is_deleted(*idx)
+                    && !self.deleted_segment_ids.contains(id)
+                    && !self.delta_nodes.contains_key(id))
+                .map(|(id, idx)| NodeView::Segment { id, segment, idx }));
+        }
+        views.extend(self.delta_nodes.values().filter(|n| !n.deleted).map(NodeView::Delta));
+        views.sort_unstable_by_key(|v| v.id());
+        views
+    }
+
+    /// Живые ноды из `ids` за один проход по сегменту (вместо `get_node` на каждую)
+    ///
+    /// Порядок не определён; отсутствующие id пропускаются.
+    pub(crate) fn get_nodes(&self, ids: &HashSet<u128>) -> Vec<NodeRecord> {
+        let mut found: HashMap<u128, NodeRecord> = HashMap::new();
+        if let Some(ref segment) = self.nodes_segment {
+            for idx in 0..segment.node_count() {
+                let Some(id) = segment.get_id(idx) else { continue };
+                if !ids.contains(&id) || self.delta_nodes.contains_key(&id) || self.deleted_segment_ids.contains(&id) {
+                    continue;
+                }
+                // При дублях в сегменте побеждает последняя строка
+                match segment.node_record(idx) {
+                    Some(record) if !record.deleted => found.insert(id, record),
+                    _ => found.remove(&id),
+                };
+            }
+        }
+        for node in self.delta_nodes.values().filter(|n| !n.deleted && ids.contains(&n.id)) {
+            found.insert(node.id, node.clone());
+        }
+        found.into_values().collect()
+    }
+
+    /// Текущее состояние графа по одной записи на ID (delta приоритетнее segment)
+    ///
+    /// С `include_deleted` в результат попадают и tombstones. Ноды отсортированы
+    /// по id, рёбра по (src, dst, type) - вывод детерминирован для diff.
+    pub(crate) fn snapshot_records(&self, include_deleted: bool) -> (Vec<NodeRecord>, Vec<EdgeRecord>) {
+        let mut nodes: HashMap<u128, NodeRecord> = HashMap::new();
+        if let Some(ref segment) = self.nodes_segment {
+            // При дублях в сегменте побеждает последняя строка
+            for record in (0..segment.node_count()).filter_map(|idx| segment.node_record(idx)) {
+                nodes.insert(record.id, record);
+            }
+        }
+        for id in &self.deleted_segment_ids {
+            if let Some(node) = nodes.get_mut(id) {
+                node.deleted = true;
+            }
+        }
+        for node in self.delta_nodes.values() {
+            nodes.insert(node.id, node.clone());
+        }
+
+        let mut nodes: Vec<NodeRecord> = nodes.into_values()
+            .filter(|n| include_deleted || !n.deleted)
+            .collect();
+        nodes.sort_by_key(|n| n.id);
+
+        let segment_edges = self.edges_segment.iter()
+            .flat_map(|seg| (0..seg.edge_count()).filter_map(|idx| seg.edge_record(idx)));
+        let mut edges: Vec<EdgeRecord> = segment_edges
+            .chain(self.delta_edges.iter().cloned())
+            .filter(|e| include_deleted || !e.deleted)
+            .collect();
+        edges.sort_by(|a, b| (a.src, a.dst, &a.edge_type).cmp(&(b.src, b.dst, &b.edge_type)));
+
+        (nodes, edges)
+    }
+
+    /// Переписать segments (segment + delta) в текущем формате и очистить delta
+    ///
+    /// Обычно strings.bin только дописывается (один builder на оба сегмента).
+    /// С `compact` словарь собирается заново из живых строк: файлы пишутся
+    /// во временную директорию и подменяются (`commit_compaction`). Подмену,
+    /// прерванную падением, open доводит до конца или откатывает.
+    fn rewrite_segments(&mut self, compact: bool) -> Result<(usize, usize)> {
+        let (all_nodes, all_edges) = self.collect_records();
+
+        // Закрываем старые segments перед перезаписью
+        self.nodes_segment = None;
+        self.edges_segment = None;
+
+        // Записываем на диск
+        let writer = SegmentWriter::new(&self.path).with_compression(self.metadata.compression);
+        if compact {
+            let tmp_dir = self.path.join(COMPACT_TMP_DIR);
+            let _ = fs::remove_dir_all(&tmp_dir);
+            fs::create_dir_all(&tmp_dir)?;
+            SegmentWriter::new(&tmp_dir)
+                .with_compression(self.metadata.compression)
+                .write_segments(&all_nodes, &all_edges, &mut DictionaryBuilder::new())?;
+            commit_compaction(&self.path)?;
+        } else {
+            writer.write_segments(&all_nodes, &all_edges, &mut DictionaryBuilder::load_or_new(&self.path)?)?;
+        }
+
+        // Обновляем metadata
+        self.metadata.format_version = FORMAT_VERSION;
+        self.metadata.node_count = all_nodes.len();
+        self.metadata.edge_count = all_edges.len();
+        self.metadata.updated_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        writer.write_metadata(&self.metadata)?;
+
+        // Очищаем delta и deleted_segment_ids (nodes are now written to new segment)
+        self.delta_nodes.clear();
+        self.delta_edges.clear();
+        self.deleted_segment_ids.clear();
+
+        // Перезагружаем segments и adjacency
+        self.reload_segments()?;
+
+        // Сбросить счётчик операций
+        self.ops_since_flush = 0;
+
+        Ok((all_nodes.len(), all_edges.len()))
+    }
+
+    /// Переоткрыть nodes.bin/edges.bin и перестроить adjacency по ним
+    fn reload_segments(&mut self) -> Result<()> {
+        let nodes_path = self.path.join("nodes.bin");
+        let edges_path = self.path.join("edges.bin");
+        let strings = StringDictionary::open_if_exists(&self.path)?.map(Arc::new);
+        self.nodes_segment = if nodes_path.exists() {
+            Some(NodesSegment::open_with_dictionary(&nodes_path, strings.clone())?)
+        } else {
+            None
+        };
+        self.edges_segment = if edges_path.exists() {
+            Some(EdgesSegment::open_with_dictionary(&edges_path, strings)?)
+        } else {
+            None
+        };
+
+        let index = self.edges_segment.as_ref().map(EdgeIndex::build).unwrap_or_default();
+        self.adjacency = index.outgoing;
+        self.reverse_adjacency = index.incoming;
+        Ok(())
+    }
+
+    /// Returns true (and logs) if a write operation must be skipped
+    fn reject_write(&self, operation: &'static str) -> bool {
+        if self.read_only {
+            tracing::warn!("Ignoring {} on read-only database {:?}", operation, self.path);
         }
+        self.read_only
+    }
 
-        tracing::info!(
-            "Opened graph at {:?}: {} nodes, {} edges",
-            path,
-            nodes_segment.as_ref().map_or(0, |s| s.node_count()),
-            edges_segment.as_ref().map_or(0, |s| s.edge_count())
-        );
-
-        Ok(Self {
-            path,
-            nodes_segment,
-            edges_segment,
-            delta_log: DeltaLog::new(),
-            delta_nodes: HashMap::new(),
-            delta_edges: Vec::new(),
-            adjacency,
-            reverse_adjacency,
-            metadata,
-            ops_since_flush: 0,
-            last_memory_check: None,
-            deleted_segment_ids: HashSet::new(),
-        })
+    /// Есть ли изменения, не записанные в segments
+    fn has_delta(&self) -> bool {
+        !self.delta_nodes.is_empty() || !self.delta_edges.is_empty() || !self.deleted_segment_ids.is_empty()
     }
 
     /// Apply delta to current state
-    fn apply_delta(&mut self, delta: &Delta) {
+    ///
+    /// Запись перемещается в delta_nodes/delta_edges (единственная копия).
+    fn apply_delta(&mut self, delta: Delta) {
         match delta {
             Delta::AddNode(node) => {
-                debug_log!("apply_delta (engine={:p}): AddNode id={}, type={:?}, name={:?}, delta_nodes before: {}",
-                    self, node.id, node.node_type, node.name, self.delta_nodes.len());
-                self.delta_nodes.insert(node.id, node.clone());
-                debug_log!("  delta_nodes after: {}", self.delta_nodes.len());
+                tracing::trace!(id = node.id, node_type = ?node.node_type, name = ?node.name, "apply_delta: AddNode");
+                self.delta_nodes.insert(node.id, node);
             }
             Delta::DeleteNode { id } => {
-                if let Some(node) = self.delta_nodes.get_mut(id) {
+                if let Some(node) = self.delta_nodes.get_mut(&id) {
                     node.deleted = true;
                 } else {
                     // Node is in segment (already flushed), track it for deletion
-                    self.deleted_segment_ids.insert(*id);
+                    self.deleted_segment_ids.insert(id);
                 }
             }
             Delta::AddEdge(edge) => {
                 let edge_idx = self.delta_edges.len();
-                self.delta_edges.push(edge.clone());
 
                 // Calculate the global edge index (segment + delta)
                 let global_idx = edge_idx + self.edges_segment.as_ref().map_or(0, |s| s.edge_count());
@@ -249,18 +800,20 @@ impl GraphEngine {
                 // Update forward adjacency list
                 self.adjacency
                     .entry(edge.src)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(global_idx);
 
                 // Update reverse adjacency list
                 self.reverse_adjacency
                     .entry(edge.dst)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(global_idx);
+
+                self.delta_edges.push(edge);
             }
             Delta::DeleteEdge { src, dst, edge_type } => {
                 for edge in &mut self.delta_edges {
-                    let matches = edge.src == *src && edge.dst == *dst &&
+                    let matches = edge.src == src && edge.dst == dst &&
                         edge.edge_type.as_deref() == Some(edge_type.as_str());
                     if matches {
                         edge.deleted = true;
@@ -268,8 +821,8 @@ impl GraphEngine {
                 }
             }
             Delta::UpdateNodeVersion { id, version } => {
-                if let Some(node) = self.delta_nodes.get_mut(id) {
-                    node.version = version.clone();
+                if let Some(node) = self.delta_nodes.get_mut(&id) {
+                    node.version = version;
                 }
             }
         }
@@ -303,7 +856,7 @@ impl GraphEngine {
                         name_offset: segment.get_name_offset(idx).unwrap_or(0),
                         version: segment.get_version(idx).unwrap_or("main").to_string(),
                         exported: segment.get_exported(idx).unwrap_or(false),
-                        replaces: None,
+                        replaces: segment.get_replaces(idx),
                         deleted: false,
                         name: segment.get_name(idx).map(|s| s.to_string()),
                         file: segment.get_file_path(idx).map(|s| s.to_string()),
@@ -318,38 +871,56 @@ impl GraphEngine {
 
     /// Clear all data (delta and segments)
     pub fn clear(&mut self) {
-        self.delta_log.clear();
+        if self.reject_write("clear") {
+            return;
+        }
         self.delta_nodes.clear();
         self.delta_edges.clear();
         self.adjacency.clear();
         self.reverse_adjacency.clear();
         self.nodes_segment = None;
         self.edges_segment = None;
-        self.metadata = GraphMetadata::default();
+        // Endpoint rules and compression belong to the database, not to its data
+        let endpoint_rules = std::mem::take(&mut self.metadata.endpoint_rules);
+        let compression = self.metadata.compression;
+        self.metadata = GraphMetadata { endpoint_rules, compression, ..GraphMetadata::default() };
         self.ops_since_flush = 0;
         self.deleted_segment_ids.clear();
         tracing::info!("Graph cleared");
     }
 
     /// Check if a node is an endpoint (for PathValidator)
+    /// Node is an endpoint if it matches at least one of the registered endpoint rules
     pub fn is_endpoint(&self, id: u128) -> bool {
-        if let Some(node) = self.get_node_internal(id) {
-            let node_type = node.node_type.as_deref().unwrap_or("UNKNOWN");
+        match self.get_node_internal(id) {
+            Some(node) => self.metadata.endpoint_rules.iter().any(|rule| rule.matches(&node)),
+            None => false,
+        }
+    }
 
-            // Endpoint types: db:query, http:request, http:endpoint, EXTERNAL, fs:operation, SIDE_EFFECT
-            if matches!(node_type,
-                "db:query" | "http:request" | "http:endpoint" |
-                "EXTERNAL" | "fs:operation" | "SIDE_EFFECT"
-            ) {
-                return true;
-            }
+    /// Get registered endpoint rules
+    pub fn endpoint_rules(&self) -> &[EndpointRule] {
+        &self.metadata.endpoint_rules
+    }
 
-            // Exported FUNCTION
-            if node_type == "FUNCTION" && node.exported {
-                return true;
-            }
+    /// Replace endpoint rules and persist them in metadata.json
+    pub fn set_endpoint_rules(&mut self, rules: Vec<EndpointRule>) -> Result<()> {
+        if self.read_only {
+            return Err(GraphError::ReadOnly("set_endpoint_rules"));
+        }
+        self.metadata.endpoint_rules = rules;
+        SegmentWriter::new(&self.path).write_metadata(&self.metadata)
+    }
+
+    /// Register an additional endpoint rule and persist it in metadata.json
+    pub fn add_endpoint_rule(&mut self, rule: EndpointRule) -> Result<()> {
+        if self.read_only {
+            return Err(GraphError::ReadOnly("add_endpoint_rule"));
         }
-        false
+        if !self.metadata.endpoint_rules.contains(&rule) {
+            self.metadata.endpoint_rules.push(rule);
+        }
+        SegmentWriter::new(&self.path).write_metadata(&self.metadata)
     }
 
     /// Version-aware operations
@@ -369,6 +940,9 @@ impl GraphEngine {
     }
 
     pub fn delete_version(&mut self, version: &str) {
+        if self.reject_write("delete_version") {
+            return;
+        }
         for (_, node) in self.delta_nodes.iter_mut() {
             if node.version == version {
                 node.deleted = true;
@@ -383,10 +957,11 @@ impl GraphEngine {
     }
 
     /// Автоматический flush если достигнут порог операций или памяти
+    #[allow(clippy::absurd_extreme_comparisons)] // AUTO_FLUSH_THRESHOLD отключён через usize::MAX
     fn maybe_auto_flush(&mut self) {
         // Проверка по количеству операций (отключена)
         if self.ops_since_flush >= AUTO_FLUSH_THRESHOLD {
-            debug_log!("Auto-flush triggered: {} ops >= threshold {}", self.ops_since_flush, AUTO_FLUSH_THRESHOLD);
+            tracing::debug!("Auto-flush triggered: {} ops >= threshold {}", self.ops_since_flush, AUTO_FLUSH_THRESHOLD);
             if let Err(e) = self.flush() {
                 tracing::error!("Auto-flush failed: {}", e);
             }
@@ -405,8 +980,8 @@ impl GraphEngine {
             let mem_usage = check_memory_usage();
             
             if mem_usage >= MEMORY_THRESHOLD_PERCENT {
-                eprintln!("[RUST MEMORY FLUSH] Memory usage: {:.1}% >= {:.1}%, flushing {} operations", 
-                         mem_usage, MEMORY_THRESHOLD_PERCENT, self.ops_since_flush);
+                tracing::warn!("Memory usage: {:.1}% >= {:.1}%, flushing {} operations",
+                    mem_usage, MEMORY_THRESHOLD_PERCENT, self.ops_since_flush);
                 if let Err(e) = self.flush() {
                     tracing::error!("Memory-triggered flush failed: {}", e);
                 }
@@ -415,6 +990,9 @@ impl GraphEngine {
     }
 
     pub fn promote_local_to_main(&mut self) {
+        if self.reject_write("promote_local_to_main") {
+            return;
+        }
         // Удалить old main ноды которые заменены
         let to_delete: Vec<u128> = self
             .delta_nodes
@@ -521,7 +1099,7 @@ impl GraphEngine {
                     }
                     if let Some(src) = edges_seg.get_src(idx) {
                         let edge_type = edges_seg.get_edge_type(idx);
-                        if edge_types.is_empty() || edge_type.map_or(false, |et| edge_types.contains(&et)) {
+                        if edge_types.is_empty() || edge_type.is_some_and(|et| edge_types.contains(&et)) {
                             result.push(src);
                         }
                     }
@@ -543,7 +1121,7 @@ impl GraphEngine {
                         continue;
                     }
                     let matches = edge_types.is_empty() ||
-                        edge.edge_type.as_deref().map_or(false, |et| edge_types.contains(&et));
+                        edge.edge_type.as_deref().is_some_and(|et| edge_types.contains(&et));
                     if matches {
                         result.push(edge.src);
                     }
@@ -554,6 +1132,146 @@ impl GraphEngine {
         result
     }
 
+    /// Достижим ли dst из src за `max_depth` шагов (двунаправленный BFS по adjacency)
+    pub fn is_reachable(&self, src: u128, dst: u128, edge_types: &[&str], max_depth: usize) -> bool {
+        self.reachable_visiting(src, dst, edge_types, max_depth).0
+    }
+
+    /// `is_reachable` плюс число посещённых нод (для статистики Datalog)
+    pub(crate) fn reachable_visiting(&self, src: u128, dst: u128, edge_types: &[&str], max_depth: usize) -> (bool, usize) {
+        traversal::bidirectional_reachable(
+            src,
+            dst,
+            max_depth,
+            |id| self.neighbors(id, edge_types),
+            |id| self.reverse_neighbors(id, edge_types),
+        )
+    }
+
+    /// Кратчайший путь src -> dst с нодами и рёбрами (None если недостижим)
+    pub fn shortest_path(&self, src: u128, dst: u128, edge_types: &[&str], direction: Direction) -> Option<GraphPath> {
+        traversal::shortest_path(self, src, dst, edge_types, direction)
+    }
+
+    /// Все простые пути из `sources` в `targets` в пределах `query`
+    pub fn all_paths(&self, sources: &[u128], targets: &[u128], query: &PathQuery) -> Vec<GraphPath> {
+        traversal::all_simple_paths(self, sources, targets, query)
+    }
+
+    /// Сильно связные компоненты с циклами (циклические импорты, взаимная рекурсия)
+    ///
+    /// Учитываются только рёбра `edge_types` (пусто - все). `with_cycles`
+    /// добавляет к каждой компоненте пример цикла.
+    pub fn cycle_components(&self, edge_types: &[&str], with_cycles: bool) -> Vec<CycleComponent> {
+        let mut roots: Vec<u128> = self.adjacency.keys().copied().collect();
+        roots.sort_unstable();
+        traversal::find_cycle_components(&roots, with_cycles, |id| self.neighbors(id, edge_types))
+    }
+
+    /// Живые рёбра по индексам из `adjacency` / `reverse_adjacency`
+    ///
+    /// Индексы < edge_count сегмента указывают в сегмент, остальные - в
+    /// `delta_edges`; `endpoint` отсеивает delta-рёбра, чей индекс устарел.
+    fn adjacent_edges(
+        &self,
+        edge_indices: Option<&Vec<usize>>,
+        edge_types: Option<&[&str]>,
+        endpoint: impl Fn(&EdgeRecord) -> bool,
+    ) -> Vec<EdgeRecord> {
+        let mut result = Vec::new();
+        let Some(edge_indices) = edge_indices else {
+            return result;
+        };
+        let segment_edge_count = self.edges_segment.as_ref().map_or(0, |s| s.edge_count());
+        let type_matches = |edge_type: Option<&str>| {
+            edge_types.is_none_or(|types| edge_type.is_some_and(|et| types.contains(&et)))
+        };
+
+        for &idx in edge_indices {
+            if idx < segment_edge_count {
+                let Some(ref edges_seg) = self.edges_segment else { continue };
+                if edges_seg.is_deleted(idx) {
+                    continue;
+                }
+                let (Some(src), Some(dst)) = (edges_seg.get_src(idx), edges_seg.get_dst(idx)) else {
+                    continue;
+                };
+                let edge_type = edges_seg.get_edge_type(idx);
+                if !type_matches(edge_type) {
+                    continue;
+                }
+                result.push(EdgeRecord {
+                    src,
+                    dst,
+                    edge_type: edge_type.map(|s| s.to_string()),
+                    version: edges_seg.get_version(idx).unwrap_or("main").to_string(),
+                    metadata: edges_seg.get_metadata(idx).map(|s| s.to_string()),
+                    deleted: false,
+                });
+            } else if let Some(edge) = self.delta_edges.get(idx - segment_edge_count) {
+                if !edge.deleted && endpoint(edge) && type_matches(edge.edge_type.as_deref()) {
+                    result.push(edge.clone());
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Все ноды, лежащие на циклах по рёбрам `edge_types` (один проход Tarjan)
+    pub fn cycle_nodes(&self, edge_types: &[&str]) -> HashSet<u128> {
+        self.cycle_components(edge_types, false)
+            .into_iter()
+            .flat_map(|c| c.nodes)
+            .collect()
+    }
+
+    /// Лежит ли нода на цикле по рёбрам `edge_types`
+    ///
+    /// Tarjan только от `id`: обходится то, что из неё достижимо. Для многих
+    /// нод дешевле один раз взять `cycle_nodes`.
+    pub fn in_cycle(&self, id: u128, edge_types: &[&str]) -> bool {
+        traversal::find_cycle_components(&[id], false, |n| self.neighbors(n, edge_types))
+            .iter()
+            .any(|c| c.nodes.contains(&id))
+    }
+
+    /// Топологический порядок нод, связанных рёбрами `edge_types` (пусто - все)
+    ///
+    /// Ноды берутся из `adjacency`/`reverse_adjacency`; циклы попадают в
+    /// `TopoOrder::cycles` вместо ошибки.
+    pub fn topological_sort(&self, edge_types: &[&str]) -> TopoOrder {
+        let mut nodes: Vec<u128> = self.adjacency.keys()
+            .chain(self.reverse_adjacency.keys())
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|&id| {
+                !self.neighbors(id, edge_types).is_empty() || !self.reverse_neighbors(id, edge_types).is_empty()
+            })
+            .collect();
+        nodes.sort_unstable();
+        traversal::topological_order(&nodes, |id| self.neighbors(id, edge_types))
+    }
+
+    /// Доминаторы от `root` по рёбрам `edge_types` (пусто - все)
+    pub fn dominators(&self, root: u128, edge_types: &[&str]) -> Dominators {
+        traversal::dominators(
+            root,
+            |id| self.neighbors(id, edge_types),
+            |id| self.reverse_neighbors(id, edge_types),
+        )
+    }
+
+    /// Пост-доминаторы относительно стока `exit`: обход по `reverse_adjacency`
+    pub fn post_dominators(&self, exit: u128, edge_types: &[&str]) -> Dominators {
+        traversal::dominators(
+            exit,
+            |id| self.reverse_neighbors(id, edge_types),
+            |id| self.neighbors(id, edge_types),
+        )
+    }
+
     /// Transitive reachability query using BFS
     /// Returns all nodes reachable from start nodes within max_depth
     /// If backward=true, traverses edges in reverse direction (find sources)
@@ -572,18 +1290,22 @@ impl GraphEngine {
 
 impl GraphStore for GraphEngine {
     fn add_nodes(&mut self, nodes: Vec<NodeRecord>) {
+        if self.reject_write("add_nodes") {
+            return;
+        }
         let count = nodes.len();
         for node in nodes {
-            self.delta_log.push(Delta::AddNode(node.clone()));
-            self.apply_delta(&Delta::AddNode(node));
+            self.apply_delta(Delta::AddNode(node));
         }
         self.ops_since_flush += count;
         self.maybe_auto_flush();
     }
 
     fn delete_node(&mut self, id: u128) {
-        self.delta_log.push(Delta::DeleteNode { id });
-        self.apply_delta(&Delta::DeleteNode { id });
+        if self.reject_write("delete_node") {
+            return;
+        }
+        self.apply_delta(Delta::DeleteNode { id });
     }
 
     fn get_node(&self, id: u128) -> Option<NodeRecord> {
@@ -625,18 +1347,7 @@ impl GraphStore for GraphEngine {
             (String::new(), String::new())
         };
 
-        // Формат в зависимости от типа
-        let identifier = if !name.is_empty() && !file_path.is_empty() {
-            format!("{}:{}@{}", type_name, name, file_path)
-        } else if !file_path.is_empty() {
-            format!("{}:{}", type_name, file_path)
-        } else if !name.is_empty() {
-            format!("{}:{}", type_name, name)
-        } else {
-            format!("{}:{}", type_name, id)
-        };
-
-        Some(identifier)
+        Some(node_identifier(type_name, &name, &file_path, id))
     }
 
     fn find_by_attr(&self, query: &AttrQuery) -> Vec<u128> {
@@ -660,19 +1371,19 @@ impl GraphStore for GraphEngine {
                 continue;
             }
 
-            let version_match = query.version.as_ref().map_or(true, |v| &node.version == v);
+            let version_match = query.version.as_ref().is_none_or(|v| &node.version == v);
             let type_match = match (&type_prefix, is_wildcard) {
-                (Some(prefix), true) => node.node_type.as_ref().map_or(false, |t| t.starts_with(prefix)),
-                (Some(exact), false) => node.node_type.as_ref().map_or(false, |t| t == exact),
+                (Some(prefix), true) => node.node_type.as_ref().is_some_and(|t| t.starts_with(prefix)),
+                (Some(exact), false) => node.node_type.as_ref() == Some(exact),
                 (None, _) => true,
             };
-            let file_id_match = query.file_id.map_or(true, |f| node.file_id == f);
+            let file_id_match = query.file_id.is_none_or(|f| node.file_id == f);
             // File path match (alternative to file_id)
-            let file_path_match = query.file.as_ref().map_or(true, |f| {
-                node.file.as_ref().map_or(false, |node_file| node_file == f)
+            let file_path_match = query.file.as_ref().is_none_or(|f| {
+                node.file.as_ref() == Some(f)
             });
-            let exported_match = query.exported.map_or(true, |e| node.exported == e);
-            let name_match = query.name.as_ref().map_or(true, |n| node.name.as_ref().map_or(false, |node_name| node_name == n));
+            let exported_match = query.exported.is_none_or(|e| node.exported == e);
+            let name_match = query.name.as_ref().is_none_or(|n| node.name.as_ref() == Some(n));
 
             let matches = version_match && type_match && file_id_match && file_path_match && exported_match && name_match;
 
@@ -686,7 +1397,17 @@ impl GraphStore for GraphEngine {
         // Поиск в segment (после flush)
         // Segment теперь хранит все поля включая version и exported
         if let Some(ref segment) = self.nodes_segment {
-            for idx in segment.iter_indices() {
+            // Точные строковые фильтры разрешаются в ключи словаря один раз,
+            // дальше по строкам сравниваются u32 без декодирования
+            let key = |s: &Option<String>| s.as_ref().map(|s| segment.find_string(s));
+            let type_key = if is_wildcard { None } else { key(&type_prefix) };
+            let file_key = key(&query.file);
+            let name_key = key(&query.name);
+            let version_key = key(&query.version);
+            // Строки нет в сегменте - ни одна его нода не подходит
+            let unmatched = [type_key, file_key, name_key, version_key].contains(&Some(None));
+
+            for idx in segment.iter_indices().filter(|_| !unmatched) {
                 if segment.is_deleted(idx) {
                     continue;
                 }
@@ -704,48 +1425,48 @@ impl GraphStore for GraphEngine {
                 }
 
                 // Проверка node_type с поддержкой wildcard
-                let type_match = match (&type_prefix, is_wildcard) {
-                    (Some(prefix), true) => segment.get_node_type(idx).map_or(false, |t| t.starts_with(prefix)),
-                    (Some(exact), false) => segment.get_node_type(idx).map_or(false, |t| t == exact),
-                    (None, _) => true,
+                let type_match = match (&type_prefix, type_key) {
+                    (Some(prefix), None) => segment.get_node_type(idx).is_some_and(|t| t.starts_with(prefix)),
+                    (_, Some(key)) => segment.get_type_offset(idx) == key,
+                    (None, None) => true,
                 };
                 if !type_match {
                     continue;
                 }
 
-                let file_id_match = query.file_id.map_or(true, |f| {
-                    segment.get_file_id(idx).map_or(false, |fid| fid == f)
+                let file_id_match = query.file_id.is_none_or(|f| {
+                    segment.get_file_id(idx) == Some(f)
                 });
                 if !file_id_match {
                     continue;
                 }
 
                 // File path match (alternative to file_id)
-                let file_path_match = query.file.as_ref().map_or(true, |f| {
-                    segment.get_file_path(idx).map_or(false, |path| path == f)
+                let file_path_match = file_key.is_none_or(|key| {
+                    segment.get_file_id(idx) == key.map(|k| k + 1)
                 });
                 if !file_path_match {
                     continue;
                 }
 
-                let name_match = query.name.as_ref().map_or(true, |n| {
-                    segment.get_name(idx).map_or(false, |name| name == n)
+                let name_match = name_key.is_none_or(|key| {
+                    segment.get_name_offset(idx) == key.map(|k| k + 1)
                 });
                 if !name_match {
                     continue;
                 }
 
                 // Проверка version
-                let version_match = query.version.as_ref().map_or(true, |v| {
-                    segment.get_version(idx).map_or(false, |ver| ver == v)
+                let version_match = version_key.is_none_or(|key| {
+                    segment.get_version_offset(idx) == key
                 });
                 if !version_match {
                     continue;
                 }
 
                 // Проверка exported
-                let exported_match = query.exported.map_or(true, |e| {
-                    segment.get_exported(idx).map_or(false, |exp| exp == e)
+                let exported_match = query.exported.is_none_or(|e| {
+                    segment.get_exported(idx) == Some(e)
                 });
                 if !exported_match {
                     continue;
@@ -756,7 +1477,7 @@ impl GraphStore for GraphEngine {
         }
 
         // Log summary only (not every node)
-        debug_log!("find_by_attr: node_type={:?} -> {} results ({} from delta, {} from segment)",
+        tracing::debug!("find_by_attr: node_type={:?} -> {} results ({} from delta, {} from segment)",
             query.node_type, result.len(), delta_count, result.len() - delta_count);
 
         result
@@ -769,6 +1490,9 @@ impl GraphStore for GraphEngine {
     }
 
     fn add_edges(&mut self, edges: Vec<EdgeRecord>, skip_validation: bool) {
+        if self.reject_write("add_edges") {
+            return;
+        }
         let mut added = 0;
         for edge in edges {
             // Валидация: проверяем что обе ноды существуют (если не отключена)
@@ -783,232 +1507,91 @@ impl GraphStore for GraphEngine {
                 }
             }
 
-            self.delta_log.push(Delta::AddEdge(edge.clone()));
-            self.apply_delta(&Delta::AddEdge(edge));
+            self.apply_delta(Delta::AddEdge(edge));
             added += 1;
         }
         self.ops_since_flush += added;
         self.maybe_auto_flush();
     }
 
-    fn delete_edge(&mut self, src: u128, dst: u128, edge_type: &str) {
-        let delta = Delta::DeleteEdge { src, dst, edge_type: edge_type.to_string() };
-        self.delta_log.push(delta.clone());
-        self.apply_delta(&delta);
-    }
-
-    fn neighbors(&self, id: u128, edge_types: &[&str]) -> Vec<u128> {
-        let mut result = Vec::new();
-
-        // Из segment edges
-        if let Some(ref edges_seg) = self.edges_segment {
-            if let Some(edge_indices) = self.adjacency.get(&id) {
-                for &idx in edge_indices {
-                    if idx < edges_seg.edge_count() {
-                        if let (Some(dst), false) = (
-                            edges_seg.get_dst(idx),
-                            edges_seg.is_deleted(idx),
-                        ) {
-                            let edge_type = edges_seg.get_edge_type(idx);
-                            if edge_types.is_empty() || edge_type.map_or(false, |et| edge_types.contains(&et)) {
-                                result.push(dst);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // From delta edges
-        for edge in &self.delta_edges {
-            if edge.src == id && !edge.deleted {
-                let matches = edge_types.is_empty() ||
-                    edge.edge_type.as_deref().map_or(false, |et| edge_types.contains(&et));
-                if matches {
-                    result.push(edge.dst);
-                }
-            }
-        }
-
-        result
-    }
-
-    fn bfs(&self, start: &[u128], max_depth: usize, edge_types: &[&str]) -> Vec<u128> {
-        traversal::bfs(start, max_depth, |node_id| {
-            self.neighbors(node_id, edge_types)
-        })
-    }
-
-    fn flush(&mut self) -> Result<()> {
-        if self.delta_log.is_empty() {
-            return Ok(());
-        }
-
-        eprintln!("[RUST FLUSH] Flushing {} operations to disk", self.delta_log.len());
-        eprintln!("[RUST FLUSH] Delta has {} nodes before flush", self.delta_nodes.len());
-
-        // Собираем все ноды (segment + delta)
-        let mut all_nodes = Vec::new();
-
-        // Из segment
-        // Из segment - сохраняем строки чтобы они не потерялись
-        if let Some(ref segment) = self.nodes_segment {
-            for idx in segment.iter_indices() {
-                if !segment.is_deleted(idx) {
-                    if let Some(id) = segment.get_id(idx) {
-                        // Skip nodes that were deleted (tracked in deleted_segment_ids)
-                        if self.deleted_segment_ids.contains(&id) {
-                            continue;
-                        }
-
-                        // Читаем строковые данные из StringTable если есть
-                        let node_type = segment.get_node_type(idx).map(|s| s.to_string());
-                        let name = segment.get_name(idx).map(|s| s.to_string());
-                        let file = segment.get_file_path(idx).map(|s| s.to_string());
-                        let metadata = segment.get_metadata(idx).map(|s| s.to_string());
-                        let version = segment.get_version(idx).unwrap_or("main");
-                        let exported = segment.get_exported(idx).unwrap_or(false);
-
-                        all_nodes.push(NodeRecord {
-                            id,
-                            node_type,
-                            file_id: 0, // Будет пересчитано в writer
-                            name_offset: 0, // Будет пересчитано в writer
-                            version: version.to_string(),
-                            exported,
-                            replaces: None,
-                            deleted: false,
-                            name,
-                            file,
-                            metadata,
-                        });
-                    }
-                }
-            }
-        }
-
-        let nodes_from_segment = all_nodes.len();
-        eprintln!("[RUST FLUSH] Collected {} nodes from segment", nodes_from_segment);
-
-        // From delta
-        let mut seen_ids = std::collections::HashSet::new();
-        for node in &all_nodes {
-            seen_ids.insert(node.id);
-        }
-
-        let mut delta_added = 0;
-        let mut delta_duplicates = 0;
-        for node in self.delta_nodes.values() {
-            if !node.deleted {
-                if seen_ids.contains(&node.id) {
-                    eprintln!("[RUST FLUSH] !!! Duplicate ID {} in flush - delta overwrites segment", node.id);
-                    delta_duplicates += 1;
-                }
-                all_nodes.push(node.clone());
-                delta_added += 1;
-            }
-        }
-
-        eprintln!("[RUST FLUSH] Added {} nodes from delta ({} duplicates)", delta_added, delta_duplicates);
-        eprintln!("[RUST FLUSH] Total nodes to write: {}", all_nodes.len());
-
-        // Собираем все рёбра
-        let mut all_edges = Vec::new();
-
-        // Из segment
-        if let Some(ref segment) = self.edges_segment {
-            for idx in 0..segment.edge_count() {
-                if !segment.is_deleted(idx) {
-                    if let (Some(src), Some(dst)) = (
-                        segment.get_src(idx),
-                        segment.get_dst(idx),
-                    ) {
-                        let edge_type = segment.get_edge_type(idx).map(|s| s.to_string());
-                        let metadata = segment.get_metadata(idx).map(|s| s.to_string());
-                        all_edges.push(EdgeRecord {
-                            src,
-                            dst,
-                            edge_type,
-                            version: "main".to_string(),
-                            metadata,
-                            deleted: false,
-                        });
-                    }
-                }
-            }
-        }
-
-        // From delta
-        for edge in &self.delta_edges {
-            if !edge.deleted {
-                all_edges.push(edge.clone());
-            }
-        }
-
-        // Закрываем старые segments перед перезаписью
-        self.nodes_segment = None;
-        self.edges_segment = None;
-
-        // Debug: count nodes with metadata containing "isClassMethod"
-        let class_methods = all_nodes.iter().filter(|n| {
-            n.metadata.as_ref().map_or(false, |m| m.contains("isClassMethod"))
-        }).count();
-        eprintln!("[RUST FLUSH] Nodes with isClassMethod metadata: {}", class_methods);
-
-        // Записываем на диск
-        let writer = SegmentWriter::new(&self.path);
-        writer.write_nodes(&all_nodes)?;
-        writer.write_edges(&all_edges)?;
-
-        // Обновляем metadata
-        self.metadata.node_count = all_nodes.len();
-        self.metadata.edge_count = all_edges.len();
-        self.metadata.updated_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        writer.write_metadata(&self.metadata)?;
-
-        // Очищаем delta log и deleted_segment_ids (nodes are now written to new segment)
-        self.delta_log.clear();
-        self.delta_nodes.clear();
-        self.delta_edges.clear();
-        self.deleted_segment_ids.clear();
+    fn delete_edge(&mut self, src: u128, dst: u128, edge_type: &str) {
+        if self.reject_write("delete_edge") {
+            return;
+        }
+        self.apply_delta(Delta::DeleteEdge { src, dst, edge_type: edge_type.to_string() });
+    }
 
-        // Перезагружаем segments
-        self.nodes_segment = Some(NodesSegment::open(&self.path.join("nodes.bin"))?);
-        self.edges_segment = Some(EdgesSegment::open(&self.path.join("edges.bin"))?);
+    fn neighbors(&self, id: u128, edge_types: &[&str]) -> Vec<u128> {
+        let mut result = Vec::new();
+        let segment_edge_count = self.edges_segment.as_ref().map_or(0, |s| s.edge_count());
+        let Some(edge_indices) = self.adjacency.get(&id) else {
+            return result;
+        };
 
-        // Rebuild adjacency and reverse_adjacency
-        self.adjacency.clear();
-        self.reverse_adjacency.clear();
-        if let Some(ref edges_seg) = self.edges_segment {
-            for idx in 0..edges_seg.edge_count() {
-                if edges_seg.is_deleted(idx) {
-                    continue;
+        for &idx in edge_indices {
+            if idx < segment_edge_count {
+                // Из segment edges
+                let Some(ref edges_seg) = self.edges_segment else { continue };
+                if let (Some(dst), false) = (
+                    edges_seg.get_dst(idx),
+                    edges_seg.is_deleted(idx),
+                ) {
+                    let edge_type = edges_seg.get_edge_type(idx);
+                    if edge_types.is_empty() || edge_type.is_some_and(|et| edge_types.contains(&et)) {
+                        result.push(dst);
+                    }
                 }
-                if let Some(src) = edges_seg.get_src(idx) {
-                    self.adjacency.entry(src).or_insert_with(Vec::new).push(idx);
+            } else if let Some(edge) = self.delta_edges.get(idx - segment_edge_count) {
+                // From delta edges (через adjacency, без прохода по всей delta)
+                if edge.deleted || edge.src != id {
+                    continue;
                 }
-                if let Some(dst) = edges_seg.get_dst(idx) {
-                    self.reverse_adjacency.entry(dst).or_insert_with(Vec::new).push(idx);
+                let matches = edge_types.is_empty() ||
+                    edge.edge_type.as_deref().is_some_and(|et| edge_types.contains(&et));
+                if matches {
+                    result.push(edge.dst);
                 }
             }
         }
 
-        tracing::info!("Flush complete: {} nodes, {} edges", all_nodes.len(), all_edges.len());
+        result
+    }
+
+    fn bfs(&self, start: &[u128], max_depth: usize, edge_types: &[&str]) -> Vec<u128> {
+        traversal::bfs(start, max_depth, |node_id| {
+            self.neighbors(node_id, edge_types)
+        })
+    }
 
-        // Сбросить счётчик операций
-        self.ops_since_flush = 0;
+    fn flush(&mut self) -> Result<()> {
+        if self.read_only {
+            return Err(GraphError::ReadOnly("flush"));
+        }
+        if !self.has_delta() {
+            return Ok(());
+        }
 
+        let _span = tracing::info_span!("flush", nodes = self.delta_nodes.len(), edges = self.delta_edges.len()).entered();
+        let (node_count, edge_count) = self.rewrite_segments(false)?;
+
+        tracing::info!("Flush complete: {} nodes, {} edges", node_count, edge_count);
         Ok(())
     }
 
     fn compact(&mut self) -> Result<()> {
-        tracing::info!("Compacting graph...");
-        // Compaction = flush в данной реализации
-        self.flush()
+        if self.read_only {
+            return Err(GraphError::ReadOnly("compact"));
+        }
+        if !self.has_delta() && self.nodes_segment.is_none() && self.edges_segment.is_none() {
+            return Ok(());
+        }
+
+        // Compaction = flush + strings.bin только из живых строк
+        let _span = tracing::info_span!("compact", nodes = self.delta_nodes.len(), edges = self.delta_edges.len()).entered();
+        let (node_count, edge_count) = self.rewrite_segments(true)?;
+
+        tracing::info!("Compaction complete: {} nodes, {} edges", node_count, edge_count);
+        Ok(())
     }
 
     fn node_count(&self) -> usize {
@@ -1021,65 +1604,15 @@ impl GraphStore for GraphEngine {
 
     /// Get all outgoing edges from a node
     /// Returns Vec<EdgeRecord> with edges where src == node_id
+    /// O(degree) complexity using adjacency
     fn get_outgoing_edges(&self, node_id: u128, edge_types: Option<&[&str]>) -> Vec<EdgeRecord> {
         let start = std::time::Instant::now();
-        let mut result = Vec::new();
-
-        // From delta_edges
-        for edge in &self.delta_edges {
-            if edge.deleted || edge.src != node_id {
-                continue;
-            }
-
-            // Filter by edge type if specified
-            if let Some(types) = edge_types {
-                if !edge.edge_type.as_deref().map_or(false, |et| types.contains(&et)) {
-                    continue;
-                }
-            }
-
-            result.push(edge.clone());
-        }
-
-        // From edges_segment using adjacency list
-        if let Some(edge_indices) = self.adjacency.get(&node_id) {
-            if let Some(ref edges_seg) = self.edges_segment {
-                for &idx in edge_indices {
-                    if edges_seg.is_deleted(idx) {
-                        continue;
-                    }
-
-                    if let (Some(src), Some(dst)) = (
-                        edges_seg.get_src(idx),
-                        edges_seg.get_dst(idx),
-                    ) {
-                        let edge_type = edges_seg.get_edge_type(idx);
-
-                        // Filter by edge type if specified
-                        if let Some(types) = edge_types {
-                            if !edge_type.map_or(false, |et| types.contains(&et)) {
-                                continue;
-                            }
-                        }
-
-                        let metadata = edges_seg.get_metadata(idx);
-                        result.push(EdgeRecord {
-                            src,
-                            dst,
-                            edge_type: edge_type.map(|s| s.to_string()),
-                            version: "main".to_string(), // TODO: Store version in segment
-                            metadata: metadata.map(|s| s.to_string()),
-                            deleted: false,
-                        });
-                    }
-                }
-            }
-        }
+        let result = self.adjacent_edges(self.adjacency.get(&node_id), edge_types, |edge| edge.src == node_id);
 
         let elapsed = start.elapsed();
         if elapsed.as_millis() > 50 {
-            eprintln!("[RUST SLOW] get_outgoing_edges: {}ms, found {} edges", 
-                     elapsed.as_millis(), result.len());
+            tracing::warn!("Slow get_outgoing_edges: {}ms, found {} edges",
+                elapsed.as_millis(), result.len());
         }
 
         result
@@ -1089,66 +1622,7 @@ impl GraphStore for GraphEngine {
     /// Returns Vec<EdgeRecord> with edges where dst == node_id
     /// O(degree) complexity using reverse_adjacency
     fn get_incoming_edges(&self, node_id: u128, edge_types: Option<&[&str]>) -> Vec<EdgeRecord> {
-        let mut result = Vec::new();
-        let segment_edge_count = self.edges_segment.as_ref().map_or(0, |s| s.edge_count());
-
-        // Use reverse_adjacency for O(degree) lookup
-        if let Some(edge_indices) = self.reverse_adjacency.get(&node_id) {
-            for &idx in edge_indices {
-                if idx < segment_edge_count {
-                    // Edge is in segment
-                    if let Some(ref edges_seg) = self.edges_segment {
-                        if edges_seg.is_deleted(idx) {
-                            continue;
-                        }
-
-                        if let (Some(src), Some(dst)) = (
-                            edges_seg.get_src(idx),
-                            edges_seg.get_dst(idx),
-                        ) {
-                            let edge_type = edges_seg.get_edge_type(idx);
-
-                            // Filter by edge type if specified
-                            if let Some(types) = edge_types {
-                                if !edge_type.map_or(false, |et| types.contains(&et)) {
-                                    continue;
-                                }
-                            }
-
-                            let metadata = edges_seg.get_metadata(idx);
-                            result.push(EdgeRecord {
-                                src,
-                                dst,
-                                edge_type: edge_type.map(|s| s.to_string()),
-                                version: "main".to_string(),
-                                metadata: metadata.map(|s| s.to_string()),
-                                deleted: false,
-                            });
-                        }
-                    }
-                } else {
-                    // Edge is in delta
-                    let delta_idx = idx - segment_edge_count;
-                    if delta_idx < self.delta_edges.len() {
-                        let edge = &self.delta_edges[delta_idx];
-                        if edge.deleted || edge.dst != node_id {
-                            continue;
-                        }
-
-                        // Filter by edge type if specified
-                        if let Some(types) = edge_types {
-                            if !edge.edge_type.as_deref().map_or(false, |et| types.contains(&et)) {
-                                continue;
-                            }
-                        }
-
-                        result.push(edge.clone());
-                    }
-                }
-            }
-        }
-
-        result
+        self.adjacent_edges(self.reverse_adjacency.get(&node_id), edge_types, |edge| edge.dst == node_id)
     }
 
     /// Get ALL edges from the graph (delta + segment)
@@ -1182,17 +1656,17 @@ impl GraphStore for GraphEngine {
                     let key = (src, dst, edge_type_key.clone());
 
                     // Don't overwrite delta edges (they are more recent)
-                    if !edges_map.contains_key(&key) {
+                    edges_map.entry(key).or_insert_with(|| {
                         let metadata = edges_seg.get_metadata(idx);
-                        edges_map.insert(key, EdgeRecord {
+                        EdgeRecord {
                             src,
                             dst,
                             edge_type: if edge_type_key.is_empty() { None } else { Some(edge_type_key) },
-                            version: "main".to_string(), // TODO: Store version in segment
+                            version: edges_seg.get_version(idx).unwrap_or("main").to_string(),
                             metadata: metadata.map(|s| s.to_string()),
                             deleted: false,
-                        });
-                    }
+                        }
+                    });
                 }
             }
         }
@@ -1486,6 +1960,486 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_delta_records_cleared_by_flush() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut engine = GraphEngine::create(temp_dir.path().join("test")).unwrap();
+        engine.add_nodes(vec![make_test_node(1, "a", "FUNCTION"), make_test_node(2, "b", "FUNCTION")]);
+        engine.flush().unwrap();
+        assert!(!engine.has_delta());
+
+        // Каждая запись хранится один раз: в delta_nodes, delta_edges или deleted_segment_ids
+        engine.add_nodes(vec![make_test_node(3, "c", "FUNCTION")]);
+        engine.add_edges(vec![make_test_edge(1, 3, "CALLS"), make_test_edge(3, 2, "IMPORTS")], false);
+        engine.delete_node(2);
+        engine.delete_node(3);
+        assert!(engine.has_delta());
+        assert_eq!(engine.delta_nodes.len(), 1);
+        assert!(engine.delta_nodes[&3].deleted);
+        let edges: Vec<_> = engine.delta_edges.iter().map(|e| (e.src, e.dst, e.edge_type.as_deref().unwrap())).collect();
+        assert_eq!(edges, vec![(1, 3, "CALLS"), (3, 2, "IMPORTS")]);
+        assert_eq!(engine.deleted_segment_ids, HashSet::from([2]));
+
+        engine.flush().unwrap();
+        assert!(!engine.has_delta());
+        assert!(engine.delta_nodes.is_empty() && engine.delta_edges.is_empty() && engine.deleted_segment_ids.is_empty());
+        assert_eq!(engine.get_node(1).unwrap().name.as_deref(), Some("a"));
+        assert!(engine.get_node(2).is_none() && engine.get_node(3).is_none());
+    }
+
+    #[test]
+    fn test_live_nodes_and_get_nodes_merge_segment_and_delta() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut engine = GraphEngine::create(temp_dir.path().join("test")).unwrap();
+        engine.add_nodes(vec![
+            make_test_node(3, "c", "FUNCTION"),
+            make_test_node(1, "a", "FUNCTION"),
+            make_test_node(2, "b", "FUNCTION"),
+        ]);
+        engine.flush().unwrap();
+        // delta перекрывает segment, удалённая нода сегмента не видна
+        engine.add_nodes(vec![make_test_node(1, "a2", "CLASS"), make_test_node(4, "d", "MODULE")]);
+        engine.delete_node(2);
+
+        let live: Vec<(u128, Option<&str>)> = engine.live_nodes().iter().map(|n| (n.id(), n.node_type())).collect();
+        assert_eq!(live, vec![(1, Some("CLASS")), (3, Some("FUNCTION")), (4, Some("MODULE"))]);
+
+        let mut found = engine.get_nodes(&[1, 2, 3, 99].into_iter().collect());
+        found.sort_by_key(|n| n.id);
+        let names: Vec<_> = found.iter().map(|n| (n.id, n.name.as_deref().unwrap())).collect();
+        assert_eq!(names, vec![(1, "a2"), (3, "c")]);
+    }
+
+    #[test]
+    fn test_is_endpoint_default_rules() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut engine = GraphEngine::create(temp_dir.path().join("test")).unwrap();
+
+        let mut exported_fn = make_test_node(3, "api", "FUNCTION");
+        exported_fn.exported = true;
+        engine.add_nodes(vec![
+            make_test_node(1, "query", "db:query"),
+            make_test_node(2, "helper", "FUNCTION"),
+            exported_fn,
+            make_test_node(4, "publish", "queue:publish"),
+        ]);
+
+        assert!(engine.is_endpoint(1));
+        assert!(!engine.is_endpoint(2));
+        assert!(engine.is_endpoint(3));
+        assert!(!engine.is_endpoint(4));
+        assert!(!engine.is_endpoint(999));
+    }
+
+    #[test]
+    fn test_endpoint_rules_persist_in_metadata() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test");
+
+        {
+            let mut engine = GraphEngine::create(&db_path).unwrap();
+            engine.add_nodes(vec![
+                make_test_node(1, "publish", "queue:publish"),
+                make_test_node(2, "call", "grpc:call"),
+                make_test_node(3, "query", "db:query"),
+            ]);
+            engine.flush().unwrap();
+            engine.set_endpoint_rules(vec![
+                EndpointRule::new("queue:publish"),
+                EndpointRule::new("grpc:*"),
+            ]).unwrap();
+        }
+
+        {
+            let engine = GraphEngine::open(&db_path).unwrap();
+            assert_eq!(engine.endpoint_rules().len(), 2);
+            assert!(engine.is_endpoint(1));
+            assert!(engine.is_endpoint(2));
+            assert!(!engine.is_endpoint(3));
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_corrupted_segment() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.rfdb");
+
+        {
+            let mut engine = GraphEngine::create(&db_path).unwrap();
+            engine.add_nodes(vec![
+                make_test_node(1, "query", "db:query"),
+                make_test_node(2, "call", "CALL"),
+            ]);
+            engine.add_edges(vec![make_test_edge(1, 2, "CALLS")], false);
+            engine.flush().unwrap();
+            engine.verify().unwrap();
+        }
+
+        // Портим один байт в колонке ids
+        let nodes_path = db_path.join("nodes.bin");
+        let mut bytes = fs::read(&nodes_path).unwrap();
+        bytes[crate::storage::segment::HEADER_SIZE_ON_DISK] ^= 0xff;
+        fs::write(&nodes_path, &bytes).unwrap();
+
+        let engine = GraphEngine::open(&db_path).unwrap();
+        match engine.verify() {
+            Err(GraphError::InvalidFormat(msg)) => assert!(msg.contains("nodes.bin") && msg.contains("ids"), "{}", msg),
+            other => panic!("expected InvalidFormat, got {:?}", other),
+        }
+        drop(engine);
+
+        // Обрезанные колонки - ошибка при open, а не паника при чтении
+        fs::write(&nodes_path, &bytes[..crate::storage::segment::HEADER_SIZE_ON_DISK + 20]).unwrap();
+        assert!(matches!(GraphEngine::open(&db_path), Err(GraphError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_checkpoint_and_restore() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut engine = GraphEngine::create(temp_dir.path().join("live")).unwrap();
+        engine.add_nodes(vec![
+            make_test_node(1, "a", "FUNCTION"),
+            make_test_node(2, "b", "FUNCTION"),
+        ]);
+        engine.add_edges(vec![make_test_edge(1, 2, "CALLS")], false);
+        engine.flush().unwrap();
+
+        // Пустая delta - сегменты шарятся через hard link
+        let nightly = temp_dir.path().join("nightly.rfdb");
+        engine.checkpoint(&nightly).unwrap();
+        assert!(engine.checkpoint(&nightly).is_err(), "destination must be empty");
+
+        // Несброшенная delta тоже попадает в checkpoint
+        engine.add_nodes(vec![make_test_node(3, "c", "CLASS")]);
+        let with_delta = temp_dir.path().join("with_delta.rfdb");
+        engine.checkpoint(&with_delta).unwrap();
+        assert!(GraphEngine::open_read_only(&with_delta).unwrap().get_node(3).is_some());
+
+        // Регрессия: flush после checkpoint не должен менять snapshot
+        engine.delete_node(1);
+        engine.flush().unwrap();
+        assert!(engine.get_node(1).is_none());
+
+        engine.restore(&nightly).unwrap();
+        assert!(engine.get_node(1).is_some());
+        assert!(engine.get_node(3).is_none());
+        assert_eq!(engine.get_outgoing_edges(1, None).len(), 1);
+        engine.verify().unwrap();
+        assert!(!engine.path.join(COMPACT_TMP_DIR).exists());
+
+        let snapshot = GraphEngine::open_read_only(&nightly).unwrap();
+        assert!(snapshot.get_node(1).is_some());
+        snapshot.verify().unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_format_from_v1() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("old.rfdb");
+        drop(GraphEngine::create(&db_path).unwrap());
+
+        // Файлы формата v1: встроенная StringTable без префиксов длины, без checksum footer
+        let u32s = |values: &[u32]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let u128s = |values: &[u128]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let v1_segment = |node_count: u64, edge_count: u64, columns: Vec<Vec<u8>>, strings: &[&str]| {
+            let columns = columns.concat();
+            let mut bytes = b"SGRF".to_vec();
+            bytes.extend_from_slice(&1u16.to_le_bytes());
+            bytes.extend_from_slice(&node_count.to_le_bytes());
+            bytes.extend_from_slice(&edge_count.to_le_bytes());
+            bytes.extend_from_slice(&((30 + columns.len()) as u64).to_le_bytes());
+            bytes.extend_from_slice(&columns);
+
+            let data = strings.concat();
+            let mut offsets = Vec::new();
+            strings.iter().fold(0u32, |offset, s| { offsets.push(offset); offset + s.len() as u32 });
+            bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(data.as_bytes());
+            bytes.extend_from_slice(&(offsets.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&u32s(&offsets));
+            bytes
+        };
+
+        // Строки: FUNCTION@0, a@8, b@9, main@10, test.js@14; file/name хранятся как offset+1
+        let nodes = v1_segment(2, 0, vec![
+            u128s(&[1, 2]), u32s(&[0, 0]), u32s(&[15, 15]), u32s(&[9, 10]),
+            u32s(&[10, 10]), vec![0, 0], vec![0, 0], u32s(&[0, 0]),
+        ], &["FUNCTION", "a", "b", "main", "test.js"]);
+        let edges = v1_segment(0, 1, vec![
+            u128s(&[1]), u128s(&[2]), u32s(&[0]), u32s(&[0]), vec![0],
+        ], &["CALLS"]);
+        fs::write(db_path.join("nodes.bin"), nodes).unwrap();
+        fs::write(db_path.join("edges.bin"), edges).unwrap();
+
+        let mut engine = GraphEngine::open(&db_path).unwrap();
+        assert_eq!(engine.format_version(), 1);
+        let node = engine.get_node(2).unwrap();
+        assert_eq!(node.name.as_deref(), Some("b"));
+        assert_eq!(node.file.as_deref(), Some("test.js"));
+        assert_eq!(node.node_type.as_deref(), Some("FUNCTION"));
+        assert_eq!(node.version, "main");
+
+        assert!(engine.upgrade_format().unwrap());
+        assert_eq!(engine.format_version(), FORMAT_VERSION);
+        assert!(!engine.upgrade_format().unwrap());
+        assert_eq!(engine.get_outgoing_edges(1, None).len(), 1);
+        drop(engine);
+
+        let engine = GraphEngine::open(&db_path).unwrap();
+        assert_eq!(engine.format_version(), FORMAT_VERSION);
+        assert!(db_path.join(DICTIONARY_FILE).exists());
+        assert_eq!(engine.get_node(2).unwrap().name.as_deref(), Some("b"));
+        assert_eq!(engine.get_outgoing_edges(1, None)[0].edge_type.as_deref(), Some("CALLS"));
+        engine.verify().unwrap();
+
+        // Файл из будущей версии не открывается
+        let nodes_path = db_path.join("nodes.bin");
+        drop(engine);
+        let mut bytes = fs::read(&nodes_path).unwrap();
+        bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&nodes_path, bytes).unwrap();
+        match GraphEngine::open(&db_path) {
+            Err(GraphError::InvalidFormat(msg)) => assert!(msg.contains("newer"), "{}", msg),
+            other => panic!("expected InvalidFormat, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_shared_string_dictionary() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("dict.rfdb");
+        let mut engine = GraphEngine::create(&db_path).unwrap();
+        engine.add_nodes(vec![
+            make_test_node(1, "main", "FUNCTION"),
+            make_test_node(2, "helper", "FUNCTION"),
+            make_test_node(3, "App", "CLASS"),
+        ]);
+        engine.add_edges(vec![make_test_edge(1, 2, "main")], false);
+        engine.flush().unwrap();
+
+        // "main" - и имя ноды, и версия, и тип ребра: одна строка в strings.bin
+        let strings = StringDictionary::open(&db_path).unwrap();
+        let main_id = strings.find("main").unwrap();
+        assert_eq!((0..strings.len() as u32).filter(|&id| strings.get(id) == Some("main")).count(), 1);
+
+        // Фильтры по сегменту сравнивают id строк
+        assert_eq!(engine.find_by_attr(&AttrQuery::new().name("main")), vec![1]);
+        assert_eq!(engine.find_by_attr(&AttrQuery::new().node_type("CLASS")), vec![3]);
+        assert!(engine.find_by_attr(&AttrQuery::new().name("missing")).is_empty());
+        let mut functions = engine.find_by_attr(&AttrQuery { file: Some("test.js".into()), ..AttrQuery::new().node_type("FUNC*") });
+        functions.sort();
+        assert_eq!(functions, vec![1, 2]);
+
+        // Следующий flush дописывает словарь, id существующих строк не меняются
+        engine.add_nodes(vec![make_test_node(4, "extra", "METHOD")]);
+        engine.flush().unwrap();
+        let strings = StringDictionary::open(&db_path).unwrap();
+        assert_eq!(strings.find("main"), Some(main_id));
+        assert_eq!(engine.get_node(4).unwrap().name.as_deref(), Some("extra"));
+        drop(engine);
+
+        // Сегменты не открываются с чужим strings.bin (например, после прерванного restore)
+        let mut builder = crate::storage::dictionary::DictionaryBuilder::new();
+        builder.intern("main");
+        builder.write(&db_path, Compression::None).unwrap();
+        match GraphEngine::open(&db_path) {
+            Err(e @ GraphError::DictionaryMismatch { .. }) => assert!(e.to_string().contains("strings.bin"), "{}", e),
+            other => panic!("expected DictionaryMismatch, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_compact_rebuilds_dictionary() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("compact.rfdb");
+        let mut engine = GraphEngine::create(&db_path).unwrap();
+        engine.add_nodes(vec![
+            make_test_node(1, "keep", "FUNCTION"),
+            make_test_node(2, "gone", "FUNCTION"),
+        ]);
+        engine.add_edges(vec![make_test_edge(1, 2, "CALLS")], false);
+        engine.flush().unwrap();
+        let old_id = StringDictionary::open(&db_path).unwrap().id();
+
+        // flush только дописывает: строки удалённой ноды остаются в strings.bin
+        engine.delete_node(2);
+        engine.add_nodes(vec![make_test_node(3, "fresh", "METHOD")]);
+        engine.flush().unwrap();
+        let strings = StringDictionary::open(&db_path).unwrap();
+        assert_eq!(strings.id(), old_id);
+        assert!(strings.find("gone").is_some());
+
+        engine.compact().unwrap();
+        let strings = StringDictionary::open(&db_path).unwrap();
+        assert_ne!(strings.id(), old_id);
+        assert!(strings.find("gone").is_none());
+        assert!(!db_path.join(".compact-tmp").exists());
+
+        assert_eq!(engine.get_node(1).unwrap().name.as_deref(), Some("keep"));
+        assert_eq!(engine.get_node(3).unwrap().node_type.as_deref(), Some("METHOD"));
+        drop(engine);
+        let engine = GraphEngine::open(&db_path).unwrap();
+        engine.verify().unwrap();
+        assert_eq!(engine.find_by_attr(&AttrQuery::new().name("fresh")), vec![3]);
+    }
+
+    #[test]
+    fn test_open_recovers_interrupted_compaction() {
+        use tempfile::tempdir;
+        use crate::storage::writer::COMPACT_COMMIT_MARKER;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("recover.rfdb");
+        let mut engine = GraphEngine::create(&db_path).unwrap();
+        engine.add_nodes(vec![
+            make_test_node(1, "keep", "FUNCTION"),
+            make_test_node(2, "gone", "FUNCTION"),
+        ]);
+        engine.add_edges(vec![make_test_edge(1, 2, "CALLS")], false);
+        engine.flush().unwrap();
+        engine.delete_node(2);
+        engine.flush().unwrap();
+        let (nodes, edges) = engine.snapshot_records(false);
+        drop(engine);
+
+        // Compact упал после подмены strings.bin: nodes.bin/edges.bin ещё старые
+        let tmp_dir = db_path.join(COMPACT_TMP_DIR);
+        fs::create_dir_all(&tmp_dir).unwrap();
+        SegmentWriter::new(&tmp_dir).write_segments(&nodes, &edges, &mut DictionaryBuilder::new()).unwrap();
+        fs::write(tmp_dir.join(COMPACT_COMMIT_MARKER), b"").unwrap();
+        fs::rename(tmp_dir.join(DICTIONARY_FILE), db_path.join(DICTIONARY_FILE)).unwrap();
+
+        assert!(GraphEngine::open_read_only(&db_path).is_err());
+        let engine = GraphEngine::open(&db_path).unwrap();
+        engine.verify().unwrap();
+        assert!(!tmp_dir.exists());
+        assert_eq!(engine.get_node(1).unwrap().name.as_deref(), Some("keep"));
+        assert!(engine.get_node(2).is_none());
+        assert!(StringDictionary::open(&db_path).unwrap().find("gone").is_none());
+        drop(engine);
+
+        // Без маркера файлы базы целы, недописанный результат выбрасывается
+        fs::create_dir_all(&tmp_dir).unwrap();
+        fs::write(tmp_dir.join("nodes.bin.tmp"), b"partial").unwrap();
+        let engine = GraphEngine::open(&db_path).unwrap();
+        assert!(!tmp_dir.exists());
+        assert_eq!(engine.get_node(1).unwrap().name.as_deref(), Some("keep"));
+    }
+
+    #[test]
+    fn test_compressed_segments_roundtrip() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("packed.rfdb");
+        let mut engine = GraphEngine::create(&db_path).unwrap();
+
+        let nodes: Vec<NodeRecord> = (1..=200u128).map(|i| {
+            let mut node = make_test_node(i, &format!("fn{}", i), "FUNCTION");
+            node.metadata = Some(format!(r#"{{"line":{},"async":false,"generator":false}}"#, i));
+            node
+        }).collect();
+        let edges: Vec<EdgeRecord> = (2..=200u128).map(|i| make_test_edge(1, i, "CALLS")).collect();
+        engine.add_nodes(nodes);
+        engine.add_edges(edges, false);
+        engine.flush().unwrap();
+        let plain_size = fs::metadata(db_path.join("edges.bin")).unwrap().len();
+
+        engine.set_compression(Compression::Lz4).unwrap();
+        assert_eq!(&fs::read(db_path.join("nodes.bin")).unwrap()[..4], b"SGRZ");
+        assert!(fs::metadata(db_path.join("edges.bin")).unwrap().len() < plain_size / 2);
+        assert_eq!(engine.get_outgoing_edges(1, None).len(), 199);
+        drop(engine);
+
+        // Настройка сохраняется, чтение прозрачное
+        let engine = GraphEngine::open(&db_path).unwrap();
+        assert_eq!(engine.compression(), Compression::Lz4);
+        engine.verify().unwrap();
+        let node = engine.get_node(42).unwrap();
+        assert_eq!(node.name.as_deref(), Some("fn42"));
+        assert_eq!(node.metadata.as_deref(), Some(r#"{"line":42,"async":false,"generator":false}"#));
+        assert_eq!(engine.get_incoming_edges(42, None).len(), 1);
+    }
+
+    #[test]
+    fn test_clear_keeps_compression() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("packed.rfdb");
+        let mut engine = GraphEngine::create(&db_path).unwrap();
+        engine.set_compression(Compression::Lz4).unwrap();
+
+        engine.clear();
+        engine.add_nodes(vec![make_test_node(1, "a", "FUNCTION")]);
+        engine.flush().unwrap();
+        assert_eq!(&fs::read(db_path.join("nodes.bin")).unwrap()[..4], b"SGRZ");
+        drop(engine);
+
+        let engine = GraphEngine::open(&db_path).unwrap();
+        assert_eq!(engine.compression(), Compression::Lz4);
+        assert_eq!(engine.get_node(1).unwrap().name.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_second_writer_is_locked_out() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test");
+
+        let writer = GraphEngine::create(&db_path).unwrap();
+        assert!(matches!(GraphEngine::open(&db_path), Err(GraphError::Locked { .. })));
+
+        drop(writer);
+        assert!(GraphEngine::open(&db_path).is_ok());
+    }
+
+    #[test]
+    fn test_open_read_only_alongside_writer() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test");
+
+        let mut writer = GraphEngine::create(&db_path).unwrap();
+        writer.add_nodes(vec![make_test_node(1, "query", "db:query")]);
+        writer.flush().unwrap();
+
+        let mut reader = GraphEngine::open_read_only(&db_path).unwrap();
+        assert!(reader.is_read_only());
+        assert!(reader.get_node(1).is_some());
+
+        // Записи игнорируются, flush/compact - ошибка
+        reader.add_nodes(vec![make_test_node(2, "call", "CALL")]);
+        assert!(reader.get_node(2).is_none());
+        assert!(matches!(reader.flush(), Err(GraphError::ReadOnly("flush"))));
+        assert!(matches!(reader.compact(), Err(GraphError::ReadOnly("compact"))));
+        assert!(reader.set_endpoint_rules(vec![]).is_err());
+        let metrics = std::collections::HashMap::from([(1u128, 0.5f64)]);
+        assert!(matches!(reader.write_node_metrics("rank", &metrics), Err(GraphError::ReadOnly(_))));
+
+        assert!(GraphEngine::open_read_only(temp_dir.path().join("missing")).is_err());
+    }
+
     #[test]
     fn test_reverse_adjacency_basic() {
         // Graph: A --CALLS--> B, C --CALLS--> B, D --IMPORTS--> B
@@ -1653,6 +2607,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_edges_from_segment_and_delta() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let mut engine = GraphEngine::create(temp_dir.path().join("test")).unwrap();
+        engine.add_nodes(vec![
+            make_test_node(1, "A", "FUNCTION"),
+            make_test_node(2, "B", "FUNCTION"),
+            make_test_node(3, "C", "FUNCTION"),
+        ]);
+        engine.add_edges(vec![make_test_edge(1, 2, "CALLS")], false);
+        engine.flush().unwrap();
+        engine.add_edges(vec![make_test_edge(1, 3, "IMPORTS"), make_test_edge(3, 2, "CALLS")], false);
+
+        let targets = |edges: Vec<EdgeRecord>| edges.iter().map(|e| (e.src, e.dst)).collect::<Vec<_>>();
+        assert_eq!(targets(engine.get_outgoing_edges(1, None)), vec![(1, 2), (1, 3)]);
+        assert_eq!(targets(engine.get_outgoing_edges(1, Some(&["IMPORTS"]))), vec![(1, 3)]);
+        assert_eq!(targets(engine.get_incoming_edges(2, None)), vec![(1, 2), (3, 2)]);
+        assert_eq!(targets(engine.get_incoming_edges(2, Some(&["CALLS"]))), vec![(1, 2), (3, 2)]);
+        assert!(engine.get_outgoing_edges(2, None).is_empty());
+    }
+
     #[test]
     fn test_reachability_edge_type_filter() {
         // A --CALLS--> B, A --IMPORTS--> C, B --CALLS--> D
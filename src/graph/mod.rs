@@ -1,11 +1,26 @@
 //! Граф API и реализация
 
 pub mod engine;
+pub mod algorithms;
+pub mod analysis;
 pub mod traversal;
 pub mod id_gen;
+/// Правила endpoint'ов хранятся в metadata.json, поэтому живут в storage
+pub use crate::storage::endpoint;
+pub mod jsonl;
+pub mod neo4j;
+pub mod subgraph;
 
 pub use engine::GraphEngine;
+pub use crate::storage::normalize_db_path;
+pub use analysis::{DeadCodeQuery, DeadCodeReport, ImpactReport, TEST_FILE_PATTERNS};
+pub use algorithms::{BetweennessConfig, DegreeDistribution, NodeDegree, OrphanQuery, PageRankConfig};
+pub use traversal::{CycleComponent, Direction, Dominators, GraphPath, PathQuery, TopoOrder};
 pub use id_gen::{compute_node_id, string_id_to_u128};
+pub use endpoint::EndpointRule;
+pub use jsonl::{ExportFilter, JsonlStats};
+pub use neo4j::{Neo4jCsvImport, Neo4jIdMode, Neo4jImportStats};
+pub use subgraph::{Subgraph, SubgraphFormat, SubgraphQuery};
 
 use crate::storage::{NodeRecord, EdgeRecord, AttrQuery};
 use crate::error::Result;
@@ -0,0 +1,619 @@
+//! Импорт CSV в формате `neo4j-admin import`
+//!
+//! Ноды: header вида `personId:ID(Person),name,age:int,:LABEL`,
+//! связи: `:START_ID(Person),since:int,:END_ID(Person),:TYPE`.
+//! Первый label -> `node_type`, свойства -> metadata JSON (с учётом типов
+//! из header). Свойства `name`, `file`, `exported` попадают в одноимённые
+//! поля `NodeRecord`. Ids внутри одной ID-группы должны быть уникальны,
+//! вычисленные u128 (`Neo4jIdMode::Computed`) - тоже.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde_json::{Map, Number, Value};
+use crate::error::{GraphError, Result};
+use crate::storage::{NodeRecord, EdgeRecord};
+use super::{GraphEngine, GraphStore, compute_node_id, string_id_to_u128};
+
+/// Нод/связей в одном add_nodes/add_edges (после разбора всех файлов)
+const IMPORT_BATCH: usize = 10_000;
+
+/// Как получить u128 id ноды
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Neo4jIdMode {
+    /// `string_id_to_u128("Group:id")` (или просто id без группы)
+    #[default]
+    Hashed,
+    /// `compute_node_id(type, name, "", file)`: совпадает с id от анализатора
+    Computed,
+}
+
+impl std::str::FromStr for Neo4jIdMode {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hashed" => Ok(Neo4jIdMode::Hashed),
+            "computed" => Ok(Neo4jIdMode::Computed),
+            other => Err(GraphError::InvalidFormat(format!("Unknown id mode: {}", other))),
+        }
+    }
+}
+
+/// CSV файл и labels/тип связи по умолчанию (`--nodes=Person=persons.csv`)
+#[derive(Debug, Clone)]
+struct CsvSource {
+    path: PathBuf,
+    labels: Vec<String>,
+}
+
+/// Настройки импорта (builder)
+#[derive(Debug, Clone)]
+pub struct Neo4jCsvImport {
+    nodes: Vec<CsvSource>,
+    relationships: Vec<CsvSource>,
+    delimiter: u8,
+    array_delimiter: char,
+    id_mode: Neo4jIdMode,
+    version: String,
+    skip_bad_relationships: bool,
+}
+
+impl Default for Neo4jCsvImport {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            relationships: Vec::new(),
+            delimiter: b',',
+            array_delimiter: ';',
+            id_mode: Neo4jIdMode::default(),
+            version: "main".to_string(),
+            skip_bad_relationships: false,
+        }
+    }
+}
+
+impl Neo4jCsvImport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Файл нод; labels берутся из колонки `:LABEL`
+    pub fn nodes(self, path: impl Into<PathBuf>) -> Self {
+        self.nodes_with_labels(path, Vec::new())
+    }
+
+    /// Файл нод с labels для всех строк (перед labels из `:LABEL`)
+    pub fn nodes_with_labels(mut self, path: impl Into<PathBuf>, labels: Vec<String>) -> Self {
+        self.nodes.push(CsvSource { path: path.into(), labels });
+        self
+    }
+
+    /// Файл связей; тип берётся из колонки `:TYPE`
+    pub fn relationships(mut self, path: impl Into<PathBuf>) -> Self {
+        self.relationships.push(CsvSource { path: path.into(), labels: Vec::new() });
+        self
+    }
+
+    /// Файл связей с типом по умолчанию (если нет `:TYPE` или он пустой)
+    pub fn relationships_with_type(mut self, path: impl Into<PathBuf>, edge_type: impl Into<String>) -> Self {
+        self.relationships.push(CsvSource { path: path.into(), labels: vec![edge_type.into()] });
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Разделитель labels и элементов массивов (по умолчанию `;`)
+    pub fn array_delimiter(mut self, delimiter: char) -> Self {
+        self.array_delimiter = delimiter;
+        self
+    }
+
+    pub fn id_mode(mut self, mode: Neo4jIdMode) -> Self {
+        self.id_mode = mode;
+        self
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Пропускать связи с неизвестными концами вместо ошибки
+    pub fn skip_bad_relationships(mut self, skip: bool) -> Self {
+        self.skip_bad_relationships = skip;
+        self
+    }
+}
+
+/// Сколько записей импортировано
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Neo4jImportStats {
+    pub nodes: usize,
+    pub relationships: usize,
+    /// Связи с неизвестными концами (при `skip_bad_relationships`)
+    pub skipped_relationships: usize,
+}
+
+/// Тип значения свойства из header (`age:int`, `tags:string[]`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PropType {
+    Int,
+    Float,
+    Bool,
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Column {
+    /// `:ID(Group)`; с именем (`personId:ID`) значение сохраняется и как свойство
+    Id { group: Option<String>, property: Option<String> },
+    StartId { group: Option<String> },
+    EndId { group: Option<String> },
+    Label,
+    Type,
+    Ignore,
+    Property { name: String, ty: PropType, array: bool },
+}
+
+/// Разобрать одну колонку header: `name:type(group)`
+fn parse_column(field: &str) -> Result<Column> {
+    let (name, spec) = match field.find('(') {
+        Some(paren) => match field[..paren].rfind(':') {
+            Some(colon) => (&field[..colon], &field[colon + 1..]),
+            None => (field, ""),
+        },
+        None => field.rsplit_once(':').unwrap_or((field, "")),
+    };
+    let (kind, group) = match spec.split_once('(') {
+        Some((kind, rest)) => {
+            let group = rest.strip_suffix(')').ok_or_else(|| GraphError::InvalidFormat(
+                format!("Bad header column '{}': unclosed '('", field)
+            ))?;
+            (kind, Some(group.to_string()).filter(|g| !g.is_empty()))
+        }
+        None => (spec, None),
+    };
+    let name = name.to_string();
+
+    let column = match kind.to_ascii_uppercase().as_str() {
+        "ID" => Column::Id { group, property: Some(name).filter(|n| !n.is_empty()) },
+        "START_ID" => Column::StartId { group },
+        "END_ID" => Column::EndId { group },
+        "LABEL" => Column::Label,
+        "TYPE" => Column::Type,
+        "IGNORE" => Column::Ignore,
+        _ => {
+            if name.is_empty() {
+                return Err(GraphError::InvalidFormat(format!("Bad header column '{}': empty property name", field)));
+            }
+            let lower = kind.to_ascii_lowercase();
+            let (base, array) = match lower.strip_suffix("[]") {
+                Some(base) => (base, true),
+                None => (lower.as_str(), false),
+            };
+            let ty = match base {
+                "" | "string" | "char" | "date" | "time" | "localtime" | "datetime"
+                | "localdatetime" | "duration" | "point" => PropType::String,
+                "int" | "long" | "short" | "byte" => PropType::Int,
+                "float" | "double" => PropType::Float,
+                "boolean" => PropType::Bool,
+                other => return Err(GraphError::InvalidFormat(
+                    format!("Bad header column '{}': unknown type '{}'", field, other)
+                )),
+            };
+            Column::Property { name, ty, array }
+        }
+    };
+    Ok(column)
+}
+
+fn parse_scalar(raw: &str, ty: PropType) -> Option<Value> {
+    match ty {
+        PropType::String => Some(Value::String(raw.to_string())),
+        PropType::Int => raw.trim().parse::<i64>().ok().map(Value::from),
+        PropType::Float => raw.trim().parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+        PropType::Bool => match raw.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    }
+}
+
+/// Открытый CSV файл с разобранным header
+struct CsvFile {
+    path: PathBuf,
+    reader: csv::Reader<std::fs::File>,
+    columns: Vec<Column>,
+}
+
+impl CsvFile {
+    fn open(path: &Path, import: &Neo4jCsvImport) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(import.delimiter)
+            .from_path(path)
+            .map_err(|e| csv_error(path, e))?;
+        let columns = reader.headers()
+            .map_err(|e| csv_error(path, e))?
+            .iter()
+            .map(parse_column)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| GraphError::InvalidFormat(format!("{}: {}", path.display(), e)))?;
+        Ok(Self { path: path.to_path_buf(), reader, columns })
+    }
+
+    fn has(&self, pred: impl Fn(&Column) -> bool) -> bool {
+        self.columns.iter().any(pred)
+    }
+
+    fn error(&self, line: u64, message: impl std::fmt::Display) -> GraphError {
+        GraphError::InvalidFormat(format!("{}:{}: {}", self.path.display(), line, message))
+    }
+
+    /// Значение свойства по типу колонки; пустая строка - свойства нет
+    fn property(&self, line: u64, name: &str, raw: &str, ty: PropType, array: bool, array_delimiter: char) -> Result<Option<Value>> {
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        let parse = |item: &str| parse_scalar(item, ty)
+            .ok_or_else(|| self.error(line, format!("column '{}': invalid {:?} value '{}'", name, ty, item)));
+        if array {
+            let items = raw.split(array_delimiter).map(parse).collect::<Result<Vec<_>>>()?;
+            Ok(Some(Value::Array(items)))
+        } else {
+            parse(raw).map(Some)
+        }
+    }
+}
+
+fn csv_error(path: &Path, e: csv::Error) -> GraphError {
+    match e.into_kind() {
+        csv::ErrorKind::Io(e) => GraphError::Io(e),
+        kind => GraphError::InvalidFormat(format!("{}: {:?}", path.display(), kind)),
+    }
+}
+
+fn take_string(props: &mut Map<String, Value>, key: &str) -> Option<String> {
+    match props.get(key) {
+        Some(Value::String(_)) => match props.remove(key) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn metadata_json(props: Map<String, Value>) -> Option<String> {
+    (!props.is_empty()).then(|| Value::Object(props).to_string())
+}
+
+impl GraphEngine {
+    /// Импортировать CSV в формате neo4j-admin: сначала все ноды, потом связи
+    ///
+    /// Все файлы сначала разбираются целиком, в delta записи попадают только
+    /// если ошибок не было; сохранить их - `flush()`.
+    pub fn import_neo4j_csv(&mut self, import: &Neo4jCsvImport) -> Result<Neo4jImportStats> {
+        if self.is_read_only() {
+            return Err(GraphError::ReadOnly("import_neo4j_csv"));
+        }
+        let _span = tracing::info_span!("import_neo4j_csv",
+            node_files = import.nodes.len(), relationship_files = import.relationships.len()).entered();
+        let mut stats = Neo4jImportStats::default();
+        // (ID-группа, исходный id) -> u128
+        let mut ids: HashMap<(Option<String>, String), u128> = HashMap::new();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        for source in &import.nodes {
+            import_neo4j_nodes(source, import, &mut ids, &mut nodes)?;
+        }
+        for source in &import.relationships {
+            stats.skipped_relationships += import_neo4j_relationships(source, import, &ids, &mut edges)?;
+        }
+
+        stats.nodes = nodes.len();
+        stats.relationships = edges.len();
+        let mut nodes = nodes.into_iter();
+        loop {
+            let batch: Vec<NodeRecord> = nodes.by_ref().take(IMPORT_BATCH).collect();
+            if batch.is_empty() {
+                break;
+            }
+            self.add_nodes(batch);
+        }
+        let mut edges = edges.into_iter();
+        loop {
+            let batch: Vec<EdgeRecord> = edges.by_ref().take(IMPORT_BATCH).collect();
+            if batch.is_empty() {
+                break;
+            }
+            self.add_edges(batch, true);
+        }
+
+        tracing::info!("Imported {} nodes, {} relationships ({} skipped)",
+            stats.nodes, stats.relationships, stats.skipped_relationships);
+        Ok(stats)
+    }
+}
+
+/// Разобрать файл нод в `nodes`; `ids` - (ID-группа, id) -> u128 по всем файлам
+fn import_neo4j_nodes(
+    source: &CsvSource,
+    import: &Neo4jCsvImport,
+    ids: &mut HashMap<(Option<String>, String), u128>,
+    nodes: &mut Vec<NodeRecord>,
+) -> Result<()> {
+    let mut file = CsvFile::open(&source.path, import)?;
+    if !file.has(|c| matches!(c, Column::Id { .. })) {
+        return Err(GraphError::InvalidFormat(format!("{}: no :ID column", source.path.display())));
+    }
+
+    // u128 -> ключ, который его занял: в Computed режиме разные ключи могут дать один id
+    let mut owners: HashMap<u128, (Option<String>, String)> = ids.iter().map(|(k, &id)| (id, k.clone())).collect();
+    let mut record = csv::StringRecord::new();
+    while file.reader.read_record(&mut record).map_err(|e| csv_error(&source.path, e))? {
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let mut key = None;
+        let mut labels = source.labels.clone();
+        let mut props = Map::new();
+
+        for (column, raw) in file.columns.iter().zip(record.iter()) {
+            match column {
+                Column::Id { group, property } => {
+                    if raw.is_empty() {
+                        return Err(file.error(line, "empty :ID"));
+                    }
+                    if let Some(property) = property {
+                        props.insert(property.clone(), Value::String(raw.to_string()));
+                    }
+                    key = Some((group.clone(), raw.to_string()));
+                }
+                Column::Label => {
+                    labels.extend(raw.split(import.array_delimiter)
+                        .filter(|l| !l.is_empty())
+                        .map(str::to_string));
+                }
+                Column::Property { name, ty, array } => {
+                    if let Some(value) = file.property(line, name, raw, *ty, *array, import.array_delimiter)? {
+                        props.insert(name.clone(), value);
+                    }
+                }
+                Column::Ignore => {}
+                Column::StartId { .. } | Column::EndId { .. } | Column::Type => {
+                    return Err(file.error(line, "relationship column in a node file"));
+                }
+            }
+        }
+        let Some(key) = key else {
+            return Err(file.error(line, "missing :ID"));
+        };
+
+        let mut labels_iter = labels.into_iter();
+        let node_type = labels_iter.next();
+        let extra_labels: Vec<Value> = labels_iter.map(Value::String).collect();
+        if !extra_labels.is_empty() {
+            props.insert("labels".to_string(), Value::Array(extra_labels));
+        }
+        let name = take_string(&mut props, "name");
+        let file_path = take_string(&mut props, "file");
+        let exported = match props.get("exported") {
+            Some(Value::Bool(b)) => {
+                let b = *b;
+                props.remove("exported");
+                b
+            }
+            _ => false,
+        };
+
+        let id = match import.id_mode {
+            Neo4jIdMode::Hashed => match key.0 {
+                Some(ref group) => string_id_to_u128(&format!("{}:{}", group, key.1)),
+                None => string_id_to_u128(&key.1),
+            },
+            Neo4jIdMode::Computed => compute_node_id(
+                node_type.as_deref().unwrap_or(""),
+                name.as_deref().unwrap_or(&key.1),
+                "",
+                file_path.as_deref().unwrap_or(""),
+            ),
+        };
+        if ids.contains_key(&key) {
+            return Err(file.error(line, format!("duplicate id '{}'", key.1)));
+        }
+        if let Some(owner) = owners.get(&id) {
+            return Err(file.error(line, format!("id '{}' maps to the same node id as '{}'", key.1, owner.1)));
+        }
+        owners.insert(id, key.clone());
+        ids.insert(key.clone(), id);
+
+        nodes.push(NodeRecord {
+            id,
+            node_type,
+            file_id: 0,
+            name_offset: 0,
+            version: import.version.clone(),
+            exported,
+            replaces: None,
+            deleted: false,
+            name: Some(name.unwrap_or(key.1)),
+            file: file_path,
+            metadata: metadata_json(props),
+        });
+    }
+    Ok(())
+}
+
+/// Разобрать файл связей в `edges`; возвращает число пропущенных связей
+fn import_neo4j_relationships(
+    source: &CsvSource,
+    import: &Neo4jCsvImport,
+    ids: &HashMap<(Option<String>, String), u128>,
+    edges: &mut Vec<EdgeRecord>,
+) -> Result<usize> {
+    let mut file = CsvFile::open(&source.path, import)?;
+    if !file.has(|c| matches!(c, Column::StartId { .. })) || !file.has(|c| matches!(c, Column::EndId { .. })) {
+        return Err(GraphError::InvalidFormat(format!("{}: needs :START_ID and :END_ID columns", source.path.display())));
+    }
+
+    let mut skipped = 0;
+    let mut record = csv::StringRecord::new();
+    while file.reader.read_record(&mut record).map_err(|e| csv_error(&source.path, e))? {
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let (mut src, mut dst) = (None, None);
+        let mut edge_type = source.labels.first().cloned();
+        let mut props = Map::new();
+
+        for (column, raw) in file.columns.iter().zip(record.iter()) {
+            match column {
+                Column::StartId { group } => src = Some((group.clone(), raw.to_string())),
+                Column::EndId { group } => dst = Some((group.clone(), raw.to_string())),
+                Column::Type if !raw.is_empty() => edge_type = Some(raw.to_string()),
+                Column::Property { name, ty, array } => {
+                    if let Some(value) = file.property(line, name, raw, *ty, *array, import.array_delimiter)? {
+                        props.insert(name.clone(), value);
+                    }
+                }
+                Column::Type | Column::Ignore => {}
+                Column::Id { .. } | Column::Label => {
+                    return Err(file.error(line, "node column in a relationship file"));
+                }
+            }
+        }
+
+        let resolve = |key: Option<(Option<String>, String)>| key.and_then(|k| ids.get(&k).copied());
+        let (Some(src_id), Some(dst_id)) = (resolve(src.clone()), resolve(dst.clone())) else {
+            if import.skip_bad_relationships {
+                skipped += 1;
+                continue;
+            }
+            let missing = if resolve(src.clone()).is_none() { src } else { dst };
+            return Err(file.error(line, format!(
+                "unknown node id '{}'", missing.map(|k| k.1).unwrap_or_default()
+            )));
+        };
+
+        edges.push(EdgeRecord {
+            src: src_id,
+            dst: dst_id,
+            edge_type,
+            version: import.version.clone(),
+            metadata: metadata_json(props),
+            deleted: false,
+        });
+    }
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::AttrQuery;
+    use tempfile::TempDir;
+
+    fn write(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_header_columns() {
+        assert_eq!(parse_column(":ID(Person)").unwrap(), Column::Id { group: Some("Person".into()), property: None });
+        assert_eq!(parse_column("personId:ID").unwrap(), Column::Id { group: None, property: Some("personId".into()) });
+        assert_eq!(parse_column(":END_ID(Person)").unwrap(), Column::EndId { group: Some("Person".into()) });
+        assert_eq!(parse_column("age:int").unwrap(), Column::Property { name: "age".into(), ty: PropType::Int, array: false });
+        assert_eq!(parse_column("tags:string[]").unwrap(), Column::Property { name: "tags".into(), ty: PropType::String, array: true });
+        assert_eq!(parse_column("name").unwrap(), Column::Property { name: "name".into(), ty: PropType::String, array: false });
+        assert!(parse_column("x:blob").is_err());
+    }
+
+    #[test]
+    fn test_import_nodes_and_relationships() {
+        let dir = TempDir::new().unwrap();
+        let nodes = write(&dir, "functions.csv", "\
+fnId:ID(Fn),name,file,exported:boolean,line:int,tags:string[],:LABEL
+f1,main,src/app.js,true,3,entry;hot,FUNCTION
+f2,parse,src/app.js,false,10,,FUNCTION;Pure
+");
+        let modules = write(&dir, "modules.csv", ":ID(Mod),name\nm1,app\n");
+        let calls = write(&dir, "calls.csv", "\
+:START_ID(Fn),:END_ID(Fn),:TYPE,argc:int
+f1,f2,CALLS,2
+f2,f9,CALLS,0
+");
+        let contains = write(&dir, "contains.csv", ":START_ID(Mod),:END_ID(Fn)\nm1,f1\n");
+
+        let mut engine = GraphEngine::create(dir.path().join("g.rfdb")).unwrap();
+        let import = Neo4jCsvImport::new()
+            .nodes(&nodes)
+            .nodes_with_labels(&modules, vec!["MODULE".into()])
+            .relationships(&calls)
+            .relationships_with_type(&contains, "CONTAINS");
+
+        // f9 не существует
+        let err = engine.import_neo4j_csv(&import).unwrap_err();
+        assert!(err.to_string().contains("calls.csv:3: unknown node id 'f9'"), "{}", err);
+        // Неудачный импорт ничего не оставляет в delta
+        assert_eq!(engine.node_count(), 0);
+        assert_eq!(engine.edge_count(), 0);
+
+        let mut engine = GraphEngine::create(dir.path().join("g2.rfdb")).unwrap();
+        let stats = engine.import_neo4j_csv(&import.clone().skip_bad_relationships(true)).unwrap();
+        assert_eq!(stats, Neo4jImportStats { nodes: 3, relationships: 2, skipped_relationships: 1 });
+
+        let main = engine.get_node(string_id_to_u128("Fn:f1")).unwrap();
+        assert_eq!(main.node_type.as_deref(), Some("FUNCTION"));
+        assert_eq!(main.name.as_deref(), Some("main"));
+        assert_eq!(main.file.as_deref(), Some("src/app.js"));
+        assert!(main.exported);
+        let meta: Value = serde_json::from_str(main.metadata.as_deref().unwrap()).unwrap();
+        assert_eq!(meta["line"], 3);
+        assert_eq!(meta["tags"], serde_json::json!(["entry", "hot"]));
+        assert_eq!(meta["fnId"], "f1");
+
+        let parse = engine.get_node(string_id_to_u128("Fn:f2")).unwrap();
+        let meta: Value = serde_json::from_str(parse.metadata.as_deref().unwrap()).unwrap();
+        assert_eq!(meta["labels"], serde_json::json!(["Pure"]));
+        assert!(meta.get("tags").is_none());
+
+        assert_eq!(engine.find_by_attr(&AttrQuery::new().node_type("MODULE")), vec![string_id_to_u128("Mod:m1")]);
+        let out = engine.get_outgoing_edges(string_id_to_u128("Fn:f1"), None);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].edge_type.as_deref(), Some("CALLS"));
+        assert_eq!(out[0].metadata.as_deref(), Some(r#"{"argc":2}"#));
+        let contains = engine.get_outgoing_edges(string_id_to_u128("Mod:m1"), None);
+        assert_eq!(contains[0].edge_type.as_deref(), Some("CONTAINS"));
+    }
+
+    #[test]
+    fn test_computed_ids_and_bad_values() {
+        let dir = TempDir::new().unwrap();
+        let nodes = write(&dir, "n.csv", ":ID|name|file|:LABEL\n1|getUser|src/users.js|FUNCTION\n");
+        let mut engine = GraphEngine::create(dir.path().join("g.rfdb")).unwrap();
+        engine.import_neo4j_csv(&Neo4jCsvImport::new()
+            .nodes(&nodes)
+            .delimiter(b'|')
+            .id_mode("computed".parse().unwrap())).unwrap();
+        assert!(engine.node_exists(compute_node_id("FUNCTION", "getUser", "", "src/users.js")));
+
+        let bad = write(&dir, "bad.csv", ":ID,age:int\n1,old\n");
+        let err = engine.import_neo4j_csv(&Neo4jCsvImport::new().nodes(&bad)).unwrap_err();
+        assert!(err.to_string().contains("bad.csv:2: column 'age'"), "{}", err);
+
+        let dup = write(&dir, "dup.csv", ":ID\n1\n1\n");
+        let err = engine.import_neo4j_csv(&Neo4jCsvImport::new().nodes(&dup)).unwrap_err();
+        assert!(err.to_string().contains("duplicate id '1'"), "{}", err);
+
+        // Разные ключи, но одинаковые type/name/file -> один computed id
+        let clash = write(&dir, "clash.csv", ":ID,name,file,:LABEL\na,init,src/a.js,FUNCTION\nb,init,src/a.js,FUNCTION\n");
+        let mut engine = GraphEngine::create(dir.path().join("g2.rfdb")).unwrap();
+        let err = engine.import_neo4j_csv(&Neo4jCsvImport::new()
+            .nodes(&clash)
+            .id_mode(Neo4jIdMode::Computed)).unwrap_err();
+        assert!(err.to_string().contains("clash.csv:3: id 'b' maps to the same node id as 'a'"), "{}", err);
+        assert_eq!(engine.node_count(), 0);
+    }
+}
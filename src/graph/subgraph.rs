@@ -0,0 +1,271 @@
+//! Экспорт подграфов в GraphML (yEd, Gephi) и Graphviz DOT
+//!
+//! Подграф - ноды, достижимые из стартовых за `max_depth` шагов по рёбрам
+//! выбранных типов (как `reachability`), и рёбра этих типов между ними.
+//! Подписи нод - `get_node_identifier`, подписи рёбер - тип ребра.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use crate::error::{GraphError, Result};
+use crate::storage::{EdgeRecord, NodeRecord};
+use super::{GraphEngine, GraphStore};
+use super::engine::node_identifier;
+
+/// Формат вывода подграфа
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubgraphFormat {
+    GraphMl,
+    Dot,
+}
+
+impl std::str::FromStr for SubgraphFormat {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "graphml" => Ok(SubgraphFormat::GraphMl),
+            "dot" => Ok(SubgraphFormat::Dot),
+            other => Err(GraphError::InvalidFormat(format!("Unknown subgraph format: {}", other))),
+        }
+    }
+}
+
+/// Какой подграф выбрать
+#[derive(Debug, Clone, Default)]
+pub struct SubgraphQuery {
+    pub start: Vec<u128>,
+    pub max_depth: usize,
+    /// Типы рёбер для обхода и вывода (пусто - все)
+    pub edge_types: Vec<String>,
+    /// Обход по входящим рёбрам (кто зависит от start)
+    pub backward: bool,
+}
+
+impl SubgraphQuery {
+    pub fn new(start: Vec<u128>, max_depth: usize) -> Self {
+        Self { start, max_depth, ..Self::default() }
+    }
+
+    pub fn edge_types(mut self, types: Vec<String>) -> Self {
+        self.edge_types = types;
+        self
+    }
+
+    pub fn backward(mut self, backward: bool) -> Self {
+        self.backward = backward;
+        self
+    }
+}
+
+/// Нода подграфа с готовой подписью
+#[derive(Debug, Clone)]
+pub struct SubgraphNode {
+    pub id: u128,
+    pub label: String,
+    pub node_type: Option<String>,
+}
+
+/// Выбранный подграф
+#[derive(Debug, Clone, Default)]
+pub struct Subgraph {
+    pub nodes: Vec<SubgraphNode>,
+    pub edges: Vec<EdgeRecord>,
+}
+
+impl Subgraph {
+    /// Записать в выбранном формате
+    pub fn write<W: Write>(&self, writer: W, format: SubgraphFormat) -> Result<()> {
+        match format {
+            SubgraphFormat::GraphMl => self.write_graphml(writer),
+            SubgraphFormat::Dot => self.write_dot(writer),
+        }
+    }
+
+    /// GraphML: атрибуты label/type у нод и label у рёбер
+    pub fn write_graphml<W: Write>(&self, mut w: W) -> Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+        writeln!(w, r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#)?;
+        writeln!(w, r#"  <key id="type" for="node" attr.name="type" attr.type="string"/>"#)?;
+        writeln!(w, r#"  <key id="edge_label" for="edge" attr.name="label" attr.type="string"/>"#)?;
+        writeln!(w, r#"  <graph id="rfdb" edgedefault="directed">"#)?;
+
+        for node in &self.nodes {
+            writeln!(w, r#"    <node id="n{}">"#, node.id)?;
+            writeln!(w, r#"      <data key="label">{}</data>"#, xml_escape(&node.label))?;
+            if let Some(ref node_type) = node.node_type {
+                writeln!(w, r#"      <data key="type">{}</data>"#, xml_escape(node_type))?;
+            }
+            writeln!(w, "    </node>")?;
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            writeln!(w, r#"    <edge id="e{}" source="n{}" target="n{}">"#, i, edge.src, edge.dst)?;
+            if let Some(ref edge_type) = edge.edge_type {
+                writeln!(w, r#"      <data key="edge_label">{}</data>"#, xml_escape(edge_type))?;
+            }
+            writeln!(w, "    </edge>")?;
+        }
+
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Graphviz DOT (digraph)
+    pub fn write_dot<W: Write>(&self, mut w: W) -> Result<()> {
+        writeln!(w, "digraph rfdb {{")?;
+        writeln!(w, "  node [shape=box];")?;
+        for node in &self.nodes {
+            writeln!(w, "  n{} [label=\"{}\"];", node.id, dot_escape(&node.label))?;
+        }
+        for edge in &self.edges {
+            match edge.edge_type {
+                Some(ref edge_type) => writeln!(w, "  n{} -> n{} [label=\"{}\"];", edge.src, edge.dst, dot_escape(edge_type))?,
+                None => writeln!(w, "  n{} -> n{};", edge.src, edge.dst)?,
+            }
+        }
+        writeln!(w, "}}")?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl GraphEngine {
+    /// Выбрать подграф: reachability от `query.start` + рёбра между найденными нодами
+    pub fn subgraph(&self, query: &SubgraphQuery) -> Subgraph {
+        let edge_types: Vec<&str> = query.edge_types.iter().map(|s| s.as_str()).collect();
+        let ids = self.reachability(&query.start, query.max_depth, &edge_types, query.backward);
+        // Ноды ищутся одним проходом; reachability возвращает и несуществующие старты
+        let mut found: HashMap<u128, NodeRecord> = self.get_nodes(&ids.iter().copied().collect())
+            .into_iter()
+            .map(|n| (n.id, n))
+            .collect();
+        let nodes: Vec<SubgraphNode> = ids.iter()
+            .filter_map(|id| found.remove(id))
+            .map(|node| {
+                let label = node_identifier(
+                    node.node_type.as_deref().unwrap_or("UNKNOWN"),
+                    node.name.as_deref().unwrap_or(""),
+                    node.file.as_deref().unwrap_or(""),
+                    node.id,
+                );
+                SubgraphNode { id: node.id, label, node_type: node.node_type }
+            })
+            .collect();
+        // Рёбра только между объявленными нодами
+        let included: HashSet<u128> = nodes.iter().map(|n| n.id).collect();
+
+        let filter = (!edge_types.is_empty()).then_some(&edge_types[..]);
+        let mut edges: Vec<EdgeRecord> = nodes.iter()
+            .flat_map(|n| self.get_outgoing_edges(n.id, filter))
+            .filter(|e| included.contains(&e.dst))
+            .collect();
+        edges.sort_by(|a, b| (a.src, a.dst, &a.edge_type).cmp(&(b.src, b.dst, &b.edge_type)));
+        edges.dedup_by(|a, b| (a.src, a.dst, &a.edge_type) == (b.src, b.dst, &b.edge_type));
+
+        Subgraph { nodes, edges }
+    }
+
+    /// Выбрать подграф и записать его в GraphML или DOT
+    pub fn export_subgraph<W: Write>(&self, writer: W, query: &SubgraphQuery, format: SubgraphFormat) -> Result<()> {
+        let _span = tracing::debug_span!("export_subgraph", ?format, start = query.start.len()).entered();
+        self.subgraph(query).write(writer, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, edge};
+    use tempfile::TempDir;
+
+    fn node(id: u128, name: &str) -> NodeRecord {
+        NodeRecord { file: Some("src/a.js".to_string()), ..test_support::node(id, "FUNCTION", name) }
+    }
+
+    fn engine(dir: &TempDir) -> GraphEngine {
+        let mut engine = GraphEngine::create(dir.path().join("g.rfdb")).unwrap();
+        engine.add_nodes(vec![node(1, "main"), node(2, "parse<T>"), node(3, "emit \"x\""), node(4, "unused")]);
+        engine.add_edges(vec![
+            edge(1, 2, "CALLS"),
+            edge(2, 3, "CALLS"),
+            edge(3, 1, "IMPORTS"),
+            edge(4, 1, "CALLS"),
+        ], false);
+        engine
+    }
+
+    #[test]
+    fn test_subgraph_selection() {
+        let dir = TempDir::new().unwrap();
+        let engine = engine(&dir);
+
+        let calls = engine.subgraph(&SubgraphQuery::new(vec![1], 1).edge_types(vec!["CALLS".into()]));
+        let ids: Vec<u128> = calls.nodes.iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(calls.edges.len(), 1);
+
+        // Без фильтра рёбер - все рёбра между найденными нодами, включая цикл
+        let all = engine.subgraph(&SubgraphQuery::new(vec![1], 5));
+        assert_eq!(all.nodes.len(), 3);
+        assert_eq!(all.edges.len(), 3);
+
+        let callers = engine.subgraph(&SubgraphQuery::new(vec![1], 1).edge_types(vec!["CALLS".into()]).backward(true));
+        assert_eq!(callers.nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_subgraph_edges_only_between_declared_nodes() {
+        let dir = TempDir::new().unwrap();
+        let mut engine = engine(&dir);
+        // Старт удалён, но его рёбра остались
+        engine.delete_node(1);
+
+        let sub = engine.subgraph(&SubgraphQuery::new(vec![1], 1).edge_types(vec!["CALLS".into()]));
+        assert_eq!(sub.nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![2]);
+        assert!(sub.edges.is_empty());
+        assert_eq!(sub.nodes[0].label, "FUNCTION:parse<T>@src/a.js");
+    }
+
+    #[test]
+    fn test_graphml_and_dot_output() {
+        let dir = TempDir::new().unwrap();
+        let engine = engine(&dir);
+        let query = SubgraphQuery::new(vec![1], 5);
+
+        let mut graphml = Vec::new();
+        engine.export_subgraph(&mut graphml, &query, SubgraphFormat::GraphMl).unwrap();
+        let graphml = String::from_utf8(graphml).unwrap();
+        assert!(graphml.contains("parse&lt;T&gt;"), "{}", graphml);
+        assert!(graphml.contains(r#"<edge id="e0" source="n1" target="n2">"#));
+        assert!(graphml.contains(r#"<data key="edge_label">IMPORTS</data>"#));
+
+        let mut dot = Vec::new();
+        engine.export_subgraph(&mut dot, &query, "dot".parse().unwrap()).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph rfdb {"));
+        assert!(dot.contains(r#"emit \"x\""#), "{}", dot);
+        assert!(dot.contains(r#"n3 -> n1 [label="IMPORTS"];"#));
+        assert!("svg".parse::<SubgraphFormat>().is_err());
+    }
+}
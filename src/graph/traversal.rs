@@ -1,6 +1,241 @@
 //! Граф traversal алгоритмы
 
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::error::{GraphError, Result};
+use crate::storage::EdgeRecord;
+use super::GraphStore;
+
+/// Направление обхода рёбер
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    /// По исходящим рёбрам (src -> dst)
+    #[default]
+    Outgoing,
+    /// По входящим рёбрам (dst -> src)
+    Incoming,
+    /// В обе стороны
+    Both,
+}
+
+impl std::str::FromStr for Direction {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "outgoing" | "out" => Ok(Direction::Outgoing),
+            "incoming" | "in" => Ok(Direction::Incoming),
+            "both" => Ok(Direction::Both),
+            other => Err(GraphError::InvalidFormat(format!("Unknown direction: {}", other))),
+        }
+    }
+}
+
+/// Путь в графе: `edges[i]` соединяет `nodes[i]` и `nodes[i + 1]`
+///
+/// Рёбра сохраняют исходную ориентацию (src/dst), даже если путь
+/// прошёл по ним в обратную сторону.
+#[derive(Debug, Clone, Default)]
+pub struct GraphPath {
+    pub nodes: Vec<u128>,
+    pub edges: Vec<EdgeRecord>,
+}
+
+impl GraphPath {
+    /// Длина пути в рёбрах
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+/// Шаги из `id`: (сосед, ребро) по направлению и типам рёбер (пусто - все)
+pub(crate) fn step_edges<G: GraphStore + ?Sized>(
+    graph: &G,
+    id: u128,
+    edge_types: &[&str],
+    direction: Direction,
+) -> Vec<(u128, EdgeRecord)> {
+    let filter = (!edge_types.is_empty()).then_some(edge_types);
+    let mut steps = Vec::new();
+    if direction != Direction::Incoming {
+        steps.extend(graph.get_outgoing_edges(id, filter).into_iter().map(|e| (e.dst, e)));
+    }
+    if direction != Direction::Outgoing {
+        steps.extend(graph.get_incoming_edges(id, filter).into_iter().map(|e| (e.src, e)));
+    }
+    steps
+}
+
+/// Кратчайший (по числу рёбер) путь от `src` до `dst`
+///
+/// BFS с запоминанием родителя; останавливается, как только найден `dst`.
+pub fn shortest_path<G: GraphStore + ?Sized>(
+    graph: &G,
+    src: u128,
+    dst: u128,
+    edge_types: &[&str],
+    direction: Direction,
+) -> Option<GraphPath> {
+    if src == dst {
+        return Some(GraphPath { nodes: vec![src], edges: Vec::new() });
+    }
+
+    // node -> (предыдущая нода, ребро по которому пришли)
+    let mut parents: HashMap<u128, (u128, EdgeRecord)> = HashMap::new();
+    let mut queue = VecDeque::from([src]);
+
+    'search: while let Some(node) = queue.pop_front() {
+        for (next, edge) in step_edges(graph, node, edge_types, direction) {
+            if next == src || parents.contains_key(&next) {
+                continue;
+            }
+            parents.insert(next, (node, edge));
+            if next == dst {
+                break 'search;
+            }
+            queue.push_back(next);
+        }
+    }
+
+    if !parents.contains_key(&dst) {
+        return None;
+    }
+    let mut path = GraphPath { nodes: vec![dst], edges: Vec::new() };
+    let mut current = dst;
+    while let Some((prev, edge)) = parents.remove(&current) {
+        path.nodes.push(prev);
+        path.edges.push(edge);
+        current = prev;
+    }
+    path.nodes.reverse();
+    path.edges.reverse();
+    Some(path)
+}
+
+/// Ограничения перечисления путей (`all_simple_paths`)
+#[derive(Debug, Clone)]
+pub struct PathQuery {
+    /// Максимальная длина пути в рёбрах
+    pub max_length: usize,
+    /// Сколько путей вернуть максимум
+    pub max_results: usize,
+    /// Типы рёбер (пусто - все)
+    pub edge_types: Vec<String>,
+    /// Типы промежуточных нод, через которые пути не идут ("http:*" - wildcard)
+    pub exclude_node_types: Vec<String>,
+    pub direction: Direction,
+}
+
+impl Default for PathQuery {
+    fn default() -> Self {
+        Self {
+            max_length: 10,
+            max_results: 1000,
+            edge_types: Vec::new(),
+            exclude_node_types: Vec::new(),
+            direction: Direction::Outgoing,
+        }
+    }
+}
+
+impl PathQuery {
+    pub fn new(max_length: usize, max_results: usize) -> Self {
+        Self { max_length, max_results, ..Self::default() }
+    }
+
+    pub fn edge_types(mut self, types: Vec<String>) -> Self {
+        self.edge_types = types;
+        self
+    }
+
+    pub fn exclude_node_types(mut self, types: Vec<String>) -> Self {
+        self.exclude_node_types = types;
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+/// Тип ноды подходит под шаблон ("http:*" - wildcard по префиксу)
+pub(crate) fn type_matches(pattern: &str, node_type: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => node_type.starts_with(prefix),
+        None => pattern == node_type,
+    }
+}
+
+/// Все простые пути (без повторов нод) из `sources` в `targets`
+///
+/// Путь заканчивается на первой встреченной target-ноде и содержит хотя бы
+/// одно ребро. Обход - DFS, порядок путей детерминирован порядком рёбер;
+/// перечисление останавливается на `max_results`.
+pub fn all_simple_paths<G: GraphStore + ?Sized>(
+    graph: &G,
+    sources: &[u128],
+    targets: &[u128],
+    query: &PathQuery,
+) -> Vec<GraphPath> {
+    let mut result = Vec::new();
+    if query.max_length == 0 || query.max_results == 0 {
+        return result;
+    }
+    let targets: HashSet<u128> = targets.iter().copied().collect();
+    let edge_types: Vec<&str> = query.edge_types.iter().map(|s| s.as_str()).collect();
+    let mut excluded: HashMap<u128, bool> = HashMap::new();
+    let mut is_excluded = |id: u128| *excluded.entry(id).or_insert_with(|| {
+        !query.exclude_node_types.is_empty() && graph.get_node(id)
+            .and_then(|n| n.node_type)
+            .is_some_and(|t| query.exclude_node_types.iter().any(|p| type_matches(p, &t)))
+    });
+
+    for &source in sources {
+        // Кадр DFS: шаги из ноды и индекс следующего шага
+        let mut stack = vec![(step_edges(graph, source, &edge_types, query.direction), 0usize)];
+        let mut path = GraphPath { nodes: vec![source], edges: Vec::new() };
+        let mut on_path = HashSet::from([source]);
+
+        while let Some((steps, next)) = stack.last_mut() {
+            let Some((node, edge)) = steps.get(*next).cloned() else {
+                stack.pop();
+                if let Some(node) = path.nodes.pop() {
+                    on_path.remove(&node);
+                }
+                path.edges.pop();
+                continue;
+            };
+            *next += 1;
+
+            if on_path.contains(&node) {
+                continue;
+            }
+            if targets.contains(&node) {
+                let mut found = path.clone();
+                found.nodes.push(node);
+                found.edges.push(edge);
+                result.push(found);
+                if result.len() >= query.max_results {
+                    return result;
+                }
+                continue;
+            }
+            // Продолжать имеет смысл, только если путь ещё может дойти до target
+            if path.edges.len() + 1 >= query.max_length || is_excluded(node) {
+                continue;
+            }
+            stack.push((step_edges(graph, node, &edge_types, query.direction), 0));
+            path.nodes.push(node);
+            path.edges.push(edge);
+            on_path.insert(node);
+        }
+    }
+    result
+}
 
 /// BFS traversal от start нод
 pub fn bfs<F>(
@@ -42,6 +277,59 @@ where
     result
 }
 
+/// Достижим ли `dst` из `src` не более чем за `max_depth` шагов
+///
+/// Двунаправленный BFS: на каждом шаге расширяется меньший из фронтов
+/// (`forward` от src, `backward` от dst); поиск останавливается, как только
+/// фронты встретились. `src == dst` достижим за 0 шагов.
+///
+/// Возвращает (достижим ли, сколько нод посещено обоими фронтами).
+pub fn bidirectional_reachable<F, B>(
+    src: u128,
+    dst: u128,
+    max_depth: usize,
+    mut forward: F,
+    mut backward: B,
+) -> (bool, usize)
+where
+    F: FnMut(u128) -> Vec<u128>,
+    B: FnMut(u128) -> Vec<u128>,
+{
+    if src == dst {
+        return (true, 1);
+    }
+
+    let mut visited_fwd = HashSet::from([src]);
+    let mut visited_bwd = HashSet::from([dst]);
+    let mut frontier_fwd = vec![src];
+    let mut frontier_bwd = vec![dst];
+    let mut depth = 0;
+
+    while depth < max_depth && !frontier_fwd.is_empty() && !frontier_bwd.is_empty() {
+        let expand_forward = frontier_fwd.len() <= frontier_bwd.len();
+        let (frontier, visited, other, next_of) = if expand_forward {
+            (&mut frontier_fwd, &mut visited_fwd, &visited_bwd, &mut forward as &mut dyn FnMut(u128) -> Vec<u128>)
+        } else {
+            (&mut frontier_bwd, &mut visited_bwd, &visited_fwd, &mut backward as &mut dyn FnMut(u128) -> Vec<u128>)
+        };
+
+        let mut next_frontier = Vec::new();
+        for node in frontier.drain(..) {
+            for neighbor in next_of(node) {
+                if other.contains(&neighbor) {
+                    return (true, visited.len() + other.len());
+                }
+                if visited.insert(neighbor) {
+                    next_frontier.push(neighbor);
+                }
+            }
+        }
+        *frontier = next_frontier;
+        depth += 1;
+    }
+    (false, visited_fwd.len() + visited_bwd.len())
+}
+
 /// DFS traversal (для обратной трассировки)
 pub fn dfs<F>(
     start: &[u128],
@@ -77,10 +365,406 @@ where
     result
 }
 
+/// Сильно связная компонента с циклом (размер > 1 или петля)
+#[derive(Debug, Clone, Default)]
+pub struct CycleComponent {
+    /// Ноды компоненты, отсортированы по id
+    pub nodes: Vec<u128>,
+    /// Пример цикла: `cycle[0] -> cycle[1] -> ... -> cycle[0]`
+    pub cycle: Option<Vec<u128>>,
+}
+
+/// Сильно связные компоненты (итеративный Tarjan)
+///
+/// Обходит всех `roots` и всё, что из них достижимо. Возвращает все
+/// компоненты, включая тривиальные из одной ноды, в обратном
+/// топологическом порядке.
+pub fn strongly_connected_components<F>(roots: &[u128], mut get_neighbors: F) -> Vec<Vec<u128>>
+where
+    F: FnMut(u128) -> Vec<u128>,
+{
+    let mut index: HashMap<u128, usize> = HashMap::new();
+    let mut lowlink: HashMap<u128, usize> = HashMap::new();
+    let mut on_stack = HashSet::new();
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for &root in roots {
+        if index.contains_key(&root) {
+            continue;
+        }
+
+        index.insert(root, index.len());
+        lowlink.insert(root, index[&root]);
+        stack.push(root);
+        on_stack.insert(root);
+        // (нода, её соседи, следующий сосед для обхода)
+        let mut call_stack = vec![(root, get_neighbors(root), 0usize)];
+
+        while let Some((node, neighbors, next)) = call_stack.last_mut() {
+            let node = *node;
+            if let Some(&neighbor) = neighbors.get(*next) {
+                *next += 1;
+                if !index.contains_key(&neighbor) {
+                    index.insert(neighbor, index.len());
+                    lowlink.insert(neighbor, index[&neighbor]);
+                    stack.push(neighbor);
+                    on_stack.insert(neighbor);
+                    call_stack.push((neighbor, get_neighbors(neighbor), 0));
+                } else if on_stack.contains(&neighbor) {
+                    let low = lowlink[&node].min(index[&neighbor]);
+                    lowlink.insert(node, low);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some((parent, _, _)) = call_stack.last() {
+                let low = lowlink[parent].min(lowlink[&node]);
+                lowlink.insert(*parent, low);
+            }
+
+            if lowlink[&node] == index[&node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+
+    components
+}
+
+/// Пример цикла через `start` внутри `component` (кратчайший, BFS)
+pub fn find_cycle<F>(start: u128, component: &HashSet<u128>, mut get_neighbors: F) -> Option<Vec<u128>>
+where
+    F: FnMut(u128) -> Vec<u128>,
+{
+    let mut parent: HashMap<u128, u128> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        for neighbor in get_neighbors(node) {
+            if neighbor == start {
+                let mut cycle = vec![node];
+                let mut current = node;
+                while current != start {
+                    current = parent[&current];
+                    cycle.push(current);
+                }
+                cycle.reverse();
+                return Some(cycle);
+            }
+            if component.contains(&neighbor) && !parent.contains_key(&neighbor) {
+                parent.insert(neighbor, node);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    None
+}
+
+/// Компоненты с циклами по `get_neighbors`, от больших к меньшим
+pub fn find_cycle_components<F>(roots: &[u128], with_cycles: bool, mut get_neighbors: F) -> Vec<CycleComponent>
+where
+    F: FnMut(u128) -> Vec<u128>,
+{
+    let mut result = Vec::new();
+    for mut nodes in strongly_connected_components(roots, &mut get_neighbors) {
+        if nodes.len() == 1 && !get_neighbors(nodes[0]).contains(&nodes[0]) {
+            continue;
+        }
+        nodes.sort_unstable();
+        let cycle = if with_cycles {
+            let members: HashSet<u128> = nodes.iter().copied().collect();
+            find_cycle(nodes[0], &members, &mut get_neighbors)
+        } else {
+            None
+        };
+        result.push(CycleComponent { nodes, cycle });
+    }
+
+    result.sort_by(|a, b| b.nodes.len().cmp(&a.nodes.len()).then(a.nodes[0].cmp(&b.nodes[0])));
+    result
+}
+
+/// Результат топологической сортировки
+#[derive(Debug, Clone, Default)]
+pub struct TopoOrder {
+    /// Ноды так, что src идёт раньше dst (порядок сборки - в обратную сторону)
+    pub order: Vec<u128>,
+    /// Слой ноды из `order`: длина самого длинного пути до неё от истока
+    pub layers: HashMap<u128, usize>,
+    /// Ноды на циклах и ниже них - их нельзя упорядочить
+    pub unordered: Vec<u128>,
+    /// Циклы, из-за которых появились `unordered` (с примером цикла)
+    pub cycles: Vec<CycleComponent>,
+}
+
+/// Топологическая сортировка (Kahn) с разбиением на слои по самому длинному пути
+///
+/// Циклы не приводят к ошибке: их ноды и всё, что от них зависит, попадают в
+/// `unordered`, а сами компоненты - в `cycles`.
+pub fn topological_order<F>(nodes: &[u128], mut get_neighbors: F) -> TopoOrder
+where
+    F: FnMut(u128) -> Vec<u128>,
+{
+    let successors: HashMap<u128, Vec<u128>> = nodes.iter().map(|&id| (id, get_neighbors(id))).collect();
+    let mut in_degree: HashMap<u128, usize> = nodes.iter().map(|&id| (id, 0)).collect();
+    for next in successors.values().flatten() {
+        if let Some(degree) = in_degree.get_mut(next) {
+            *degree += 1;
+        }
+    }
+
+    let mut result = TopoOrder::default();
+    let mut queue: VecDeque<u128> = nodes.iter().copied().filter(|id| in_degree[id] == 0).collect();
+    for &id in &queue {
+        result.layers.insert(id, 0);
+    }
+
+    while let Some(node) = queue.pop_front() {
+        result.order.push(node);
+        let layer = result.layers[&node];
+        for &next in &successors[&node] {
+            let Some(degree) = in_degree.get_mut(&next) else { continue };
+            let next_layer = result.layers.entry(next).or_insert(0);
+            *next_layer = (*next_layer).max(layer + 1);
+            *degree -= 1;
+            if *degree == 0 {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    if result.order.len() < nodes.len() {
+        result.unordered = nodes.iter().copied().filter(|id| in_degree[id] > 0).collect();
+        result.layers.retain(|id, _| in_degree[id] == 0);
+        let blocked: HashSet<u128> = result.unordered.iter().copied().collect();
+        result.cycles = find_cycle_components(&result.unordered, true, |id| {
+            successors[&id].iter().copied().filter(|next| blocked.contains(next)).collect()
+        });
+    }
+    result
+}
+
+/// Дерево доминаторов от `root`
+///
+/// `a` доминирует `b`, если каждый путь от root до `b` проходит через `a`.
+/// Для пост-доминаторов обход идёт по обратным рёбрам от стока.
+#[derive(Debug, Clone, Default)]
+pub struct Dominators {
+    root: u128,
+    /// node -> непосредственный доминатор (root и недостижимых нод нет)
+    idom: HashMap<u128, u128>,
+}
+
+impl Dominators {
+    pub fn root(&self) -> u128 {
+        self.root
+    }
+
+    /// Непосредственный доминатор (None для root и недостижимых нод)
+    pub fn immediate_dominator(&self, id: u128) -> Option<u128> {
+        self.idom.get(&id).copied()
+    }
+
+    /// Все доминаторы ноды, от неё самой до root (пусто, если недостижима)
+    pub fn dominators(&self, id: u128) -> Vec<u128> {
+        if id != self.root && !self.idom.contains_key(&id) {
+            return Vec::new();
+        }
+        let mut chain = vec![id];
+        let mut current = id;
+        while let Some(&dom) = self.idom.get(&current) {
+            chain.push(dom);
+            current = dom;
+        }
+        chain
+    }
+
+    /// Доминирует ли `a` над `b` (каждая нода доминирует сама себя)
+    pub fn dominates(&self, a: u128, b: u128) -> bool {
+        self.dominators(b).contains(&a)
+    }
+
+    /// Карта node -> непосредственный доминатор
+    pub fn idoms(&self) -> &HashMap<u128, u128> {
+        &self.idom
+    }
+}
+
+/// Доминаторы от `root` (Lengauer-Tarjan, простой вариант со сжатием путей)
+///
+/// `get_successors` задаёт рёбра обхода, `get_predecessors` - обратные к ним.
+pub fn dominators<S, P>(root: u128, mut get_successors: S, mut get_predecessors: P) -> Dominators
+where
+    S: FnMut(u128) -> Vec<u128>,
+    P: FnMut(u128) -> Vec<u128>,
+{
+    const NONE: usize = usize::MAX;
+
+    // DFS preorder: номер -> нода, нода -> номер, родитель в DFS-дереве
+    let mut vertex = vec![root];
+    let mut number: HashMap<u128, usize> = HashMap::from([(root, 0)]);
+    let mut parent = vec![NONE];
+    let mut stack = vec![(0usize, get_successors(root), 0usize)];
+    while let Some((node, successors, next)) = stack.last_mut() {
+        let node = *node;
+        let Some(&succ) = successors.get(*next) else {
+            stack.pop();
+            continue;
+        };
+        *next += 1;
+        if let std::collections::hash_map::Entry::Vacant(entry) = number.entry(succ) {
+            let n = vertex.len();
+            entry.insert(n);
+            vertex.push(succ);
+            parent.push(node);
+            stack.push((n, get_successors(succ), 0));
+        }
+    }
+
+    let n = vertex.len();
+    let mut semi: Vec<usize> = (0..n).collect();
+    let mut label: Vec<usize> = (0..n).collect();
+    let mut ancestor = vec![NONE; n];
+    let mut idom = vec![NONE; n];
+    let mut bucket: Vec<Vec<usize>> = vec![Vec::new(); n];
+
+    // eval со сжатием пути в лесе `ancestor`
+    let eval = |v: usize, ancestor: &mut Vec<usize>, label: &mut Vec<usize>, semi: &Vec<usize>| -> usize {
+        if ancestor[v] == NONE {
+            return v;
+        }
+        let mut path = Vec::new();
+        let mut x = v;
+        while ancestor[ancestor[x]] != NONE {
+            path.push(x);
+            x = ancestor[x];
+        }
+        for &y in path.iter().rev() {
+            let a = ancestor[y];
+            if semi[label[a]] < semi[label[y]] {
+                label[y] = label[a];
+            }
+            ancestor[y] = ancestor[a];
+        }
+        label[v]
+    };
+
+    for w in (1..n).rev() {
+        for pred in get_predecessors(vertex[w]) {
+            let Some(&v) = number.get(&pred) else { continue };
+            let u = eval(v, &mut ancestor, &mut label, &semi);
+            if semi[u] < semi[w] {
+                semi[w] = semi[u];
+            }
+        }
+        bucket[semi[w]].push(w);
+        let p = parent[w];
+        ancestor[w] = p;
+        for v in std::mem::take(&mut bucket[p]) {
+            let u = eval(v, &mut ancestor, &mut label, &semi);
+            idom[v] = if semi[u] < semi[v] { u } else { p };
+        }
+    }
+    for w in 1..n {
+        if idom[w] != semi[w] {
+            idom[w] = idom[idom[w]];
+        }
+    }
+
+    Dominators {
+        root,
+        idom: (1..n).map(|w| (vertex[w], vertex[idom[w]])).collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use crate::graph::GraphEngine;
+    use crate::test_support::{edge, node, test_engine};
+    use tempfile::TempDir;
+
+    /// FUNCTION-ноды `f<id>` для всех концов рёбер
+    fn function_graph(dir: &TempDir, edges: &[(u128, u128, &str)]) -> GraphEngine {
+        let mut ids: Vec<u128> = edges.iter().flat_map(|&(s, d, _)| [s, d]).collect();
+        ids.sort();
+        ids.dedup();
+        test_engine(
+            dir,
+            ids.into_iter().map(|id| node(id, "FUNCTION", &format!("f{}", id))).collect(),
+            edges.iter().map(|&(src, dst, t)| edge(src, dst, t)).collect(),
+        )
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let dir = TempDir::new().unwrap();
+        // 1 -> 2 -> 3 -> 4 и короткий путь 1 -> 5 -> 4 через IMPORTS
+        let engine = function_graph(&dir, &[
+            (1, 2, "CALLS"), (2, 3, "CALLS"), (3, 4, "CALLS"),
+            (1, 5, "IMPORTS"), (5, 4, "CALLS"),
+        ]);
+
+        let path = shortest_path(&engine, 1, 4, &[], Direction::Outgoing).unwrap();
+        assert_eq!(path.nodes, vec![1, 5, 4]);
+        assert_eq!(path.edges[0].edge_type.as_deref(), Some("IMPORTS"));
+        assert_eq!(path.len(), 2);
+
+        let calls = shortest_path(&engine, 1, 4, &["CALLS"], Direction::Outgoing).unwrap();
+        assert_eq!(calls.nodes, vec![1, 2, 3, 4]);
+
+        assert!(shortest_path(&engine, 4, 1, &[], Direction::Outgoing).is_none());
+        let back = shortest_path(&engine, 4, 1, &[], Direction::Incoming).unwrap();
+        assert_eq!(back.nodes, vec![4, 5, 1]);
+        // Рёбра сохраняют исходную ориентацию
+        assert_eq!((back.edges[0].src, back.edges[0].dst), (5, 4));
+
+        let both = shortest_path(&engine, 2, 5, &[], "both".parse().unwrap()).unwrap();
+        assert_eq!(both.nodes, vec![2, 1, 5]);
+        assert!(shortest_path(&engine, 3, 3, &[], Direction::Outgoing).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_all_simple_paths() {
+        let dir = TempDir::new().unwrap();
+        // Два пути 1 -> 4, цикл 2 <-> 3, и 6 -> 4
+        let mut engine = function_graph(&dir, &[
+            (1, 2, "CALLS"), (2, 3, "CALLS"), (3, 2, "CALLS"), (3, 4, "CALLS"),
+            (1, 5, "CALLS"), (5, 4, "CALLS"), (6, 4, "CALLS"), (4, 7, "CALLS"),
+        ]);
+
+        let paths = all_simple_paths(&engine, &[1, 6], &[4, 7], &PathQuery::default());
+        let nodes: Vec<Vec<u128>> = paths.iter().map(|p| p.nodes.clone()).collect();
+        // Путь заканчивается на первой target-ноде (4), дальше к 7 не идёт
+        assert_eq!(nodes, vec![vec![1, 2, 3, 4], vec![1, 5, 4], vec![6, 4]]);
+        assert!(paths.iter().all(|p| p.edges.len() + 1 == p.nodes.len()));
+
+        let short = all_simple_paths(&engine, &[1], &[4], &PathQuery::new(2, 10));
+        assert_eq!(short.iter().map(|p| p.nodes.clone()).collect::<Vec<_>>(), vec![vec![1, 5, 4]]);
+        assert_eq!(all_simple_paths(&engine, &[1, 6], &[4], &PathQuery::new(10, 1)).len(), 1);
+
+        // Ноды исключённых типов не бывают промежуточными
+        let mut node_5 = engine.get_node(5).unwrap();
+        node_5.node_type = Some("db:query".to_string());
+        engine.add_nodes(vec![node_5]);
+        let query = PathQuery::new(10, 10).exclude_node_types(vec!["db:*".to_string()]);
+        let paths = all_simple_paths(&engine, &[1], &[4], &query);
+        assert_eq!(paths.iter().map(|p| p.nodes.clone()).collect::<Vec<_>>(), vec![vec![1, 2, 3, 4]]);
+
+        let back = all_simple_paths(&engine, &[4], &[1], &PathQuery::new(5, 10).direction(Direction::Incoming));
+        assert_eq!(back.len(), 2);
+        assert!(all_simple_paths(&engine, &[1], &[4], &PathQuery::new(5, 10).edge_types(vec!["IMPORTS".into()])).is_empty());
+    }
 
     #[test]
     fn test_bfs_simple_graph() {
@@ -107,6 +791,160 @@ mod tests {
         assert!(result.contains(&4));
     }
 
+    #[test]
+    fn test_bidirectional_reachable() {
+        // Цепочка 1 -> 2 -> ... -> 10 и ветвление 1 -> 100..200
+        let mut forward: HashMap<u128, Vec<u128>> = (1..10).map(|i| (i, vec![i + 1])).collect();
+        forward.get_mut(&1).unwrap().extend(100..200);
+        let mut backward: HashMap<u128, Vec<u128>> = HashMap::new();
+        for (&src, dsts) in &forward {
+            for &dst in dsts {
+                backward.entry(dst).or_default().push(src);
+            }
+        }
+
+        let mut expanded = 0;
+        let mut reachable = |src, dst, depth| bidirectional_reachable(src, dst, depth,
+            |id| { expanded += 1; forward.get(&id).cloned().unwrap_or_default() },
+            |id| backward.get(&id).cloned().unwrap_or_default()).0;
+
+        assert!(reachable(1, 10, 9));
+        assert!(!reachable(1, 10, 8));
+        assert!(!reachable(10, 1, 100));
+        assert!(reachable(5, 5, 0));
+        assert!(reachable(1, 150, 1));
+        // Обратный фронт от 10 меньше: широкое ветвление у 1 раскрывается не больше пары раз
+        assert!(expanded <= 4, "forward expanded {} times", expanded);
+
+        // Посещённые обоими фронтами ноды: 2..=9 вперёд и 10 назад
+        let visits = |src, dst| bidirectional_reachable(src, dst, 100,
+            |id| forward.get(&id).cloned().unwrap_or_default(),
+            |id| backward.get(&id).cloned().unwrap_or_default());
+        assert_eq!(visits(2, 10), (true, 9));
+        assert_eq!(visits(5, 5), (true, 1));
+        assert_eq!(bidirectional_reachable(10, 1, 100, |_| vec![], |_| vec![]), (false, 2));
+    }
+
+    #[test]
+    fn test_cycle_components() {
+        let dir = TempDir::new().unwrap();
+        // Импорты 1 -> 2 -> 3 -> 1, петля 5 -> 5; вызовы 3 <-> 4 и 6 -> 1 без цикла
+        let engine = function_graph(&dir, &[
+            (1, 2, "IMPORTS"), (2, 3, "IMPORTS"), (3, 1, "IMPORTS"), (5, 5, "IMPORTS"),
+            (3, 4, "CALLS"), (4, 3, "CALLS"), (6, 1, "IMPORTS"),
+        ]);
+
+        let imports = engine.cycle_components(&["IMPORTS"], true);
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].nodes, vec![1, 2, 3]);
+        assert_eq!(imports[0].cycle, Some(vec![1, 2, 3]));
+        assert_eq!(imports[1].nodes, vec![5]);
+        assert_eq!(imports[1].cycle, Some(vec![5]));
+
+        let all = engine.cycle_components(&[], false);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].nodes, vec![1, 2, 3, 4]);
+        assert!(all[0].cycle.is_none());
+
+        assert!(engine.in_cycle(2, &["IMPORTS"]));
+        assert!(!engine.in_cycle(4, &["IMPORTS"]));
+        assert!(engine.in_cycle(4, &["CALLS"]));
+        assert!(engine.in_cycle(5, &["IMPORTS"]));
+        assert_eq!(engine.cycle_nodes(&["IMPORTS"]).len(), 4);
+        assert!(!engine.in_cycle(6, &[]));
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let dir = TempDir::new().unwrap();
+        // 1 -> {2, 3} -> 4, 3 -> 5 -> 4; цикл 6 <-> 7, от него зависит 8
+        let engine = function_graph(&dir, &[
+            (1, 2, "DEPENDS_ON"), (1, 3, "DEPENDS_ON"), (2, 4, "DEPENDS_ON"),
+            (3, 5, "DEPENDS_ON"), (5, 4, "DEPENDS_ON"),
+            (6, 7, "DEPENDS_ON"), (7, 6, "DEPENDS_ON"), (7, 8, "DEPENDS_ON"),
+            (4, 1, "CALLS"),
+        ]);
+
+        let topo = engine.topological_sort(&["DEPENDS_ON"]);
+        let position = |id| topo.order.iter().position(|&n| n == id).unwrap();
+        assert_eq!(topo.order.len(), 5);
+        assert!(position(1) < position(3) && position(3) < position(5) && position(5) < position(4));
+        assert!(position(2) < position(4));
+        assert_eq!(topo.layers, HashMap::from([(1, 0), (2, 1), (3, 1), (5, 2), (4, 3)]));
+
+        assert_eq!(topo.unordered, vec![6, 7, 8]);
+        assert_eq!(topo.cycles.len(), 1);
+        assert_eq!(topo.cycles[0].nodes, vec![6, 7]);
+        assert_eq!(topo.cycles[0].cycle, Some(vec![6, 7]));
+
+        // С CALLS 4 -> 1 замыкает цикл через всю первую часть
+        let all = engine.topological_sort(&[]);
+        assert!(all.order.is_empty());
+        assert_eq!(all.cycles.len(), 2);
+    }
+
+    #[test]
+    fn test_dominators() {
+        let dir = TempDir::new().unwrap();
+        // main=1 -> {2, 3} -> 4 -> 5 -> {6, 7} -> 8, цикл 8 -> 4; обход 1 -> 9 -> 6 по USES
+        let engine = function_graph(&dir, &[
+            (1, 2, "CALLS"), (1, 3, "CALLS"), (2, 4, "CALLS"), (3, 4, "CALLS"), (4, 5, "CALLS"),
+            (5, 6, "CALLS"), (5, 7, "CALLS"), (6, 8, "CALLS"), (7, 8, "CALLS"), (8, 4, "CALLS"),
+            (1, 9, "USES"), (9, 6, "USES"),
+        ]);
+
+        let dom = engine.dominators(1, &["CALLS"]);
+        assert_eq!(dom.idoms(), &HashMap::from([(2, 1), (3, 1), (4, 1), (5, 4), (6, 5), (7, 5), (8, 5)]));
+        assert_eq!(dom.dominators(8), vec![8, 5, 4, 1]);
+        assert!(dom.dominates(4, 7));
+        assert!(!dom.dominates(2, 4));
+        assert_eq!(dom.immediate_dominator(1), None);
+        assert!(dom.dominators(9).is_empty());
+
+        let dom = engine.dominators(1, &[]);
+        assert_eq!(dom.immediate_dominator(6), Some(1));
+        assert_eq!(dom.immediate_dominator(8), Some(1));
+        assert_eq!(dom.immediate_dominator(5), Some(4));
+
+        let post = engine.post_dominators(8, &["CALLS"]);
+        assert_eq!(post.idoms(), &HashMap::from([(1, 4), (2, 4), (3, 4), (4, 5), (5, 8), (6, 8), (7, 8)]));
+    }
+
+    #[test]
+    fn test_dominators_match_naive() {
+        // a доминирует b <=> без a нода b недостижима из root
+        let mut seed = 42u64;
+        let mut random = |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for _ in 0..20 {
+            let mut succ: HashMap<u128, Vec<u128>> = HashMap::new();
+            let mut pred: HashMap<u128, Vec<u128>> = HashMap::new();
+            for _ in 0..40 {
+                let (a, b) = (random(15) as u128, random(15) as u128);
+                succ.entry(a).or_default().push(b);
+                pred.entry(b).or_default().push(a);
+            }
+            let neighbors = |id: u128| succ.get(&id).cloned().unwrap_or_default();
+            let dom = dominators(0, neighbors, |id| pred.get(&id).cloned().unwrap_or_default());
+            let reachable: HashSet<u128> = bfs(&[0], usize::MAX, neighbors).into_iter().collect();
+
+            for b in 0..15u128 {
+                let expected: HashSet<u128> = if reachable.contains(&b) {
+                    (0..15u128)
+                        .filter(|&a| a == b || a == 0 || !bfs(&[0], usize::MAX, |id| {
+                            neighbors(id).into_iter().filter(|&n| n != a).collect()
+                        }).contains(&b))
+                        .collect()
+                } else {
+                    HashSet::new()
+                };
+                assert_eq!(dom.dominators(b).into_iter().collect::<HashSet<_>>(), expected, "node {}", b);
+            }
+        }
+    }
+
     #[test]
     fn test_bfs_max_depth() {
         // Граф: 1 -> 2 -> 3 -> 4
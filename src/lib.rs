@@ -46,6 +46,9 @@ pub mod index;
 pub mod error;
 pub mod datalog;
 
+#[cfg(test)]
+pub(crate) mod test_support;
+
 #[cfg(feature = "napi")]
 pub mod ffi;
 
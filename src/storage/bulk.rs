@@ -0,0 +1,548 @@
+//! BulkLoader - первичная загрузка большого графа в обход delta
+//!
+//! Записи копятся в буфере ограниченного размера; полный буфер сортируется
+//! и выгружается во временный run-файл (external sort). `finish` сливает
+//! runs, пишет колонки потоком во временные файлы и собирает из них
+//! nodes.bin / edges.bin / strings.bin / metadata.json.
+//!
+//! Памяти нужно на один буфер, индекс словаря (хэш -> id, сами строки
+//! выгружаются на диск) и (при сжатии) одну колонку. `finish_indexed`
+//! попутно собирает adjacency по edges.bin (`GraphEngine::finish_bulk_load`).
+//! Работает только с новой базой.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use crate::error::Result;
+use crate::storage::{NodeRecord, EdgeRecord, Compression, DbLock, GraphMetadata, SegmentWriter, normalize_db_path};
+use crate::storage::dictionary::SpillDictionary;
+use crate::storage::segment::{SegmentHeader, Checksum, EdgeIndex, MAGIC_COMPRESSED, write_checksum_footer};
+use crate::storage::writer::{SectionWriter, commit_file};
+
+/// Записей в одном run по умолчанию (отдельно для нод и рёбер)
+pub const DEFAULT_RUN_SIZE: usize = 1_000_000;
+
+/// Директория временных файлов внутри базы
+const BULK_TMP_DIR: &str = "bulk-tmp";
+
+/// Итог загрузки
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkLoadStats {
+    pub nodes: usize,
+    pub edges: usize,
+    /// Сколько run-файлов было выгружено на диск
+    pub spilled_runs: usize,
+    /// Память под индекс словаря (строки лежат на диске)
+    pub dictionary_bytes: usize,
+}
+
+/// Нода в run-файле (NodeRecord пропускает None при сериализации, bincode так не умеет)
+#[derive(Serialize, Deserialize)]
+struct SpillNode {
+    id: u128,
+    node_type: Option<String>,
+    name: Option<String>,
+    file: Option<String>,
+    version: String,
+    exported: bool,
+    replaces: Option<u128>,
+    deleted: bool,
+    metadata: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpillEdge {
+    src: u128,
+    dst: u128,
+    edge_type: Option<String>,
+    version: String,
+    metadata: Option<String>,
+    deleted: bool,
+}
+
+/// Отсортированная серия записей: в памяти или в run-файле
+enum Run<T> {
+    Memory(std::vec::IntoIter<T>),
+    File { reader: BufReader<File>, remaining: usize },
+}
+
+impl<T: DeserializeOwned> Run<T> {
+    fn next(&mut self) -> Result<Option<T>> {
+        match self {
+            Run::Memory(iter) => Ok(iter.next()),
+            Run::File { remaining: 0, .. } => Ok(None),
+            Run::File { reader, remaining } => {
+                *remaining -= 1;
+                Ok(Some(bincode::deserialize_from(reader)?))
+            }
+        }
+    }
+}
+
+/// Буфер + выгруженные runs одного вида записей
+struct Sorter<T> {
+    buffer: Vec<T>,
+    runs: Vec<(PathBuf, usize)>,
+}
+
+impl<T: Serialize + DeserializeOwned> Sorter<T> {
+    fn new() -> Self {
+        Self { buffer: Vec::new(), runs: Vec::new() }
+    }
+
+    /// Отсортировать буфер (стабильно - порядок добавления сохраняется) и выгрузить
+    fn spill<K: Ord>(&mut self, path: PathBuf, key: impl Fn(&T) -> K) -> Result<()> {
+        self.buffer.sort_by_key(|r| key(r));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for record in &self.buffer {
+            bincode::serialize_into(&mut writer, record)?;
+        }
+        writer.flush()?;
+        self.runs.push((path, self.buffer.len()));
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Слить все runs по `key`; при равных ключах записи идут в порядке добавления
+    fn merge<K: Ord>(mut self, key: impl Fn(&T) -> K, mut emit: impl FnMut(T) -> Result<()>) -> Result<()> {
+        self.buffer.sort_by_key(|r| key(r));
+        let mut runs: Vec<Run<T>> = Vec::with_capacity(self.runs.len() + 1);
+        for (path, count) in &self.runs {
+            runs.push(Run::File { reader: BufReader::new(File::open(path)?), remaining: *count });
+        }
+        runs.push(Run::Memory(std::mem::take(&mut self.buffer).into_iter()));
+
+        let mut heads: Vec<Option<T>> = Vec::with_capacity(runs.len());
+        let mut heap = BinaryHeap::new();
+        for (idx, run) in runs.iter_mut().enumerate() {
+            let head = run.next()?;
+            if let Some(ref record) = head {
+                heap.push(Reverse((key(record), idx)));
+            }
+            heads.push(head);
+        }
+
+        while let Some(Reverse((_, idx))) = heap.pop() {
+            let record = heads[idx].take().expect("heap entry without head");
+            if let Some(next) = runs[idx].next()? {
+                heap.push(Reverse((key(&next), idx)));
+                heads[idx] = Some(next);
+            }
+            emit(record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Колонка сегмента, выгружаемая во временный файл
+struct ColumnSpill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    hasher: blake3::Hasher,
+}
+
+impl ColumnSpill {
+    fn create(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(&path)?;
+        Ok(Self { path, writer: BufWriter::new(file), hasher: blake3::Hasher::new() })
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.hasher.update(bytes);
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Закрыть и вернуть (путь, checksum)
+    fn finish(mut self) -> Result<(PathBuf, Checksum)> {
+        self.writer.flush()?;
+        Ok((self.path, *self.hasher.finalize().as_bytes()))
+    }
+}
+
+/// Потоковая загрузка нод и рёбер прямо в сегменты
+///
+/// ```no_run
+/// use rfdb::storage::bulk::BulkLoader;
+/// # fn main() -> rfdb::Result<()> {
+/// let mut loader = BulkLoader::create("./graph.rfdb")?;
+/// // loader.add_nodes(batch)?; loader.add_edges(batch)?; ...
+/// // или loader.finish()? и потом GraphEngine::open
+/// let (engine, stats) = rfdb::GraphEngine::finish_bulk_load(loader)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BulkLoader {
+    path: PathBuf,
+    tmp_dir: PathBuf,
+    compression: Compression,
+    run_size: usize,
+    nodes: Sorter<SpillNode>,
+    edges: Sorter<SpillEdge>,
+    _lock: DbLock,
+}
+
+impl BulkLoader {
+    /// Начать загрузку в новую базу (segments в `path` не должны существовать)
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = normalize_db_path(path);
+        fs::create_dir_all(&path)?;
+        let lock = DbLock::acquire(&path)?;
+
+        if path.join("nodes.bin").exists() || path.join("edges.bin").exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("Database {:?} already has segments; BulkLoader only builds new databases", path),
+            ).into());
+        }
+
+        let tmp_dir = path.join(BULK_TMP_DIR);
+        if tmp_dir.exists() {
+            fs::remove_dir_all(&tmp_dir)?;
+        }
+        fs::create_dir(&tmp_dir)?;
+
+        Ok(Self {
+            path,
+            tmp_dir,
+            compression: Compression::None,
+            run_size: DEFAULT_RUN_SIZE,
+            nodes: Sorter::new(),
+            edges: Sorter::new(),
+            _lock: lock,
+        })
+    }
+
+    /// Сжатие создаваемых сегментов (сохраняется в metadata.json)
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Директория базы
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Сколько записей держать в памяти до выгрузки run-файла
+    pub fn with_run_size(mut self, run_size: usize) -> Self {
+        self.run_size = run_size.max(1);
+        self
+    }
+
+    /// Добавить ноды; при повторе id побеждает последняя добавленная
+    pub fn add_nodes(&mut self, nodes: impl IntoIterator<Item = NodeRecord>) -> Result<()> {
+        for n in nodes {
+            self.nodes.buffer.push(SpillNode {
+                id: n.id,
+                node_type: n.node_type,
+                name: n.name,
+                file: n.file,
+                version: n.version,
+                exported: n.exported,
+                replaces: n.replaces,
+                deleted: n.deleted,
+                metadata: n.metadata,
+            });
+            if self.nodes.buffer.len() >= self.run_size {
+                let path = self.tmp_dir.join(format!("nodes-{:04}.run", self.nodes.runs.len()));
+                self.nodes.spill(path, |n| n.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Добавить рёбра (концы не проверяются, как в `add_edges(.., true)`)
+    ///
+    /// При повторе (src, dst, type) побеждает последнее добавленное, tombstone удаляет ребро.
+    pub fn add_edges(&mut self, edges: impl IntoIterator<Item = EdgeRecord>) -> Result<()> {
+        for e in edges {
+            self.edges.buffer.push(SpillEdge {
+                src: e.src,
+                dst: e.dst,
+                edge_type: e.edge_type,
+                version: e.version,
+                metadata: e.metadata,
+                deleted: e.deleted,
+            });
+            if self.edges.buffer.len() >= self.run_size {
+                let path = self.tmp_dir.join(format!("edges-{:04}.run", self.edges.runs.len()));
+                self.edges.spill(path, edge_key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Слить runs и записать segments, strings.bin и metadata.json
+    pub fn finish(self) -> Result<BulkLoadStats> {
+        self.load(None).map(|(stats, _)| stats)
+    }
+
+    /// Как `finish`, плюс индекс рёбер, собранный при записи edges.bin
+    pub fn finish_indexed(self) -> Result<(BulkLoadStats, EdgeIndex)> {
+        self.load(Some(EdgeIndex::default()))
+            .map(|(stats, index)| (stats, index.unwrap_or_default()))
+    }
+
+    fn load(mut self, mut index: Option<EdgeIndex>) -> Result<(BulkLoadStats, Option<EdgeIndex>)> {
+        let _span = tracing::info_span!("bulk_load", path = ?self.path).entered();
+        let mut stats = BulkLoadStats {
+            spilled_runs: self.nodes.runs.len() + self.edges.runs.len(),
+            ..BulkLoadStats::default()
+        };
+        // База новая: словарь тоже новый, строки сразу уходят в bulk-tmp
+        let mut dictionary = SpillDictionary::create(&self.tmp_dir)?;
+
+        // Ноды: id по возрастанию, из дублей остаётся последняя, tombstones отбрасываются
+        let mut columns = ["ids", "types", "files", "names", "versions", "exported", "deleted", "metadata", "replaces"]
+            .iter()
+            .map(|name| ColumnSpill::create(self.tmp_dir.join(format!("nodes.{}.col", name))))
+            .collect::<Result<Vec<_>>>()?;
+        let mut pending: Option<SpillNode> = None;
+        let mut write_node = |n: SpillNode, columns: &mut [ColumnSpill], dictionary: &mut SpillDictionary| -> Result<()> {
+            if n.deleted {
+                return Ok(());
+            }
+            // Те же sentinel'ы, что и в SegmentWriter::write_nodes
+            let type_id = n.node_type.as_deref().map(|t| dictionary.intern(t)).transpose()?.unwrap_or(0);
+            let file_id = n.file.as_deref().map(|f| dictionary.intern(f)).transpose()?.map_or(0, |id| id + 1);
+            let name_id = n.name.as_deref().map(|s| dictionary.intern(s)).transpose()?.map_or(0, |id| id + 1);
+            let version_id = dictionary.intern(&n.version)?;
+            let metadata_id = n.metadata.as_deref().map(|m| dictionary.intern(m)).transpose()?.unwrap_or(0);
+
+            columns[0].write(&n.id.to_le_bytes())?;
+            columns[1].write(&type_id.to_le_bytes())?;
+            columns[2].write(&file_id.to_le_bytes())?;
+            columns[3].write(&name_id.to_le_bytes())?;
+            columns[4].write(&version_id.to_le_bytes())?;
+            columns[5].write(&[n.exported as u8])?;
+            columns[6].write(&[0])?;
+            columns[7].write(&metadata_id.to_le_bytes())?;
+            columns[8].write(&n.replaces.unwrap_or(0).to_le_bytes())?;
+            stats.nodes += 1;
+            Ok(())
+        };
+        std::mem::replace(&mut self.nodes, Sorter::new()).merge(|n| n.id, |node| {
+            match pending.take() {
+                Some(prev) if prev.id != node.id => write_node(prev, &mut columns, &mut dictionary)?,
+                _ => {}
+            }
+            pending = Some(node);
+            Ok(())
+        })?;
+        if let Some(last) = pending.take() {
+            write_node(last, &mut columns, &mut dictionary)?;
+        }
+        let node_columns = columns.into_iter().map(ColumnSpill::finish).collect::<Result<Vec<_>>>()?;
+
+        // Рёбра: по (src, dst, type), чтобы adjacency и сжатие шли подряд;
+        // как и у нод, из дублей ключа остаётся последнее, tombstones отбрасываются
+        let mut columns = ["src", "dst", "types", "metadata", "deleted", "versions"]
+            .iter()
+            .map(|name| ColumnSpill::create(self.tmp_dir.join(format!("edges.{}.col", name))))
+            .collect::<Result<Vec<_>>>()?;
+        let mut pending: Option<SpillEdge> = None;
+        let mut write_edge = |e: SpillEdge, columns: &mut [ColumnSpill], dictionary: &mut SpillDictionary| -> Result<()> {
+            if e.deleted {
+                return Ok(());
+            }
+            let type_id = e.edge_type.as_deref().map(|t| dictionary.intern(t)).transpose()?.unwrap_or(0);
+            let metadata_id = e.metadata.as_deref().map(|m| dictionary.intern(m)).transpose()?.unwrap_or(0);
+            let version_id = dictionary.intern(&e.version)?;
+            if let Some(ref mut index) = index {
+                index.insert(stats.edges, e.src, e.dst);
+            }
+            columns[0].write(&e.src.to_le_bytes())?;
+            columns[1].write(&e.dst.to_le_bytes())?;
+            columns[2].write(&type_id.to_le_bytes())?;
+            columns[3].write(&metadata_id.to_le_bytes())?;
+            columns[4].write(&[0])?;
+            columns[5].write(&version_id.to_le_bytes())?;
+            stats.edges += 1;
+            Ok(())
+        };
+        std::mem::replace(&mut self.edges, Sorter::new()).merge(edge_key, |edge| {
+            match pending.take() {
+                Some(prev) if edge_key(&prev) != edge_key(&edge) => write_edge(prev, &mut columns, &mut dictionary)?,
+                _ => {}
+            }
+            pending = Some(edge);
+            Ok(())
+        })?;
+        if let Some(last) = pending.take() {
+            write_edge(last, &mut columns, &mut dictionary)?;
+        }
+        let edge_columns = columns.into_iter().map(ColumnSpill::finish).collect::<Result<Vec<_>>>()?;
+
+        stats.dictionary_bytes = dictionary.resident_bytes();
+
+        // Словарь раньше сегментов: сегменты не ссылаются на несуществующие id
+        let dictionary_id = dictionary.id();
+        dictionary.write(&self.path, self.compression)?;
+        self.assemble("nodes.bin", SegmentHeader::new(stats.nodes as u64, 0, dictionary_id), &node_columns, &[0, 8])?;
+        self.assemble("edges.bin", SegmentHeader::new(0, stats.edges as u64, dictionary_id), &edge_columns, &[0, 1])?;
+
+        let metadata = GraphMetadata {
+            node_count: stats.nodes,
+            edge_count: stats.edges,
+            compression: self.compression,
+            ..GraphMetadata::default()
+        };
+        SegmentWriter::new(&self.path).write_metadata(&metadata)?;
+
+        tracing::info!("Bulk loaded {} nodes, {} edges ({} runs spilled, dictionary index {} bytes)",
+            stats.nodes, stats.edges, stats.spilled_runs, stats.dictionary_bytes);
+        Ok((stats, index))
+    }
+
+    /// Собрать сегмент из выгруженных колонок: header, секции, checksum footer
+    fn assemble(&self, file_name: &str, mut header: SegmentHeader, columns: &[(PathBuf, Checksum)], u128_columns: &[usize]) -> Result<()> {
+        let path = self.path.join(file_name);
+        let tmp_path = self.path.join(format!("{}.tmp", file_name));
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(&tmp_path)?;
+        let mut writer = BufWriter::new(file);
+
+        if self.compression.is_enabled() {
+            header.magic = MAGIC_COMPRESSED;
+        }
+        let segment_writer = SegmentWriter::new(&self.path).with_compression(self.compression);
+        segment_writer.write_header(&mut writer, &header)?;
+
+        let mut sections = SectionWriter::new(&mut writer, self.compression);
+        for (idx, (column_path, checksum)) in columns.iter().enumerate() {
+            let column = BufReader::new(File::open(column_path)?);
+            sections.copy_section(column, *checksum, u128_columns.contains(&idx))?;
+            fs::remove_file(column_path)?;
+        }
+        let checksums = sections.checksums;
+        write_checksum_footer(&mut writer, &checksums)?;
+        commit_file(writer, &tmp_path, &path)
+    }
+}
+
+impl Drop for BulkLoader {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.tmp_dir);
+    }
+}
+
+fn edge_key(e: &SpillEdge) -> (u128, u128, Option<String>) {
+    (e.src, e.dst, e.edge_type.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{GraphEngine, GraphStore};
+    use crate::test_support::{self, edge};
+    use tempfile::TempDir;
+
+    fn node(id: u128, name: &str) -> NodeRecord {
+        NodeRecord {
+            exported: id.is_multiple_of(3),
+            file: Some(format!("src/f{}.js", id % 7)),
+            metadata: id.is_multiple_of(5).then(|| format!(r#"{{"line":{}}}"#, id)),
+            ..test_support::node(id, "FUNCTION", name)
+        }
+    }
+
+    #[test]
+    fn test_bulk_load_with_spilled_runs() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("g.rfdb");
+
+        let mut loader = BulkLoader::create(&path).unwrap().with_run_size(16);
+        // Обратный порядок, чтобы сортировка и слияние runs были нужны
+        loader.add_nodes((1..=100u128).rev().map(|id| node(id, &format!("fn{}", id)))).unwrap();
+        loader.add_edges((1..100u128).rev().map(|id| edge(id, id + 1, "CALLS"))).unwrap();
+        // Повтор id: побеждает последняя запись; tombstone удаляет ноду
+        loader.add_nodes(vec![node(7, "renamed")]).unwrap();
+        let mut gone = node(8, "gone");
+        gone.deleted = true;
+        loader.add_nodes(vec![gone]).unwrap();
+        // Рёбра так же: дубль схлопывается, tombstone удаляет ребро
+        loader.add_edges(vec![edge(41, 42, "CALLS")]).unwrap();
+        let mut cut = edge(60, 61, "CALLS");
+        cut.deleted = true;
+        loader.add_edges(vec![cut]).unwrap();
+
+        let stats = loader.finish().unwrap();
+        assert_eq!(stats.nodes, 99);
+        assert_eq!(stats.edges, 98);
+        assert!(stats.spilled_runs > 2, "{:?}", stats);
+        assert!(!path.join(BULK_TMP_DIR).exists());
+
+        let engine = GraphEngine::open(&path).unwrap();
+        engine.verify().unwrap();
+        assert_eq!(engine.node_count(), 99);
+        assert_eq!(engine.get_node(7).unwrap().name.as_deref(), Some("renamed"));
+        assert!(engine.get_node(8).is_none());
+        let node_10 = engine.get_node(10).unwrap();
+        assert_eq!(node_10.metadata.as_deref(), Some(r#"{"line":10}"#));
+        assert_eq!(node_10.file.as_deref(), Some("src/f3.js"));
+        assert_eq!(engine.neighbors(41, &["CALLS"]), vec![42]);
+        assert!(engine.neighbors(60, &["CALLS"]).is_empty());
+        assert_eq!(engine.reachability(&[1], 200, &["CALLS"], false).len(), 60);
+    }
+
+    #[test]
+    fn test_bulk_load_keeps_strings_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("g.rfdb");
+
+        // ~5 MB уникальных metadata: в памяти остаётся только индекс хэшей
+        let padding = "x".repeat(500);
+        let mut loader = BulkLoader::create(&path).unwrap().with_run_size(256);
+        loader.add_nodes((1..=10_000u128).map(|id| NodeRecord {
+            metadata: Some(format!(r#"{{"id":{},"doc":"{}"}}"#, id, padding)),
+            ..node(id, &format!("fn{}", id))
+        })).unwrap();
+        loader.add_edges((1..10_000u128).map(|id| edge(id, id + 1, "CALLS"))).unwrap();
+
+        let (engine, stats) = GraphEngine::finish_bulk_load(loader).unwrap();
+        let strings_size = fs::metadata(path.join("strings.bin")).unwrap().len() as usize;
+        assert!(strings_size > 5_000_000, "{}", strings_size);
+        assert!(stats.dictionary_bytes * 5 < strings_size, "{:?}", stats);
+
+        // Adjacency собрана при записи edges.bin и совпадает с построенной при open
+        engine.verify().unwrap();
+        assert_eq!(engine.neighbors(41, &["CALLS"]), vec![42]);
+        assert_eq!(engine.get_incoming_edges(10_000, None).len(), 1);
+        assert!(engine.get_node(77).unwrap().metadata.unwrap().starts_with(r#"{"id":77,"#));
+        drop(engine);
+        let reopened = GraphEngine::open(&path).unwrap();
+        assert_eq!(reopened.reachability(&[1], usize::MAX, &["CALLS"], false).len(), 10_000);
+    }
+
+    #[test]
+    fn test_bulk_load_compressed_and_existing_db() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("g.rfdb");
+
+        let mut loader = BulkLoader::create(&path).unwrap()
+            .with_compression(Compression::Lz4)
+            .with_run_size(10);
+        loader.add_nodes((1..=50u128).map(|id| node(id, "f"))).unwrap();
+        loader.add_edges((1..50u128).map(|id| edge(id, id + 1, "CALLS"))).unwrap();
+        loader.finish().unwrap();
+
+        let mut engine = GraphEngine::open(&path).unwrap();
+        engine.verify().unwrap();
+        assert_eq!(engine.compression(), Compression::Lz4);
+        assert_eq!(engine.node_count(), 50);
+        assert_eq!(engine.get_incoming_edges(50, None).len(), 1);
+
+        // Дальше база работает как обычно
+        engine.add_nodes(vec![node(51, "late")]);
+        engine.flush().unwrap();
+        assert_eq!(engine.node_count(), 51);
+        drop(engine);
+
+        let err = BulkLoader::create(&path).err().unwrap();
+        assert!(err.to_string().contains("already has segments"), "{}", err);
+    }
+}
@@ -0,0 +1,212 @@
+//! Блочное сжатие секций сегмента (формат v3, magic `SGRZ`)
+//!
+//! Каждая колонка / string table хранится одним блоком:
+//! `[u8 codec][u64 raw_len][u64 encoded_len][encoded bytes]`.
+//! Writer пробует подходящие для колонки кодеки и оставляет самый короткий;
+//! reader распаковывает всё обратно в плоский layout.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{GraphError, Result};
+
+/// Режим сжатия сегментов, задаётся на уровне базы (metadata.json)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Плоские колонки, читаются прямо из mmap
+    #[default]
+    None,
+    /// LZ4 блоки (+ delta/varint для u128 колонок), распаковываются при открытии
+    Lz4,
+}
+
+impl Compression {
+    pub fn is_enabled(self) -> bool {
+        self != Compression::None
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            other => Err(GraphError::InvalidFormat(format!("Unknown compression: {}", other))),
+        }
+    }
+}
+
+/// Кодек отдельного блока
+pub const CODEC_RAW: u8 = 0;
+pub const CODEC_LZ4: u8 = 1;
+pub const CODEC_DELTA_VARINT_U128: u8 = 2;
+
+/// Размер заголовка блока: codec + raw_len + encoded_len
+pub const BLOCK_HEADER_SIZE: usize = 1 + 8 + 8;
+
+/// Закодировать секцию наименьшим из подходящих кодеков
+///
+/// `u128_column` - секция является массивом u128 (id, src, dst).
+pub fn encode_block(raw: &[u8], u128_column: bool) -> (u8, Vec<u8>) {
+    let mut best = (CODEC_RAW, raw.to_vec());
+
+    let lz4 = lz4_flex::block::compress(raw);
+    if lz4.len() < best.1.len() {
+        best = (CODEC_LZ4, lz4);
+    }
+
+    if u128_column && raw.len().is_multiple_of(16) {
+        let delta = encode_delta_varint(raw);
+        if delta.len() < best.1.len() {
+            best = (CODEC_DELTA_VARINT_U128, delta);
+        }
+    }
+    best
+}
+
+/// Записать блок: заголовок + данные
+pub fn write_block(out: &mut Vec<u8>, codec: u8, raw_len: usize, encoded: &[u8]) {
+    out.push(codec);
+    out.extend_from_slice(&(raw_len as u64).to_le_bytes());
+    out.extend_from_slice(&(encoded.len() as u64).to_le_bytes());
+    out.extend_from_slice(encoded);
+}
+
+/// Прочитать блок начиная с `pos`, вернуть распакованные данные и позицию после блока
+pub fn read_block(data: &[u8], pos: usize) -> Result<(Vec<u8>, usize)> {
+    let header = data.get(pos..pos + BLOCK_HEADER_SIZE)
+        .ok_or_else(|| GraphError::InvalidFormat(format!("Truncated block header at {}", pos)))?;
+    let codec = header[0];
+    let raw_len = u64::from_le_bytes(header[1..9].try_into().unwrap());
+    let encoded_len = u64::from_le_bytes(header[9..17].try_into().unwrap());
+
+    let start = pos + BLOCK_HEADER_SIZE;
+    let end = usize::try_from(encoded_len).ok()
+        .and_then(|len| start.checked_add(len))
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| GraphError::InvalidFormat(format!("Block at {} exceeds file size", pos)))?;
+    let encoded = &data[start..end];
+
+    // LZ4 сжимает не лучше ~255:1, varint - не меньше байта на u128;
+    // больший raw_len означает битый заголовок, не выделяем под него память
+    let max_raw = (encoded.len() as u64).saturating_mul(255).saturating_add(16);
+    if raw_len > max_raw {
+        return Err(GraphError::InvalidFormat(format!(
+            "Block at {}: raw length {} is implausible for {} encoded bytes", pos, raw_len, encoded.len()
+        )));
+    }
+    let raw_len = raw_len as usize;
+
+    let raw = match codec {
+        CODEC_RAW => encoded.to_vec(),
+        CODEC_LZ4 => lz4_flex::block::decompress(encoded, raw_len)
+            .map_err(|e| GraphError::InvalidFormat(format!("LZ4 block at {}: {}", pos, e)))?,
+        CODEC_DELTA_VARINT_U128 => decode_delta_varint(encoded, raw_len)?,
+        other => return Err(GraphError::InvalidFormat(format!("Unknown block codec {} at {}", other, pos))),
+    };
+
+    if raw.len() != raw_len {
+        return Err(GraphError::InvalidFormat(format!(
+            "Block at {}: decoded {} bytes, expected {}", pos, raw.len(), raw_len
+        )));
+    }
+    Ok((raw, end))
+}
+
+/// u128 LE массив -> zigzag(delta) в LEB128 varint
+///
+/// Сортированные колонки (src рёбер) дают дельты 0 для повторов.
+fn encode_delta_varint(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() / 2);
+    let mut prev = 0u128;
+    for chunk in raw.chunks_exact(16) {
+        let value = u128::from_le_bytes(chunk.try_into().unwrap());
+        let delta = value.wrapping_sub(prev) as i128;
+        let mut zigzag = ((delta << 1) ^ (delta >> 127)) as u128;
+        prev = value;
+
+        loop {
+            let byte = (zigzag & 0x7f) as u8;
+            zigzag >>= 7;
+            if zigzag == 0 {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+    out
+}
+
+fn decode_delta_varint(encoded: &[u8], raw_len: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(raw_len);
+    let mut prev = 0u128;
+    let mut pos = 0;
+
+    while pos < encoded.len() {
+        let mut zigzag = 0u128;
+        let mut shift = 0;
+        loop {
+            let byte = *encoded.get(pos)
+                .ok_or_else(|| GraphError::InvalidFormat("Truncated varint".into()))?;
+            pos += 1;
+            if shift >= 128 {
+                return Err(GraphError::InvalidFormat("Varint too long".into()));
+            }
+            zigzag |= ((byte & 0x7f) as u128) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let delta = ((zigzag >> 1) as i128) ^ -((zigzag & 1) as i128);
+        prev = prev.wrapping_add(delta as u128);
+        out.extend_from_slice(&prev.to_le_bytes());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(raw: &[u8], u128_column: bool) -> u8 {
+        let (codec, encoded) = encode_block(raw, u128_column);
+        let mut block = Vec::new();
+        write_block(&mut block, codec, raw.len(), &encoded);
+        let (decoded, end) = read_block(&block, 0).unwrap();
+        assert_eq!(decoded, raw);
+        assert_eq!(end, block.len());
+        codec
+    }
+
+    #[test]
+    fn test_codecs_roundtrip() {
+        // Сортированные id с повторами - delta/varint
+        let ids: Vec<u8> = [5u128, 5, 5, 9, u128::MAX - 3, u128::MAX]
+            .iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(roundtrip(&ids, true), CODEC_DELTA_VARINT_U128);
+
+        // Повторяющийся JSON - lz4
+        let text = r#"{"line":1,"column":2}"#.repeat(50);
+        assert_eq!(roundtrip(text.as_bytes(), false), CODEC_LZ4);
+
+        // Короткие данные не сжимаются
+        assert_eq!(roundtrip(&[1, 2, 3], false), CODEC_RAW);
+        roundtrip(&[], true);
+    }
+
+    #[test]
+    fn test_corrupted_block_is_error() {
+        let mut block = Vec::new();
+        write_block(&mut block, CODEC_LZ4, 1 << 40, &[0; 4]);
+        assert!(read_block(&block, 0).is_err());
+        assert!(read_block(&block[..10], 0).is_err());
+
+        let mut block = Vec::new();
+        write_block(&mut block, 42, 1, &[0]);
+        assert!(read_block(&block, 0).is_err());
+    }
+}
@@ -0,0 +1,690 @@
+//! Общий словарь строк strings.bin (format v5+)
+//!
+//! Layout: `[magic "STRD"][u16 version][u64 dictionary_id]`, затем чанки
+//! `[u8 compressed][u64 section_len][section][checksum]`. Section - string table
+//! (при `compressed` - блок `encode_block`), checksum считается по несжатой таблице.
+//! Строки адресуются u32 id (сквозной номер по всем чанкам), id 0 - пустая строка.
+//! Словарь только дописывается: запись сегмента добавляет в конец чанк
+//! только с новыми строками, уже записанные байты не меняются, поэтому
+//! id стабильны и старые сегменты остаются валидными. Недописанный хвостовой чанк (прерванный flush)
+//! при открытии игнорируется и обрезается следующей записью. Сегмент хранит
+//! dictionary_id и не откроется с чужим strings.bin (прерванный restore).
+//! Мусор от удалённых записей убирает `compact`: он собирает новый словарь
+//! (новый id) только из живых строк.
+//! Layout v1 (одна string table + checksum footer) читается как есть
+//! и переписывается в текущий при первом дописывании.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use memmap2::Mmap;
+use crate::error::{GraphError, Result};
+use crate::storage::compression::{Compression, read_block};
+use crate::storage::segment::{Checksum, CHECKSUM_SIZE, read_checksum_footer, section_checksum};
+use crate::storage::string_table::{StringTable, StringTableView};
+use crate::storage::writer::{SectionWriter, commit_file};
+
+/// Имя файла словаря в директории базы
+pub const DICTIONARY_FILE: &str = "strings.bin";
+
+/// Магическое число strings.bin
+pub const DICTIONARY_MAGIC: [u8; 4] = *b"STRD";
+
+/// Версия layout strings.bin
+pub const DICTIONARY_VERSION: u16 = 2;
+
+/// Layout с одной string table и checksum footer
+const LEGACY_DICTIONARY_VERSION: u16 = 1;
+
+/// magic + version + dictionary_id
+const DICTIONARY_HEADER_SIZE: usize = 4 + 2 + 8;
+
+/// compressed + section_len
+const CHUNK_HEADER_SIZE: usize = 1 + 8;
+
+/// Один чанк словаря: строки с id от `first_id`
+struct DictionaryChunk {
+    first_id: u32,
+    /// Распакованная таблица сжатого чанка (иначе таблица читается из mmap)
+    decoded: Option<Vec<u8>>,
+    /// Байты таблицы в `decoded` или в mmap
+    range: Range<usize>,
+    table: StringTableView,
+    checksum: Option<Checksum>,
+}
+
+/// Открытый (mmap) словарь строк
+pub struct StringDictionary {
+    mmap: Mmap,
+    id: u64,
+    chunks: Vec<DictionaryChunk>,
+    len: usize,
+    /// Конец последнего целого чанка, дальше - недописанный хвост
+    valid_len: usize,
+    legacy: bool,
+}
+
+impl StringDictionary {
+    /// Открыть strings.bin в директории базы
+    pub fn open(db_path: &Path) -> Result<Self> {
+        let file = File::open(db_path.join(DICTIONARY_FILE))?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < DICTIONARY_HEADER_SIZE || mmap[0..4] != DICTIONARY_MAGIC {
+            return Err(GraphError::InvalidFormat("strings.bin: bad magic".into()));
+        }
+        let version = u16::from_le_bytes([mmap[4], mmap[5]]);
+        let id = u64::from_le_bytes(mmap[6..14].try_into().unwrap());
+
+        let (chunks, valid_len) = match version {
+            DICTIONARY_VERSION => parse_chunks(&mmap)?,
+            LEGACY_DICTIONARY_VERSION => (vec![parse_legacy(&mmap)?], mmap.len()),
+            other => return Err(GraphError::InvalidFormat(format!(
+                "strings.bin: unsupported version {} (expected {})", other, DICTIONARY_VERSION
+            ))),
+        };
+        let len = chunks.iter().map(|c| c.table.len()).sum();
+
+        Ok(Self { mmap, id, chunks, len, valid_len, legacy: version == LEGACY_DICTIONARY_VERSION })
+    }
+
+    /// Открыть strings.bin, если он есть (базы до v5 его не имеют)
+    pub fn open_if_exists(db_path: &Path) -> Result<Option<Self>> {
+        if db_path.join(DICTIONARY_FILE).exists() {
+            Self::open(db_path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Идентификатор словаря, записываемый в header сегментов
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn chunk_bytes<'a>(&'a self, chunk: &'a DictionaryChunk) -> &'a [u8] {
+        chunk.decoded.as_deref().unwrap_or(&self.mmap)
+    }
+
+    /// Строка по id, O(log чанков)
+    pub fn get(&self, id: u32) -> Option<&str> {
+        let idx = self.chunks.partition_point(|c| c.first_id <= id).checked_sub(1)?;
+        let chunk = &self.chunks[idx];
+        chunk.table.get_by_id(self.chunk_bytes(chunk), id - chunk.first_id)
+    }
+
+    /// Найти id строки (линейный проход)
+    pub fn find(&self, s: &str) -> Option<u32> {
+        self.chunks.iter().find_map(|chunk| {
+            chunk.table.find_id(self.chunk_bytes(chunk), s).map(|id| chunk.first_id + id)
+        })
+    }
+
+    pub fn contains_id(&self, id: u32) -> bool {
+        (id as usize) < self.len
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Число чанков (flush'ей, дописавших строки)
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Сверить checksums таблиц строк
+    pub fn verify(&self) -> Result<()> {
+        for chunk in &self.chunks {
+            let table = &self.chunk_bytes(chunk)[chunk.range.clone()];
+            if chunk.checksum.is_some_and(|expected| section_checksum(table) != expected) {
+                return Err(GraphError::InvalidFormat(format!(
+                    "strings.bin: checksum mismatch in chunk starting at string #{}", chunk.first_id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Разобрать чанки v2; недописанный последний чанк отбрасывается
+fn parse_chunks(data: &[u8]) -> Result<(Vec<DictionaryChunk>, usize)> {
+    let mut chunks = Vec::new();
+    let mut first_id = 0u32;
+    let mut pos = DICTIONARY_HEADER_SIZE;
+
+    while pos < data.len() {
+        let section_end = data.get(pos + 1..pos + CHUNK_HEADER_SIZE)
+            .map(|len| u64::from_le_bytes(len.try_into().unwrap()))
+            .and_then(|len| usize::try_from(len).ok())
+            .and_then(|len| (pos + CHUNK_HEADER_SIZE).checked_add(len))
+            .filter(|&end| end.checked_add(CHECKSUM_SIZE).is_some_and(|end| end <= data.len()));
+        let Some(section_end) = section_end else {
+            tracing::warn!("strings.bin: ignoring incomplete chunk at {} ({} bytes)", pos, data.len() - pos);
+            break;
+        };
+
+        let section = pos + CHUNK_HEADER_SIZE..section_end;
+        let (decoded, range) = match data[pos] {
+            0 => (None, section),
+            1 => {
+                let (raw, end) = read_block(&data[..section_end], section.start)?;
+                if end != section_end {
+                    return Err(GraphError::InvalidFormat(format!("strings.bin: chunk at {} has trailing bytes", pos)));
+                }
+                let len = raw.len();
+                (Some(raw), 0..len)
+            }
+            other => return Err(GraphError::InvalidFormat(format!(
+                "strings.bin: unknown chunk encoding {} at {}", other, pos
+            ))),
+        };
+        let table = StringTableView::parse(decoded.as_deref().unwrap_or(data), range.clone(), true)?;
+        let checksum = data[section_end..section_end + CHECKSUM_SIZE].try_into().unwrap();
+
+        let next_id = u32::try_from(first_id as usize + table.len())
+            .map_err(|_| GraphError::InvalidFormat("strings.bin: more than u32::MAX strings".into()))?;
+        chunks.push(DictionaryChunk { first_id, decoded, range, table, checksum: Some(checksum) });
+        first_id = next_id;
+        pos = section_end + CHECKSUM_SIZE;
+    }
+    Ok((chunks, pos.min(data.len())))
+}
+
+/// Разобрать layout v1: одна таблица + checksum footer
+fn parse_legacy(data: &[u8]) -> Result<DictionaryChunk> {
+    let (checksums, table_end) = read_checksum_footer(data)?;
+    let checksum = match checksums.as_deref() {
+        None => None,
+        Some([checksum]) => Some(*checksum),
+        Some(other) => return Err(GraphError::InvalidFormat(format!(
+            "strings.bin: expected 1 checksum, found {}", other.len()
+        ))),
+    };
+    let range = DICTIONARY_HEADER_SIZE..table_end;
+    let table = StringTableView::parse(data, range.clone(), true)?;
+    Ok(DictionaryChunk { first_id: 0, decoded: None, range, table, checksum })
+}
+
+/// Закодировать string table в чанк (через SectionWriter: блоком при сжатии)
+fn encode_chunk(table: Vec<u8>, compression: Compression) -> Result<Vec<u8>> {
+    let mut section = Vec::new();
+    let mut sections = SectionWriter::new(&mut section, compression);
+    sections.write_section(table, false)?;
+    let checksum = sections.checksums[0];
+
+    let mut chunk = Vec::with_capacity(CHUNK_HEADER_SIZE + section.len() + CHECKSUM_SIZE);
+    chunk.push(compression.is_enabled() as u8);
+    chunk.extend_from_slice(&(section.len() as u64).to_le_bytes());
+    chunk.extend_from_slice(&section);
+    chunk.extend_from_slice(&checksum);
+    Ok(chunk)
+}
+
+fn write_header<W: Write>(writer: &mut W, id: u64) -> Result<()> {
+    writer.write_all(&DICTIONARY_MAGIC)?;
+    writer.write_all(&DICTIONARY_VERSION.to_le_bytes())?;
+    writer.write_all(&id.to_le_bytes())?;
+    Ok(())
+}
+
+/// Есть ли у файла другие hard link'и (checkpoint, restore)
+///
+/// Такой файл нельзя дописывать на месте - изменится и копия.
+#[cfg(unix)]
+fn is_shared(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(path)?.nlink() > 1)
+}
+
+#[cfg(not(unix))]
+fn is_shared(_path: &Path) -> Result<bool> {
+    // Число ссылок не узнать - всегда пишем копию
+    Ok(true)
+}
+
+/// Хэш строки для индексов словаря (128 бит считаются уникальными, как id нод)
+fn string_hash(s: &str) -> u128 {
+    u128::from_le_bytes(blake3::hash(s.as_bytes()).as_bytes()[..16].try_into().unwrap())
+}
+
+/// Builder словаря для writer'а: продолжает существующий strings.bin
+///
+/// Уже записанные строки в памяти только как хэш -> id; `write`
+/// дописывает чанк со строками, добавленными после прошлой записи.
+pub struct DictionaryBuilder {
+    id: u64,
+    index: HashMap<u128, u32>,
+    /// Строки, ещё не записанные в strings.bin (id от `persisted`)
+    pending: StringTable,
+    persisted: u32,
+    /// Длина целой части strings.bin; None - файл пишется заново
+    file_len: Option<u64>,
+}
+
+impl DictionaryBuilder {
+    /// Новый пустой словарь со свежим id
+    pub fn new() -> Self {
+        let mut builder = Self::empty(fresh_dictionary_id());
+        builder.intern("");
+        builder
+    }
+
+    fn empty(id: u64) -> Self {
+        Self { id, index: HashMap::new(), pending: StringTable::new(), persisted: 0, file_len: None }
+    }
+
+    /// Продолжить словарь из `db_path`, либо начать новый
+    pub fn load_or_new(db_path: &Path) -> Result<Self> {
+        let Some(existing) = StringDictionary::open_if_exists(db_path)? else {
+            return Ok(Self::new());
+        };
+
+        let mut builder = Self::empty(existing.id());
+        for id in 0..existing.len() as u32 {
+            let s = existing.get(id).ok_or_else(|| GraphError::InvalidFormat(
+                format!("strings.bin: string #{} is unreadable", id)
+            ))?;
+            if existing.legacy {
+                // v1 переписывается целиком в текущем layout, id те же
+                builder.intern(s);
+            } else if builder.index.insert(string_hash(s), id).is_some() {
+                return Err(GraphError::InvalidFormat("strings.bin: duplicate strings".into()));
+            }
+        }
+        if existing.legacy {
+            if builder.pending.len() != existing.len() {
+                return Err(GraphError::InvalidFormat("strings.bin: duplicate strings".into()));
+            }
+        } else {
+            builder.persisted = existing.len() as u32;
+            builder.file_len = Some(existing.valid_len as u64);
+        }
+        Ok(builder)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Добавить строку, вернуть её id
+    pub fn intern(&mut self, s: &str) -> u32 {
+        let hash = string_hash(s);
+        if let Some(&id) = self.index.get(&hash) {
+            return id;
+        }
+        let id = self.persisted + self.pending.intern_id(s);
+        self.index.insert(hash, id);
+        id
+    }
+
+    /// Дописать в strings.bin строки, добавленные после прошлой записи
+    ///
+    /// Записанные байты не меняются, поэтому сегменты, ссылающиеся
+    /// на предыдущую версию файла, остаются валидными.
+    pub fn write(&mut self, db_path: &Path, compression: Compression) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let path = db_path.join(DICTIONARY_FILE);
+        let mut table = Vec::new();
+        self.pending.write_to(&mut table)?;
+        let chunk = encode_chunk(table, compression)?;
+
+        let file_len = match self.file_len {
+            Some(valid_len) if !is_shared(&path)? => {
+                let mut file = OpenOptions::new().write(true).open(&path)?;
+                // Обрезаем недописанный хвост прерванной записи
+                file.set_len(valid_len)?;
+                file.seek(SeekFrom::End(0))?;
+                file.write_all(&chunk)?;
+                file.sync_all()?;
+                valid_len + chunk.len() as u64
+            }
+            existing => {
+                // Новый файл, либо копия файла, разделённого с checkpoint
+                let tmp_path = db_path.join(format!("{}.tmp", DICTIONARY_FILE));
+                let file = OpenOptions::new().create(true).write(true).truncate(true).open(&tmp_path)?;
+                let mut writer = BufWriter::new(file);
+                let copied = match existing {
+                    Some(valid_len) => std::io::copy(&mut File::open(&path)?.take(valid_len), &mut writer)?,
+                    None => {
+                        write_header(&mut writer, self.id)?;
+                        DICTIONARY_HEADER_SIZE as u64
+                    }
+                };
+                writer.write_all(&chunk)?;
+                commit_file(writer, &tmp_path, &path)?;
+                copied + chunk.len() as u64
+            }
+        };
+
+        tracing::debug!("Appended {} strings to {:?} ({} total)", self.pending.len(), path, self.index.len());
+        self.persisted += self.pending.len() as u32;
+        self.pending = StringTable::new();
+        self.file_len = Some(file_len);
+        Ok(())
+    }
+}
+
+impl Default for DictionaryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Словарь для BulkLoader: строки сразу уходят во временные файлы
+///
+/// В памяти только blake3-хэш строки -> id (как и id нод, 128 бит считаются
+/// уникальными), поэтому длинные metadata JSON не копятся в RAM. `write`
+/// потоком собирает из временных файлов обычный strings.bin.
+pub(crate) struct SpillDictionary {
+    id: u64,
+    index: HashMap<u128, u32>,
+    data: BufWriter<File>,
+    data_path: PathBuf,
+    data_len: u64,
+    offsets: BufWriter<File>,
+    offsets_path: PathBuf,
+}
+
+impl SpillDictionary {
+    /// Новый словарь со свежим id, временные файлы в `tmp_dir`
+    pub(crate) fn create(tmp_dir: &Path) -> Result<Self> {
+        let data_path = tmp_dir.join("strings.data");
+        let offsets_path = tmp_dir.join("strings.offsets");
+        let mut dictionary = Self {
+            id: fresh_dictionary_id(),
+            index: HashMap::new(),
+            data: BufWriter::new(File::create(&data_path)?),
+            data_path,
+            data_len: 0,
+            offsets: BufWriter::new(File::create(&offsets_path)?),
+            offsets_path,
+        };
+        dictionary.intern("")?;
+        Ok(dictionary)
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Добавить строку, вернуть её id
+    pub(crate) fn intern(&mut self, s: &str) -> Result<u32> {
+        let hash = string_hash(s);
+        if let Some(&id) = self.index.get(&hash) {
+            return Ok(id);
+        }
+
+        // Offsets в string table - u32, как и в StringTable
+        let offset = u32::try_from(self.data_len).map_err(|_| GraphError::InvalidFormat(
+            format!("strings.bin: string data exceeds {} bytes", u32::MAX)
+        ))?;
+        let id = self.index.len() as u32;
+        self.offsets.write_all(&offset.to_le_bytes())?;
+        self.data.write_all(&(s.len() as u32).to_le_bytes())?;
+        self.data.write_all(s.as_bytes())?;
+        self.data_len += 4 + s.len() as u64;
+        self.index.insert(hash, id);
+        Ok(id)
+    }
+
+    /// Оценка памяти под индекс (строки лежат на диске)
+    pub(crate) fn resident_bytes(&self) -> usize {
+        self.index.capacity() * (std::mem::size_of::<u128>() + std::mem::size_of::<u32>())
+    }
+
+    /// Записать strings.bin (один чанк) в `db_path` и удалить временные файлы
+    ///
+    /// Без сжатия таблица копируется потоком; для сжатия читается целиком,
+    /// т.к. чанк - один блок.
+    pub(crate) fn write(mut self, db_path: &Path, compression: Compression) -> Result<()> {
+        self.data.flush()?;
+        self.offsets.flush()?;
+
+        let path = db_path.join(DICTIONARY_FILE);
+        let tmp_path = db_path.join(format!("{}.tmp", DICTIONARY_FILE));
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(&tmp_path)?;
+        let mut writer = BufWriter::new(file);
+        write_header(&mut writer, self.id)?;
+
+        // Тот же layout, что StringTable::write_to
+        let count = self.index.len() as u64;
+        let mut table_parts = File::open(&self.data_path)?.chain(File::open(&self.offsets_path)?);
+        if compression.is_enabled() {
+            let mut table = self.data_len.to_le_bytes().to_vec();
+            (&mut table_parts).take(self.data_len).read_to_end(&mut table)?;
+            table.extend_from_slice(&count.to_le_bytes());
+            table_parts.read_to_end(&mut table)?;
+            writer.write_all(&encode_chunk(table, compression)?)?;
+        } else {
+            let section_len = 8 + self.data_len + 8 + count * 4;
+            writer.write_all(&[0])?;
+            writer.write_all(&section_len.to_le_bytes())?;
+
+            // checksum считается по ходу копирования
+            let mut table = HashingWriter { writer: &mut writer, hasher: blake3::Hasher::new() };
+            table.write_all(&self.data_len.to_le_bytes())?;
+            std::io::copy(&mut (&mut table_parts).take(self.data_len), &mut table)?;
+            table.write_all(&count.to_le_bytes())?;
+            std::io::copy(&mut table_parts, &mut table)?;
+            let checksum = *table.hasher.finalize().as_bytes();
+            writer.write_all(&checksum)?;
+        }
+
+        commit_file(writer, &tmp_path, &path)?;
+        fs::remove_file(&self.data_path)?;
+        fs::remove_file(&self.offsets_path)?;
+
+        tracing::debug!("Written {} strings to {:?}", count, path);
+        Ok(())
+    }
+}
+
+/// Writer, считающий blake3 от всего записанного
+struct HashingWriter<'a, W: Write> {
+    writer: &'a mut W,
+    hasher: blake3::Hasher,
+}
+
+impl<W: Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Уникальный ненулевой id нового словаря
+fn fresh_dictionary_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&nanos.to_le_bytes());
+    hasher.update(&std::process::id().to_le_bytes());
+    hasher.update(&COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    let id = u64::from_le_bytes(hasher.finalize().as_bytes()[..8].try_into().unwrap());
+    id.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dictionary_is_append_only() {
+        let dir = TempDir::new().unwrap();
+
+        let mut builder = DictionaryBuilder::load_or_new(dir.path()).unwrap();
+        let function = builder.intern("FUNCTION");
+        builder.write(dir.path(), Compression::None).unwrap();
+        let first_write = std::fs::read(dir.path().join(DICTIONARY_FILE)).unwrap();
+
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        assert_eq!(dict.get(0), Some(""));
+        assert_eq!(dict.get(function), Some("FUNCTION"));
+        assert_eq!(dict.find("FUNCTION"), Some(function));
+        dict.verify().unwrap();
+
+        // Повторная загрузка сохраняет id строк и самого словаря
+        let mut builder = DictionaryBuilder::load_or_new(dir.path()).unwrap();
+        assert_eq!(builder.id(), dict.id());
+        assert_eq!(builder.intern("FUNCTION"), function);
+        let calls = builder.intern("CALLS");
+        builder.write(dir.path(), Compression::None).unwrap();
+        // Без новых строк файл не трогается
+        builder.write(dir.path(), Compression::None).unwrap();
+
+        // Дописан только чанк с "CALLS", прежние байты не изменились
+        let second_write = std::fs::read(dir.path().join(DICTIONARY_FILE)).unwrap();
+        assert!(second_write.starts_with(&first_write));
+        let calls_table = 8 + (4 + "CALLS".len()) + 8 + 4;
+        assert_eq!(second_write.len() - first_write.len(), CHUNK_HEADER_SIZE + calls_table + CHECKSUM_SIZE);
+
+        let reopened = StringDictionary::open(dir.path()).unwrap();
+        assert_eq!(reopened.chunk_count(), 2);
+        reopened.verify().unwrap();
+        assert_eq!(reopened.get(function), Some("FUNCTION"));
+        assert_eq!(reopened.get(calls), Some("CALLS"));
+        assert_eq!(reopened.get(calls + 1), None);
+        assert_ne!(DictionaryBuilder::new().id(), dict.id());
+    }
+
+    #[test]
+    fn test_spill_dictionary_matches_builder_layout() {
+        let dir = TempDir::new().unwrap();
+        let mut spill = SpillDictionary::create(dir.path()).unwrap();
+        let function = spill.intern("FUNCTION").unwrap();
+        let metadata = spill.intern(r#"{"line":1}"#).unwrap();
+        assert_eq!(spill.intern("FUNCTION").unwrap(), function);
+        let id = spill.id();
+        spill.write(dir.path(), Compression::None).unwrap();
+        assert!(!dir.path().join("strings.data").exists());
+
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        dict.verify().unwrap();
+        assert_eq!((dict.id(), dict.len()), (id, 3));
+        assert_eq!(dict.get(0), Some(""));
+        assert_eq!(dict.get(metadata), Some(r#"{"line":1}"#));
+
+        // Обычный builder продолжает его без перенумерации
+        let mut builder = DictionaryBuilder::load_or_new(dir.path()).unwrap();
+        assert_eq!(builder.intern("FUNCTION"), function);
+    }
+
+    #[test]
+    fn test_corrupted_dictionary_detected() {
+        let dir = TempDir::new().unwrap();
+        let mut builder = DictionaryBuilder::new();
+        builder.intern("FUNCTION");
+        builder.write(dir.path(), Compression::None).unwrap();
+
+        let path = dir.path().join(DICTIONARY_FILE);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let pos = bytes.windows(8).position(|w| w == b"FUNCTION").unwrap();
+        bytes[pos] = b'X';
+        std::fs::write(&path, &bytes).unwrap();
+
+        let err = StringDictionary::open(dir.path()).unwrap().verify().unwrap_err();
+        assert!(err.to_string().contains("checksum"), "{}", err);
+    }
+
+    #[test]
+    fn test_compressed_chunks_and_torn_tail() {
+        let dir = TempDir::new().unwrap();
+        let metadata = r#"{"line":1,"column":2,"async":false}"#.repeat(40);
+
+        let mut builder = DictionaryBuilder::new();
+        let plain = builder.intern("FUNCTION");
+        builder.write(dir.path(), Compression::None).unwrap();
+        let compressed = builder.intern(&metadata);
+        builder.write(dir.path(), Compression::Lz4).unwrap();
+
+        let path = dir.path().join(DICTIONARY_FILE);
+        let size = std::fs::metadata(&path).unwrap().len() as usize;
+        assert!(size < metadata.len() / 2, "{} bytes", size);
+
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        dict.verify().unwrap();
+        assert_eq!(dict.get(plain), Some("FUNCTION"));
+        assert_eq!(dict.get(compressed), Some(metadata.as_str()));
+        assert_eq!(dict.find(&metadata), Some(compressed));
+        drop(dict);
+
+        // Прерванная запись оставила пол-чанка: он игнорируется и обрезается
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0, 200, 0, 0, 0, 0, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        assert_eq!(dict.len(), 3);
+
+        let mut builder = DictionaryBuilder::load_or_new(dir.path()).unwrap();
+        let calls = builder.intern("CALLS");
+        builder.write(dir.path(), Compression::None).unwrap();
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        dict.verify().unwrap();
+        assert_eq!((dict.chunk_count(), dict.get(calls)), (3, Some("CALLS")));
+    }
+
+    #[test]
+    fn test_hard_linked_dictionary_is_not_modified() {
+        let dir = TempDir::new().unwrap();
+        let snapshot = TempDir::new().unwrap();
+
+        let mut builder = DictionaryBuilder::new();
+        builder.intern("FUNCTION");
+        builder.write(dir.path(), Compression::None).unwrap();
+        std::fs::hard_link(dir.path().join(DICTIONARY_FILE), snapshot.path().join(DICTIONARY_FILE)).unwrap();
+        let before = std::fs::read(snapshot.path().join(DICTIONARY_FILE)).unwrap();
+
+        let mut builder = DictionaryBuilder::load_or_new(dir.path()).unwrap();
+        let calls = builder.intern("CALLS");
+        builder.write(dir.path(), Compression::None).unwrap();
+
+        assert_eq!(std::fs::read(snapshot.path().join(DICTIONARY_FILE)).unwrap(), before);
+        assert_eq!(StringDictionary::open(dir.path()).unwrap().get(calls), Some("CALLS"));
+    }
+
+    #[test]
+    fn test_legacy_dictionary_is_rewritten_on_append() {
+        let dir = TempDir::new().unwrap();
+        let mut table = StringTable::new();
+        table.intern_id("");
+        let function = table.intern_id("FUNCTION");
+        let mut bytes = DICTIONARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&LEGACY_DICTIONARY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&7u64.to_le_bytes());
+        table.write_to(&mut bytes).unwrap();
+        std::fs::write(dir.path().join(DICTIONARY_FILE), &bytes).unwrap();
+
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        assert_eq!((dict.id(), dict.get(function)), (7, Some("FUNCTION")));
+
+        let mut builder = DictionaryBuilder::load_or_new(dir.path()).unwrap();
+        assert_eq!(builder.intern("FUNCTION"), function);
+        let calls = builder.intern("CALLS");
+        builder.write(dir.path(), Compression::None).unwrap();
+
+        let dict = StringDictionary::open(dir.path()).unwrap();
+        assert!(!dict.legacy);
+        assert_eq!((dict.id(), dict.get(function), dict.get(calls)), (7, Some("FUNCTION"), Some("CALLS")));
+    }
+}
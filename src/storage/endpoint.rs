@@ -0,0 +1,148 @@
+//! Endpoint classification rules for PathValidator
+//!
+//! A node is an endpoint if it matches at least one rule. Rules are stored
+//! per database in `metadata.json`, so every stack can register its own
+//! endpoint types (e.g. `queue:publish`, `grpc:call`).
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::storage::NodeRecord;
+
+/// Single endpoint rule: type pattern + optional exported/metadata conditions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndpointRule {
+    /// Node type pattern. Supports wildcard: "http:*" for all http types, "*" for any type
+    pub node_type: String,
+
+    /// If set, node `exported` flag must be equal to this value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported: Option<bool>,
+
+    /// Metadata conditions: every key must be present in node metadata JSON with equal value
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl EndpointRule {
+    pub fn new(node_type: impl Into<String>) -> Self {
+        Self {
+            node_type: node_type.into(),
+            exported: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    pub fn exported(mut self, e: bool) -> Self {
+        self.exported = Some(e);
+        self
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+
+    /// Check if node type matches the pattern (with wildcard support)
+    pub fn matches_type(&self, node_type: &str) -> bool {
+        if self.node_type.ends_with('*') {
+            node_type.starts_with(self.node_type.trim_end_matches('*'))
+        } else {
+            node_type == self.node_type
+        }
+    }
+
+    /// Check if node satisfies all conditions of the rule
+    pub fn matches(&self, node: &NodeRecord) -> bool {
+        self.matches_parts(node.node_type.as_deref(), node.exported, node.metadata.as_deref())
+    }
+
+    /// Same as `matches`, but on separate columns (no `NodeRecord` needed)
+    pub fn matches_parts(&self, node_type: Option<&str>, exported: bool, metadata: Option<&str>) -> bool {
+        if !self.matches_type(node_type.unwrap_or("UNKNOWN")) {
+            return false;
+        }
+
+        if self.exported.is_some_and(|e| exported != e) {
+            return false;
+        }
+
+        if self.metadata.is_empty() {
+            return true;
+        }
+
+        let Some(metadata) = metadata
+            .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        else {
+            return false;
+        };
+
+        self.metadata.iter().all(|(key, expected)| metadata.get(key) == Some(expected))
+    }
+}
+
+/// Default rules (previously hard-coded in `GraphEngine::is_endpoint`)
+pub fn default_endpoint_rules() -> Vec<EndpointRule> {
+    vec![
+        EndpointRule::new("db:query"),
+        EndpointRule::new("http:request"),
+        EndpointRule::new("http:endpoint"),
+        EndpointRule::new("EXTERNAL"),
+        EndpointRule::new("fs:operation"),
+        EndpointRule::new("SIDE_EFFECT"),
+        EndpointRule::new("FUNCTION").exported(true),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_node(node_type: &str, exported: bool, metadata: Option<&str>) -> NodeRecord {
+        NodeRecord {
+            id: 1,
+            node_type: Some(node_type.to_string()),
+            file_id: 0,
+            name_offset: 0,
+            version: "main".to_string(),
+            exported,
+            replaces: None,
+            deleted: false,
+            name: None,
+            file: None,
+            metadata: metadata.map(|m| m.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_exact_and_wildcard_type() {
+        let exact = EndpointRule::new("queue:publish");
+        assert!(exact.matches(&make_node("queue:publish", false, None)));
+        assert!(!exact.matches(&make_node("queue:consume", false, None)));
+
+        let wildcard = EndpointRule::new("grpc:*");
+        assert!(wildcard.matches(&make_node("grpc:call", false, None)));
+        assert!(!wildcard.matches(&make_node("http:request", false, None)));
+    }
+
+    #[test]
+    fn test_exported_condition() {
+        let rule = EndpointRule::new("FUNCTION").exported(true);
+        assert!(rule.matches(&make_node("FUNCTION", true, None)));
+        assert!(!rule.matches(&make_node("FUNCTION", false, None)));
+    }
+
+    #[test]
+    fn test_metadata_condition() {
+        let rule = EndpointRule::new("CALL").metadata("object", serde_json::json!("redis"));
+        assert!(rule.matches(&make_node("CALL", false, Some(r#"{"object":"redis","line":3}"#))));
+        assert!(!rule.matches(&make_node("CALL", false, Some(r#"{"object":"console"}"#))));
+        assert!(!rule.matches(&make_node("CALL", false, None)));
+        assert!(!rule.matches(&make_node("CALL", false, Some("not json"))));
+    }
+
+    #[test]
+    fn test_rule_serde_defaults() {
+        let rule: EndpointRule = serde_json::from_str(r#"{"node_type":"cache:write"}"#).unwrap();
+        assert_eq!(rule, EndpointRule::new("cache:write"));
+    }
+}
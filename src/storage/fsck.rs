@@ -0,0 +1,534 @@
+//! Офлайн-проверка и починка директории базы (rfdb-fsck)
+//!
+//! `check` ничего не пишет. `repair` берёт writer lock, переписывает strings.bin /
+//! nodes.bin / edges.bin без битых строк и оставляет оригиналы как `*.bak`.
+//! Проблемы, не привязанные к строке (нечитаемый файл, checksum колонки, чужой
+//! strings.bin, metadata.json), остаются пользователю: `repair` отказывается,
+//! а не выдаёт испорченные данные за целые.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use crate::error::{GraphError, Result};
+use crate::storage::segment::{NodesSegment, EdgesSegment};
+use crate::storage::{DbLock, GraphMetadata, SegmentWriter, StringDictionary};
+use crate::storage::dictionary::{DictionaryBuilder, DICTIONARY_FILE};
+use crate::storage::writer::{COMPACT_TMP_DIR, InterruptedCompaction, compacted_file_path, interrupted_compaction, mark_compaction_committed, recover_compaction};
+
+/// Тип найденной проблемы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// Файл не открывается (header, размеры колонок, string table)
+    Unreadable,
+    /// Сегмент записан под другой strings.bin (dictionary_id в header не совпадает)
+    DictionaryMismatch,
+    /// Checksum колонки или string table не сходится
+    Checksum,
+    /// Offset указывает мимо string table
+    BadStringOffset,
+    /// Нода с тем же ID уже встречалась в сегменте
+    DuplicateId,
+    /// Metadata не является валидным JSON
+    InvalidMetadata,
+    /// src или dst ребра отсутствует среди нод
+    DanglingEdge,
+    /// Compact прерван: repair доводит подмену файлов до конца или откатывает её
+    InterruptedCompaction,
+}
+
+/// Одна проблема: файл, строка (если относится к строке) и описание
+#[derive(Debug, Clone)]
+pub struct FsckIssue {
+    pub file: &'static str,
+    pub row: Option<usize>,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+/// Результат проверки
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub node_rows: usize,
+    pub edge_rows: usize,
+    pub issues: Vec<FsckIssue>,
+
+    // Строки, которые repair выбросит
+    bad_nodes: BTreeSet<usize>,
+    bad_edges: BTreeSet<usize>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Можно ли починить перезаписью (см. `blocking_issue`)
+    pub fn is_repairable(&self) -> bool {
+        self.blocking_issue().is_none()
+    }
+
+    /// Проблема, которую перезапись не исправит
+    ///
+    /// Нечитаемый файл или чужой strings.bin, checksum, не привязанный к строке (перезапись выдала бы
+    /// битые данные за целые со свежим checksum), и битый metadata.json.
+    pub fn blocking_issue(&self) -> Option<&FsckIssue> {
+        self.issues.iter().find(|i| match i.kind {
+            IssueKind::Unreadable | IssueKind::DictionaryMismatch => true,
+            IssueKind::Checksum => i.row.is_none(),
+            IssueKind::InvalidMetadata => i.file == "metadata.json",
+            _ => false,
+        })
+    }
+
+    /// Сколько строк (нод, рёбер) будет выброшено при repair
+    pub fn rows_to_drop(&self) -> (usize, usize) {
+        (self.bad_nodes.len(), self.bad_edges.len())
+    }
+
+    fn add(&mut self, file: &'static str, row: Option<usize>, kind: IssueKind, message: String) {
+        self.issues.push(FsckIssue { file, row, kind, message });
+    }
+}
+
+/// Итог repair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepairStats {
+    pub nodes_kept: usize,
+    pub nodes_dropped: usize,
+    pub edges_kept: usize,
+    pub edges_dropped: usize,
+}
+
+/// Открытые сегменты (None если файла нет или он не читается)
+struct Segments {
+    nodes: Option<NodesSegment>,
+    edges: Option<EdgesSegment>,
+}
+
+fn open_segments(db_path: &Path, strings: Option<Arc<StringDictionary>>, report: &mut FsckReport) -> Segments {
+    let nodes = open_segment(db_path, "nodes.bin", |p| NodesSegment::open_with_dictionary(p, strings.clone()), report);
+    let edges = open_segment(db_path, "edges.bin", |p| EdgesSegment::open_with_dictionary(p, strings.clone()), report);
+    Segments { nodes, edges }
+}
+
+fn open_segment<T>(
+    db_path: &Path,
+    file: &'static str,
+    open: impl Fn(&Path) -> Result<T>,
+    report: &mut FsckReport,
+) -> Option<T> {
+    let path = compacted_file_path(db_path, file);
+    if !path.exists() {
+        return None;
+    }
+    match open(&path) {
+        Ok(segment) => Some(segment),
+        Err(e) => {
+            let kind = match e {
+                GraphError::DictionaryMismatch { .. } => IssueKind::DictionaryMismatch,
+                _ => IssueKind::Unreadable,
+            };
+            report.add(file, None, kind, e.to_string());
+            None
+        }
+    }
+}
+
+fn is_valid_json(s: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(s).is_ok()
+}
+
+/// Проверить базу без изменений
+pub fn check(db_path: &Path) -> Result<FsckReport> {
+    check_segments(db_path).map(|(report, _)| report)
+}
+
+fn check_segments(db_path: &Path) -> Result<(FsckReport, Segments)> {
+    if !db_path.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Database not found: {:?}", db_path),
+        ).into());
+    }
+
+    let mut report = FsckReport::default();
+
+    // После закоммиченного compact проверяются уже новые файлы (так их откроет engine)
+    match interrupted_compaction(db_path) {
+        Some(InterruptedCompaction::Incomplete) => report.add(COMPACT_TMP_DIR, None, IssueKind::InterruptedCompaction,
+            "unfinished compaction output, database files are intact".to_string()),
+        Some(InterruptedCompaction::Committed) => report.add(COMPACT_TMP_DIR, None, IssueKind::InterruptedCompaction,
+            "compaction was interrupted while swapping files".to_string()),
+        None => {}
+    }
+
+    let strings_path = compacted_file_path(db_path, DICTIONARY_FILE);
+    let strings = match StringDictionary::open_if_exists(strings_path.parent().unwrap_or(db_path)) {
+        Ok(Some(strings)) => {
+            if let Err(e) = strings.verify() {
+                report.add(DICTIONARY_FILE, None, IssueKind::Checksum, e.to_string());
+            }
+            Some(Arc::new(strings))
+        }
+        Ok(None) => None,
+        Err(e) => {
+            report.add(DICTIONARY_FILE, None, IssueKind::Unreadable, e.to_string());
+            None
+        }
+    };
+    let segments = open_segments(db_path, strings, &mut report);
+
+    let meta_path = db_path.join("metadata.json");
+    if meta_path.exists() {
+        if let Err(e) = serde_json::from_slice::<GraphMetadata>(&fs::read(&meta_path)?) {
+            report.add("metadata.json", None, IssueKind::InvalidMetadata, e.to_string());
+        }
+    }
+
+    // Живые ID нод (для проверки висячих рёбер)
+    let mut node_ids: HashSet<u128> = HashSet::new();
+
+    if let Some(ref nodes) = segments.nodes {
+        report.node_rows = nodes.node_count();
+
+        if let Err(e) = nodes.verify_checksums() {
+            report.add("nodes.bin", None, IssueKind::Checksum, e.to_string());
+        }
+        for (idx, problem) in nodes.invalid_rows() {
+            report.add("nodes.bin", Some(idx), IssueKind::BadStringOffset, problem);
+            report.bad_nodes.insert(idx);
+        }
+
+        // Дубликаты: flush дописывает delta после segment, поэтому побеждает последняя строка
+        let mut last_row: HashMap<u128, usize> = HashMap::new();
+        for idx in 0..nodes.node_count() {
+            if nodes.is_deleted(idx) || report.bad_nodes.contains(&idx) {
+                continue;
+            }
+            let Some(id) = nodes.get_id(idx) else { continue };
+
+            if let Some(metadata) = nodes.get_metadata(idx) {
+                if !is_valid_json(metadata) {
+                    report.add("nodes.bin", Some(idx), IssueKind::InvalidMetadata,
+                        format!("node {}: metadata is not valid JSON", id));
+                    report.bad_nodes.insert(idx);
+                    continue;
+                }
+            }
+
+            if let Some(prev) = last_row.insert(id, idx) {
+                report.add("nodes.bin", Some(prev), IssueKind::DuplicateId,
+                    format!("node {} repeated at row {}", id, idx));
+                report.bad_nodes.insert(prev);
+            }
+        }
+        node_ids.extend(last_row.into_keys());
+    }
+
+    if let Some(ref edges) = segments.edges {
+        report.edge_rows = edges.edge_count();
+
+        if let Err(e) = edges.verify_checksums() {
+            report.add("edges.bin", None, IssueKind::Checksum, e.to_string());
+        }
+        for (idx, problem) in edges.invalid_rows() {
+            report.add("edges.bin", Some(idx), IssueKind::BadStringOffset, problem);
+            report.bad_edges.insert(idx);
+        }
+
+        // Без читаемого nodes.bin висячие рёбра не проверить
+        let nodes_readable = segments.nodes.is_some() || !compacted_file_path(db_path, "nodes.bin").exists();
+
+        for idx in 0..edges.edge_count() {
+            if edges.is_deleted(idx) || report.bad_edges.contains(&idx) {
+                continue;
+            }
+            let (Some(src), Some(dst)) = (edges.get_src(idx), edges.get_dst(idx)) else { continue };
+
+            if let Some(metadata) = edges.get_metadata(idx) {
+                if !is_valid_json(metadata) {
+                    report.add("edges.bin", Some(idx), IssueKind::InvalidMetadata,
+                        format!("edge {} -> {}: metadata is not valid JSON", src, dst));
+                    report.bad_edges.insert(idx);
+                    continue;
+                }
+            }
+
+            if nodes_readable && !(node_ids.contains(&src) && node_ids.contains(&dst)) {
+                let missing = if node_ids.contains(&src) { dst } else { src };
+                report.add("edges.bin", Some(idx), IssueKind::DanglingEdge,
+                    format!("edge {} -> {}: node {} does not exist", src, dst, missing));
+                report.bad_edges.insert(idx);
+            }
+        }
+    }
+
+    Ok((report, segments))
+}
+
+/// Перезаписать сегменты без битых строк
+///
+/// Удалённые (deleted) строки тоже не переносятся, strings.bin собирается
+/// заново без неиспользуемых строк. Оригиналы сохраняются как `*.bak`.
+/// Прерванный compact сначала доводится до конца (или откатывается); файлы
+/// подменяются так же, как при compact, metadata.json пишется последним.
+pub fn repair(db_path: &Path) -> Result<RepairStats> {
+    let _lock = DbLock::acquire(db_path)?;
+    recover_compaction(db_path)?;
+    let (stats, metadata) = stage_repair(db_path)?;
+
+    // Маркер уже записан: подмену доводит recover_compaction
+    recover_compaction(db_path)?;
+    SegmentWriter::new(db_path).write_metadata(&metadata)?;
+
+    tracing::info!(?db_path, ?stats, "Repair complete");
+    Ok(stats)
+}
+
+/// Записать исправленные файлы в COMPACT_TMP_DIR, сделать `*.bak` и маркер подмены
+///
+/// Возвращает статистику и metadata.json, который пишется после подмены.
+fn stage_repair(db_path: &Path) -> Result<(RepairStats, GraphMetadata)> {
+    let (report, segments) = check_segments(db_path)?;
+
+    if let Some(issue) = report.blocking_issue() {
+        return Err(GraphError::InvalidFormat(format!(
+            "{} cannot be repaired: {}", issue.file, issue.message
+        )));
+    }
+
+    let nodes: Vec<_> = segments.nodes.as_ref().map(|seg| {
+        (0..seg.node_count())
+            .filter(|idx| !report.bad_nodes.contains(idx))
+            .filter_map(|idx| seg.node_record(idx))
+            .filter(|n| !n.deleted)
+            .collect()
+    }).unwrap_or_default();
+
+    let edges: Vec<_> = segments.edges.as_ref().map(|seg| {
+        (0..seg.edge_count())
+            .filter(|idx| !report.bad_edges.contains(idx))
+            .filter_map(|idx| seg.edge_record(idx))
+            .filter(|e| !e.deleted)
+            .collect()
+    }).unwrap_or_default();
+
+    let stats = RepairStats {
+        nodes_kept: nodes.len(),
+        nodes_dropped: report.node_rows - nodes.len(),
+        edges_kept: edges.len(),
+        edges_dropped: report.edge_rows - edges.len(),
+    };
+
+    // Битый metadata.json отсекается blocking_issue, отсутствующий - default
+    let meta_path = db_path.join("metadata.json");
+    let metadata = if meta_path.exists() {
+        serde_json::from_slice::<GraphMetadata>(&fs::read(&meta_path)?)?
+    } else {
+        GraphMetadata::default()
+    };
+    drop(segments);
+
+    let tmp_dir = db_path.join(COMPACT_TMP_DIR);
+    let _ = fs::remove_dir_all(&tmp_dir);
+    fs::create_dir_all(&tmp_dir)?;
+    SegmentWriter::new(&tmp_dir)
+        .with_compression(metadata.compression)
+        .write_segments(&nodes, &edges, &mut DictionaryBuilder::new())?;
+
+    // Оригиналы остаются на месте до подмены, `*.bak` - жёсткие ссылки на них
+    for file in [DICTIONARY_FILE, "nodes.bin", "edges.bin"] {
+        let path = db_path.join(file);
+        if path.exists() {
+            let backup = db_path.join(format!("{}.bak", file));
+            let _ = fs::remove_file(&backup);
+            if fs::hard_link(&path, &backup).is_err() {
+                fs::copy(&path, &backup)?;
+            }
+        }
+    }
+    mark_compaction_committed(db_path)?;
+
+    let metadata = GraphMetadata {
+        node_count: nodes.len(),
+        edge_count: edges.len(),
+        ..metadata
+    };
+    Ok((stats, metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::NodeRecord;
+    use crate::storage::segment::HEADER_SIZE_ON_DISK;
+    use crate::test_support::{self, edge};
+    use tempfile::TempDir;
+
+    fn node(id: u128, metadata: Option<&str>) -> NodeRecord {
+        NodeRecord {
+            file: Some("src/a.js".to_string()),
+            metadata: metadata.map(|m| m.to_string()),
+            ..test_support::node(id, "FUNCTION", &format!("fn{}", id))
+        }
+    }
+
+    #[test]
+    fn test_check_and_repair_broken_rows() {
+        let dir = TempDir::new().unwrap();
+        let writer = SegmentWriter::new(dir.path());
+        writer.write_nodes(&[
+            node(1, None),
+            node(2, Some("{broken")),
+            node(3, Some(r#"{"line":1}"#)),
+            node(3, Some(r#"{"line":2}"#)),
+        ]).unwrap();
+        writer.write_edges(&[edge(1, 3, "CALLS"), edge(1, 99, "CALLS")]).unwrap();
+
+        let report = check(dir.path()).unwrap();
+        let kinds: Vec<IssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert!(kinds.contains(&IssueKind::InvalidMetadata));
+        assert!(kinds.contains(&IssueKind::DuplicateId));
+        assert!(kinds.contains(&IssueKind::DanglingEdge));
+        assert!(report.is_repairable());
+
+        // Нода 2 ушла вместе с битой metadata, ребро 1 -> 99 висит
+        let stats = repair(dir.path()).unwrap();
+        assert_eq!(stats, RepairStats { nodes_kept: 2, nodes_dropped: 2, edges_kept: 1, edges_dropped: 1 });
+        assert!(dir.path().join("nodes.bin.bak").exists());
+
+        assert!(check(dir.path()).unwrap().is_clean());
+        let nodes = NodesSegment::open(&dir.path().join("nodes.bin")).unwrap();
+        let kept = (0..nodes.node_count()).find(|&i| nodes.get_id(i) == Some(3)).unwrap();
+        assert_eq!(nodes.get_metadata(kept), Some(r#"{"line":2}"#));
+    }
+
+    #[test]
+    fn test_repair_interrupted_after_commit_marker_is_finished_on_open() {
+        use crate::graph::{GraphEngine, GraphStore};
+
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("g.rfdb");
+        fs::create_dir_all(&db_path).unwrap();
+        let writer = SegmentWriter::new(&db_path);
+        writer.write_nodes(&[node(1, None), node(2, Some("{broken")), node(3, None)]).unwrap();
+        writer.write_edges(&[edge(1, 3, "CALLS"), edge(1, 2, "CALLS")]).unwrap();
+        writer.write_metadata(&GraphMetadata::default()).unwrap();
+
+        // Падение сразу после маркера: файлы базы ещё старые, новые лежат в COMPACT_TMP_DIR
+        let (stats, _) = stage_repair(&db_path).unwrap();
+        assert_eq!((stats.nodes_kept, stats.edges_kept), (2, 1));
+        assert_eq!(interrupted_compaction(&db_path), Some(InterruptedCompaction::Committed));
+        assert!(db_path.join("nodes.bin.bak").exists());
+
+        let engine = GraphEngine::open(&db_path).unwrap();
+        assert!(!db_path.join(COMPACT_TMP_DIR).exists());
+        assert_eq!(engine.node_count(), 2);
+        assert!(engine.get_node(2).is_none());
+        assert_eq!(engine.neighbors(1, &["CALLS"]), vec![3]);
+        drop(engine);
+        assert!(check(&db_path).unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_unreadable_segment_is_not_repairable() {
+        let dir = TempDir::new().unwrap();
+        SegmentWriter::new(dir.path()).write_nodes(&[node(1, None)]).unwrap();
+
+        let path = dir.path().join("nodes.bin");
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..40]).unwrap();
+
+        let report = check(dir.path()).unwrap();
+        assert_eq!(report.issues[0].kind, IssueKind::Unreadable);
+        assert!(!report.is_repairable());
+        assert!(matches!(repair(dir.path()), Err(GraphError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_foreign_dictionary_is_reported() {
+        let dir = TempDir::new().unwrap();
+        let writer = SegmentWriter::new(dir.path());
+        writer.write_nodes(&[node(1, None), node(2, None)]).unwrap();
+        writer.write_edges(&[edge(1, 2, "CALLS")]).unwrap();
+
+        // strings.bin от другой базы: строки целы, но id словаря другой
+        let other = TempDir::new().unwrap();
+        SegmentWriter::new(other.path()).write_nodes(&[node(1, None)]).unwrap();
+        fs::copy(other.path().join(DICTIONARY_FILE), dir.path().join(DICTIONARY_FILE)).unwrap();
+
+        let report = check(dir.path()).unwrap();
+        let issues: Vec<(&str, IssueKind)> = report.issues.iter().map(|i| (i.file, i.kind)).collect();
+        assert_eq!(issues, vec![("nodes.bin", IssueKind::DictionaryMismatch), ("edges.bin", IssueKind::DictionaryMismatch)]);
+        assert!(!report.is_repairable());
+        assert!(repair(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_column_checksum_is_not_repaired() {
+        let dir = TempDir::new().unwrap();
+        let writer = SegmentWriter::new(dir.path());
+        writer.write_nodes(&[node(1, None), node(2, None)]).unwrap();
+        writer.write_edges(&[edge(1, 2, "CALLS")]).unwrap();
+
+        // Портим колонку edge_type_ids: строка цела, сходится только checksum
+        let path = dir.path().join("edges.bin");
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_SIZE_ON_DISK + 16 + 16] ^= 0x01;
+        fs::write(&path, &bytes).unwrap();
+
+        let report = check(dir.path()).unwrap();
+        let issue = report.blocking_issue().unwrap();
+        assert_eq!((issue.file, issue.kind, issue.row), ("edges.bin", IssueKind::Checksum, None));
+        assert!(!report.is_repairable());
+
+        // Ничего не перезаписано: checksum по-прежнему не сходится
+        assert!(matches!(repair(dir.path()), Err(GraphError::InvalidFormat(_))));
+        assert!(!dir.path().join("edges.bin.bak").exists());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_interrupted_compaction_is_repaired() {
+        use crate::storage::writer::COMPACT_COMMIT_MARKER;
+
+        let dir = TempDir::new().unwrap();
+        let writer = SegmentWriter::new(dir.path());
+        writer.write_nodes(&[node(1, None), node(2, None)]).unwrap();
+        writer.write_edges(&[edge(1, 2, "CALLS")]).unwrap();
+
+        // Подмена прервана после strings.bin: старые сегменты ссылаются на чужой словарь
+        let tmp_dir = dir.path().join(COMPACT_TMP_DIR);
+        fs::create_dir_all(&tmp_dir).unwrap();
+        SegmentWriter::new(&tmp_dir)
+            .write_segments(&[node(1, None), node(2, None)], &[edge(1, 2, "CALLS")], &mut DictionaryBuilder::new())
+            .unwrap();
+        fs::write(tmp_dir.join(COMPACT_COMMIT_MARKER), b"").unwrap();
+        fs::rename(tmp_dir.join(DICTIONARY_FILE), dir.path().join(DICTIONARY_FILE)).unwrap();
+
+        let report = check(dir.path()).unwrap();
+        let kinds: Vec<IssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, vec![IssueKind::InterruptedCompaction]);
+        assert!(report.is_repairable());
+
+        let stats = repair(dir.path()).unwrap();
+        assert_eq!((stats.nodes_kept, stats.edges_kept), (2, 1));
+        assert!(!tmp_dir.exists());
+        assert!(check(dir.path()).unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_invalid_metadata_json_is_not_repaired() {
+        let dir = TempDir::new().unwrap();
+        let writer = SegmentWriter::new(dir.path());
+        writer.write_nodes(&[node(1, None)]).unwrap();
+        writer.write_metadata(&GraphMetadata::default()).unwrap();
+        fs::write(dir.path().join("metadata.json"), "{\"node_count\":").unwrap();
+
+        let report = check(dir.path()).unwrap();
+        assert_eq!(report.blocking_issue().map(|i| i.kind), Some(IssueKind::InvalidMetadata));
+        assert!(repair(dir.path()).is_err());
+        assert!(!dir.path().join("nodes.bin.bak").exists());
+    }
+}
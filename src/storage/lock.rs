@@ -0,0 +1,90 @@
+//! Inter-process lock for database directory
+//!
+//! Writer holds an exclusive advisory lock (flock) on `<db>/rfdb.lock`
+//! for its whole lifetime. Read-only engines don't take the lock.
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use fs2::FileExt;
+use crate::error::{GraphError, Result};
+
+/// Lock file name inside database directory
+pub const LOCK_FILE: &str = "rfdb.lock";
+
+/// Exclusive writer lock (released on drop)
+#[derive(Debug)]
+pub struct DbLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl DbLock {
+    /// Acquire exclusive lock for database directory (non-blocking)
+    ///
+    /// Returns `GraphError::Locked` if another writer holds the lock.
+    pub fn acquire(db_path: &Path) -> Result<Self> {
+        let path = db_path.join(LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.kind() == ErrorKind::WouldBlock || e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                let mut owner = String::new();
+                let _ = file.read_to_string(&mut owner);
+                return Err(GraphError::Locked {
+                    path: db_path.to_path_buf(),
+                    owner_pid: owner.trim().parse().ok(),
+                });
+            }
+            return Err(e.into());
+        }
+
+        // PID владельца - только для диагностики
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", std::process::id())?;
+        file.flush()?;
+
+        tracing::debug!(?path, "Acquired database lock");
+        Ok(Self { file, path })
+    }
+
+    /// Path to lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_second_lock_fails_until_released() {
+        let dir = TempDir::new().unwrap();
+
+        let lock = DbLock::acquire(dir.path()).unwrap();
+        match DbLock::acquire(dir.path()) {
+            Err(GraphError::Locked { owner_pid, .. }) => {
+                assert_eq!(owner_pid, Some(std::process::id()));
+            }
+            other => panic!("expected Locked, got {:?}", other),
+        }
+
+        drop(lock);
+        assert!(DbLock::acquire(dir.path()).is_ok());
+    }
+}
@@ -3,11 +3,52 @@
 pub mod segment;
 pub mod delta;
 pub mod string_table;
+pub mod dictionary;
 pub mod writer;
+pub mod lock;
+pub mod fsck;
+pub mod compression;
+pub mod bulk;
+pub mod endpoint;
 
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 
 pub use writer::{SegmentWriter, GraphMetadata};
+pub use lock::DbLock;
+pub use compression::Compression;
+pub use dictionary::StringDictionary;
+pub use bulk::{BulkLoader, BulkLoadStats};
+pub use endpoint::EndpointRule;
+
+/// Normalize database path ensuring .rfdb extension
+///
+/// Examples:
+/// - `/path/to/db` -> `/path/to/db.rfdb`
+/// - `/path/to/db.db` -> `/path/to/db.rfdb`
+/// - `/path/to/db.rfdb` -> `/path/to/db.rfdb` (unchanged)
+pub fn normalize_db_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+
+    // If path already has .rfdb extension, return as is
+    if path.extension().and_then(|s| s.to_str()) == Some("rfdb") {
+        return path.to_path_buf();
+    }
+
+    // If there's another extension, replace with .rfdb
+    if path.extension().is_some() {
+        return path.with_extension("rfdb");
+    }
+
+    // If no extension, add .rfdb
+    let mut new_path = path.to_path_buf();
+    let new_filename = format!(
+        "{}.rfdb",
+        path.file_name().and_then(|s| s.to_str()).unwrap_or("db")
+    );
+    new_path.set_file_name(new_filename);
+    new_path
+}
 
 /// Node record in columnar format
 #[derive(Debug, Clone, Serialize, Deserialize)]
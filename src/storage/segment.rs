@@ -1,16 +1,343 @@
 //! Immutable segment files (nodes.bin, edges.bin)
 
+use std::collections::HashMap;
 use std::path::Path;
 use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
+use std::sync::Arc;
 use memmap2::Mmap;
 use crate::error::{GraphError, Result};
-use crate::storage::string_table::StringTable;
+use crate::storage::compression::read_block;
+use crate::storage::dictionary::StringDictionary;
+use crate::storage::string_table::StringTableView;
+use crate::storage::{NodeRecord, EdgeRecord};
 
 /// Магическое число для валидации формата
 pub const MAGIC: [u8; 4] = *b"SGRF"; // Semantic Graph Format
 
-/// Версия формата
-pub const FORMAT_VERSION: u16 = 1;
+/// Магическое число сжатого сегмента (секции в блоках, см. `compression`)
+pub const MAGIC_COMPRESSED: [u8; 4] = *b"SGRZ";
+
+/// Текущая версия формата (пишется writer'ом)
+///
+/// История:
+/// - 1: колонки + встроенная StringTable, checksum footer опционален
+/// - 2: checksum footer обязателен
+/// - 3: опциональное сжатие секций (magic SGRZ)
+/// - 4: строки в StringTable с префиксом длины (O(1) чтение)
+/// - 5: общий словарь strings.bin вместо встроенных StringTable
+/// - 6: колонки replaces (ноды) и version (рёбра)
+pub const FORMAT_VERSION: u16 = 6;
+
+/// Первая версия с length-prefixed строками
+pub const LENGTH_PREFIXED_STRINGS_VERSION: u16 = 4;
+
+/// Первая версия с общим словарём strings.bin
+pub const SHARED_DICTIONARY_VERSION: u16 = 5;
+
+/// Первая версия с колонками replaces / edge version
+pub const RECORD_VERSIONS_VERSION: u16 = 6;
+
+/// Самая старая версия, которую ещё умеем читать
+pub const MIN_FORMAT_VERSION: u16 = 1;
+
+/// Маркер checksum footer в конце файла
+pub const CHECKSUM_MAGIC: [u8; 4] = *b"SGCK";
+
+/// Размер одной checksum (blake3)
+pub const CHECKSUM_SIZE: usize = 32;
+
+/// Checksum секции (колонки или string table)
+pub type Checksum = [u8; CHECKSUM_SIZE];
+
+/// Посчитать checksum секции
+pub fn section_checksum(bytes: &[u8]) -> Checksum {
+    *blake3::hash(bytes).as_bytes()
+}
+
+/// Записать checksum footer: [checksums...][u32 count][SGCK]
+///
+/// Footer опционален: сегменты без него (старые файлы) читаются,
+/// но `verify` проверяет только границы.
+pub fn write_checksum_footer<W: Write>(writer: &mut W, checksums: &[Checksum]) -> Result<()> {
+    for checksum in checksums {
+        writer.write_all(checksum)?;
+    }
+    writer.write_all(&(checksums.len() as u32).to_le_bytes())?;
+    writer.write_all(&CHECKSUM_MAGIC)?;
+    Ok(())
+}
+
+/// Прочитать checksum footer. Возвращает (checksums, начало footer)
+pub(crate) fn read_checksum_footer(data: &[u8]) -> Result<(Option<Vec<Checksum>>, usize)> {
+    let len = data.len();
+    if len < HEADER_SIZE_ON_DISK + 8 || data[len - 4..] != CHECKSUM_MAGIC {
+        return Ok((None, len));
+    }
+
+    let count = u32::from_le_bytes(data[len - 8..len - 4].try_into().unwrap()) as usize;
+    let footer_start = count.checked_mul(CHECKSUM_SIZE)
+        .and_then(|size| (len - 8).checked_sub(size))
+        .filter(|&start| start >= HEADER_SIZE_ON_DISK)
+        .ok_or_else(|| GraphError::InvalidFormat(
+            format!("Checksum footer too large: {} entries in {} bytes", count, len)
+        ))?;
+
+    let checksums = data[footer_start..len - 8]
+        .chunks_exact(CHECKSUM_SIZE)
+        .map(|c| c.try_into().unwrap())
+        .collect();
+    Ok((Some(checksums), footer_start))
+}
+
+/// Разметить колонки подряд после header, проверяя что они помещаются в `data_end`
+///
+/// Возвращает offset начала каждой колонки и конец последней.
+fn layout_columns(row_count: u64, widths: &[usize], data_end: usize) -> Result<(Vec<usize>, usize)> {
+    let rows = usize::try_from(row_count)
+        .map_err(|_| GraphError::InvalidFormat(format!("Row count too large: {}", row_count)))?;
+
+    let mut offsets = Vec::with_capacity(widths.len());
+    let mut offset = HEADER_SIZE_ON_DISK;
+    for &width in widths {
+        offsets.push(offset);
+        offset = rows.checked_mul(width)
+            .and_then(|size| offset.checked_add(size))
+            .filter(|&end| end <= data_end)
+            .ok_or_else(|| GraphError::InvalidFormat(
+                format!("Columns for {} rows exceed file size {}", rows, data_end)
+            ))?;
+    }
+    Ok((offsets, offset))
+}
+
+/// Источник строк сегмента
+enum SegmentStrings {
+    /// Нет строк (пустая или отсутствующая StringTable)
+    None,
+    /// Встроенная StringTable (до v5), ключ - byte offset
+    Embedded(StringTableView),
+    /// Общий strings.bin (v5+), ключ - id строки
+    Shared(Arc<StringDictionary>),
+}
+
+impl SegmentStrings {
+    fn get<'a>(&'a self, data: &'a [u8], key: u32) -> Option<&'a str> {
+        match self {
+            SegmentStrings::None => None,
+            SegmentStrings::Embedded(view) => view.get(data, key),
+            SegmentStrings::Shared(dict) => dict.get(key),
+        }
+    }
+
+    fn contains(&self, data: &[u8], key: u32) -> bool {
+        match self {
+            SegmentStrings::None => false,
+            SegmentStrings::Embedded(view) => view.contains_offset(data, key),
+            SegmentStrings::Shared(dict) => dict.contains_id(key),
+        }
+    }
+
+    fn find(&self, data: &[u8], s: &str) -> Option<u32> {
+        match self {
+            SegmentStrings::None => None,
+            SegmentStrings::Embedded(view) => view.find_offset(data, s),
+            SegmentStrings::Shared(dict) => dict.find(s),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            SegmentStrings::None => true,
+            SegmentStrings::Embedded(view) => view.is_empty(),
+            SegmentStrings::Shared(dict) => dict.is_empty(),
+        }
+    }
+}
+
+/// Подключить строки сегмента: встроенную StringTable или общий словарь
+///
+/// Для v5 словарь берётся из `dictionary`, иначе открывается strings.bin рядом с файлом.
+fn load_strings(
+    path: &Path,
+    data: &[u8],
+    header: &SegmentHeader,
+    columns_end: usize,
+    data_end: usize,
+    dictionary: Option<Arc<StringDictionary>>,
+) -> Result<SegmentStrings> {
+    let Some(expected_id) = header.dictionary_id() else {
+        return load_string_table(data, header, columns_end, data_end)
+            .map(|view| view.map_or(SegmentStrings::None, SegmentStrings::Embedded));
+    };
+
+    let dict = match dictionary {
+        Some(dict) => dict,
+        None => {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            Arc::new(StringDictionary::open(dir)?)
+        }
+    };
+    if dict.id() != expected_id {
+        return Err(GraphError::DictionaryMismatch { expected: expected_id, found: dict.id() });
+    }
+    Ok(SegmentStrings::Shared(dict))
+}
+
+/// Загрузить встроенную StringTable, проверив её offset
+fn load_string_table(data: &[u8], header: &SegmentHeader, columns_end: usize, data_end: usize) -> Result<Option<StringTableView>> {
+    let Some(st_offset) = header.embedded_string_table_offset() else {
+        return Ok(None);
+    };
+
+    if st_offset < columns_end || st_offset > data_end {
+        return Err(GraphError::InvalidFormat(format!(
+            "String table offset {} outside of [{}, {}]", st_offset, columns_end, data_end
+        )));
+    }
+
+    let length_prefixed = header.version >= LENGTH_PREFIXED_STRINGS_VERSION;
+    StringTableView::parse(data, st_offset..data_end, length_prefixed).map(Some)
+}
+
+/// Секция встроенной StringTable для checksum footer (в v5 её нет)
+fn string_table_section(header: &SegmentHeader, columns_end: usize, data_end: usize) -> Option<(&'static str, Range<usize>)> {
+    if header.dictionary_id().is_some() {
+        return None;
+    }
+    let range = match header.embedded_string_table_offset() {
+        Some(start) => start.max(columns_end)..data_end,
+        None => columns_end..columns_end,
+    };
+    Some(("string_table", range))
+}
+
+/// Сверить checksums секций с footer
+fn verify_sections(data: &[u8], sections: &[(&'static str, Range<usize>)], checksums: Option<&[Checksum]>) -> Result<()> {
+    let Some(checksums) = checksums else {
+        return Ok(());
+    };
+
+    if checksums.len() != sections.len() {
+        return Err(GraphError::InvalidFormat(format!(
+            "Expected {} checksums, found {}", sections.len(), checksums.len()
+        )));
+    }
+
+    for ((name, range), expected) in sections.iter().zip(checksums) {
+        if section_checksum(&data[range.clone()]) != *expected {
+            return Err(GraphError::InvalidFormat(format!("Checksum mismatch in section '{}'", name)));
+        }
+    }
+    Ok(())
+}
+
+/// Данные сегмента: mmap плоского файла или распакованный буфер сжатого
+enum SegmentData {
+    Mapped(Mmap),
+    Decoded(Vec<u8>),
+}
+
+impl std::ops::Deref for SegmentData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            SegmentData::Mapped(mmap) => mmap,
+            SegmentData::Decoded(buf) => buf,
+        }
+    }
+}
+
+/// Открытый файл сегмента до разметки колонок
+struct RawSegment {
+    data: SegmentData,
+    header: SegmentHeader,
+    checksums: Option<Vec<Checksum>>,
+    data_end: usize,
+}
+
+/// Открыть файл сегмента: header, checksum footer и (для SGRZ) распаковка
+///
+/// `widths` - ширины колонок, `rows` - число строк из header. Сжатый файл
+/// распаковывается в тот же плоский layout, поэтому accessors не меняются.
+fn open_raw(path: &Path, widths: fn(u16) -> &'static [usize], rows: impl Fn(&SegmentHeader) -> u64) -> Result<RawSegment> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    // Читаем и валидируем заголовок (используем размер на диске: 30 байт)
+    if mmap.len() < HEADER_SIZE_ON_DISK {
+        return Err(GraphError::InvalidFormat("Файл слишком мал".into()));
+    }
+
+    // Manually parse header from bytes (30 bytes on disk)
+    let mut magic = [0u8; 4];
+    magic.copy_from_slice(&mmap[0..4]);
+
+    let header = SegmentHeader {
+        magic,
+        version: u16::from_le_bytes([mmap[4], mmap[5]]),
+        node_count: u64::from_le_bytes(mmap[6..14].try_into().unwrap()),
+        edge_count: u64::from_le_bytes(mmap[14..22].try_into().unwrap()),
+        string_table_offset: u64::from_le_bytes(mmap[22..30].try_into().unwrap()),
+    };
+    header.validate()?;
+
+    let (checksums, footer_start) = read_checksum_footer(&mmap)?;
+    header.validate_footer(checksums.is_some())?;
+
+    if header.magic != MAGIC_COMPRESSED {
+        return Ok(RawSegment { data: SegmentData::Mapped(mmap), header, checksums, data_end: footer_start });
+    }
+
+    // Колонки + string table, каждая секция - отдельный блок
+    let row_count = rows(&header);
+    let widths = widths(header.version);
+    let mut buf = mmap[..HEADER_SIZE_ON_DISK].to_vec();
+    let mut pos = HEADER_SIZE_ON_DISK;
+    for (i, &width) in widths.iter().enumerate() {
+        let (raw, next) = read_block(&mmap[..footer_start], pos)?;
+        if Some(raw.len() as u64) != row_count.checked_mul(width as u64) {
+            return Err(GraphError::InvalidFormat(format!(
+                "Column #{}: decoded {} bytes for {} rows of width {}", i, raw.len(), row_count, width
+            )));
+        }
+        buf.extend_from_slice(&raw);
+        pos = next;
+    }
+    if header.dictionary_id().is_none() {
+        let (string_table, next) = read_block(&mmap[..footer_start], pos)?;
+        buf.extend_from_slice(&string_table);
+        pos = next;
+    }
+    if pos != footer_start {
+        return Err(GraphError::InvalidFormat(format!(
+            "{} unexpected bytes after compressed sections", footer_start.saturating_sub(pos)
+        )));
+    }
+
+    let data_end = buf.len();
+    Ok(RawSegment { data: SegmentData::Decoded(buf), header, checksums, data_end })
+}
+
+/// Ширины колонок nodes.bin: ids (u128), type_offsets, file_ids, name_offsets,
+/// version_offsets (u32), exported, deleted (u8), metadata_offsets (u32), replaces (u128, v6+)
+pub const NODE_COLUMN_WIDTHS: [usize; 9] = [16, 4, 4, 4, 4, 1, 1, 4, 16];
+
+/// Ширины колонок edges.bin: src, dst (u128), edge_type_offsets, metadata_offsets (u32),
+/// deleted (u8), version_offsets (u32, v6+)
+pub const EDGE_COLUMN_WIDTHS: [usize; 6] = [16, 16, 4, 4, 1, 4];
+
+/// Колонки nodes.bin в файле версии `version`
+pub fn node_column_widths(version: u16) -> &'static [usize] {
+    if version >= RECORD_VERSIONS_VERSION { &NODE_COLUMN_WIDTHS } else { &NODE_COLUMN_WIDTHS[..8] }
+}
+
+/// Колонки edges.bin в файле версии `version`
+pub fn edge_column_widths(version: u16) -> &'static [usize] {
+    if version >= RECORD_VERSIONS_VERSION { &EDGE_COLUMN_WIDTHS } else { &EDGE_COLUMN_WIDTHS[..5] }
+}
 
 /// Заголовок сегмента
 #[repr(C)]
@@ -20,6 +347,7 @@ pub struct SegmentHeader {
     pub version: u16,
     pub node_count: u64,
     pub edge_count: u64,
+    /// Offset встроенной StringTable; с v5 - dictionary_id файла strings.bin
     pub string_table_offset: u64,
 }
 
@@ -38,29 +366,59 @@ impl SegmentHeader {
     }
 
     pub fn validate(&self) -> Result<()> {
-        if self.magic != MAGIC {
+        let compressed = self.magic == MAGIC_COMPRESSED && self.version >= 3;
+        if self.magic != MAGIC && !compressed {
             return Err(GraphError::InvalidFormat(
                 format!("Неверное магическое число: {:?}", self.magic)
             ));
         }
         // Copy to avoid unaligned reference
         let version = self.version;
-        if version != FORMAT_VERSION {
+        if version > FORMAT_VERSION {
+            return Err(GraphError::InvalidFormat(format!(
+                "Format version {} is newer than supported {} (written by a newer rfdb)",
+                version, FORMAT_VERSION
+            )));
+        }
+        if version < MIN_FORMAT_VERSION {
             return Err(GraphError::InvalidFormat(
                 format!("Неподдерживаемая версия формата: {}", version)
             ));
         }
         Ok(())
     }
+
+    /// Offset встроенной StringTable (до v5; 0 - таблицы нет)
+    fn embedded_string_table_offset(&self) -> Option<usize> {
+        if self.version >= SHARED_DICTIONARY_VERSION || self.string_table_offset == 0 {
+            return None;
+        }
+        Some(usize::try_from(self.string_table_offset).unwrap_or(usize::MAX))
+    }
+
+    /// Id общего словаря strings.bin (v5+ хранит его в поле string_table_offset)
+    pub fn dictionary_id(&self) -> Option<u64> {
+        (self.version >= SHARED_DICTIONARY_VERSION).then_some(self.string_table_offset)
+    }
+
+    /// Проверить наличие checksum footer для версий, где он обязателен
+    fn validate_footer(&self, has_footer: bool) -> Result<()> {
+        if self.version >= 2 && !has_footer {
+            return Err(GraphError::InvalidFormat(
+                format!("Missing checksum footer (required since format version 2, file has {})", self.version)
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// Immutable сегмент нод (memory-mapped)
 pub struct NodesSegment {
-    mmap: Mmap,
+    data: SegmentData,
     header: SegmentHeader,
     node_count: usize,
 
-    // Offsets в mmap для колоночных массивов
+    // Offsets в data для колоночных массивов
     ids_offset: usize,
     type_offsets_offset: usize,  // Теперь u32 offsets в StringTable (было kinds u16)
     file_ids_offset: usize,
@@ -69,98 +427,52 @@ pub struct NodesSegment {
     exported_offset: usize,
     deleted_offset: usize,
     metadata_offsets_offset: usize,
+    // Колонка replaces (v6+; 0 = нет значения)
+    replaces_offset: Option<usize>,
+    columns_end: usize,
+
+    // Конец данных (начало checksum footer)
+    data_end: usize,
+    checksums: Option<Vec<Checksum>>,
 
-    // String table для file paths, имён, версий, типов нод и metadata
-    string_table: Option<StringTable>,
+    // Строки для file paths, имён, версий, типов нод и metadata
+    strings: SegmentStrings,
 }
 
 impl NodesSegment {
-    /// Открыть существующий сегмент
+    /// Открыть существующий сегмент (strings.bin берётся из той же директории)
     pub fn open(path: &Path) -> Result<Self> {
-        let file = File::open(path)?;
-        let mmap = unsafe { Mmap::map(&file)? };
-
-        // Читаем и валидируем заголовок (используем размер на диске: 30 байт)
-        if mmap.len() < HEADER_SIZE_ON_DISK {
-            return Err(GraphError::InvalidFormat("Файл слишком мал".into()));
-        }
-
-        // Manually parse header from bytes (30 bytes on disk)
-        let mut magic = [0u8; 4];
-        magic.copy_from_slice(&mmap[0..4]);
-
-        let version = u16::from_le_bytes([mmap[4], mmap[5]]);
-        let node_count = u64::from_le_bytes(mmap[6..14].try_into().unwrap());
-        let edge_count = u64::from_le_bytes(mmap[14..22].try_into().unwrap());
-        let string_table_offset = u64::from_le_bytes(mmap[22..30].try_into().unwrap());
+        Self::open_with_dictionary(path, None)
+    }
 
-        let header = SegmentHeader {
-            magic,
-            version,
-            node_count,
-            edge_count,
-            string_table_offset,
-        };
-        header.validate()?;
+    /// Открыть сегмент с уже открытым общим словарём
+    pub fn open_with_dictionary(path: &Path, dictionary: Option<Arc<StringDictionary>>) -> Result<Self> {
+        let RawSegment { data, header, checksums, data_end } =
+            open_raw(path, node_column_widths, |h| h.node_count)?;
 
-        // Вычисляем offsets для колоночных массивов
+        // Вычисляем offsets для колоночных массивов и проверяем границы
+        let (offsets, columns_end) = layout_columns(header.node_count, node_column_widths(header.version), data_end)?;
         let node_count = header.node_count as usize;
-        let mut offset = HEADER_SIZE_ON_DISK;
-
-        let ids_offset = offset;
-        offset += node_count * std::mem::size_of::<u128>();
 
-        // type_offsets: u32 offsets в StringTable (было kinds: u16)
-        let type_offsets_offset = offset;
-        offset += node_count * std::mem::size_of::<u32>();
-
-        let file_ids_offset = offset;
-        offset += node_count * std::mem::size_of::<u32>();
-
-        let name_offsets_offset = offset;
-        offset += node_count * std::mem::size_of::<u32>();
-
-        let version_offsets_offset = offset;
-        offset += node_count * std::mem::size_of::<u32>();
-
-        let exported_offset = offset;
-        offset += node_count * std::mem::size_of::<u8>();
-
-        let deleted_offset = offset;
-        offset += node_count * std::mem::size_of::<u8>();
-
-        let metadata_offsets_offset = offset;
-
-        // Попытка загрузить string table если он есть
-        let string_table = if header.string_table_offset > 0
-            && (header.string_table_offset as usize) < mmap.len()
-        {
-            // Создаём sub-mmap для string table
-            let st_offset = header.string_table_offset as usize;
-            let st_mmap = &mmap[st_offset..];
-
-            // Пытаемся загрузить (может упасть если формат неверный)
-            match StringTable::load_from_mmap_slice(st_mmap) {
-                Ok(st) => Some(st),
-                Err(_) => None, // Игнорируем ошибки, просто не будет string table
-            }
-        } else {
-            None
-        };
+        let strings = load_strings(path, &data, &header, columns_end, data_end, dictionary)?;
 
         Ok(Self {
-            mmap,
+            data,
             header,
             node_count,
-            ids_offset,
-            type_offsets_offset,
-            file_ids_offset,
-            name_offsets_offset,
-            version_offsets_offset,
-            exported_offset,
-            deleted_offset,
-            metadata_offsets_offset,
-            string_table,
+            ids_offset: offsets[0],
+            type_offsets_offset: offsets[1],
+            file_ids_offset: offsets[2],
+            name_offsets_offset: offsets[3],
+            version_offsets_offset: offsets[4],
+            exported_offset: offsets[5],
+            deleted_offset: offsets[6],
+            metadata_offsets_offset: offsets[7],
+            replaces_offset: offsets.get(8).copied(),
+            columns_end,
+            data_end,
+            checksums,
+            strings,
         })
     }
 
@@ -168,21 +480,97 @@ impl NodesSegment {
         self.node_count
     }
 
+    /// Есть ли в файле checksum footer
+    pub fn has_checksums(&self) -> bool {
+        self.checksums.is_some()
+    }
+
+    /// Полная проверка целостности: checksums колонок и string table,
+    /// плюс все ссылки в string table указывают на начало строки
+    pub fn verify(&self) -> Result<()> {
+        self.verify_checksums()?;
+        match self.invalid_rows().into_iter().next() {
+            Some((idx, problem)) => Err(GraphError::InvalidFormat(format!("Node #{}: {}", idx, problem))),
+            None => Ok(()),
+        }
+    }
+
+    /// Сверить checksums колонок и string table с footer (no-op для файлов без footer)
+    pub fn verify_checksums(&self) -> Result<()> {
+        let sections = [
+            ("ids", self.ids_offset..self.type_offsets_offset),
+            ("type_offsets", self.type_offsets_offset..self.file_ids_offset),
+            ("file_ids", self.file_ids_offset..self.name_offsets_offset),
+            ("name_offsets", self.name_offsets_offset..self.version_offsets_offset),
+            ("version_offsets", self.version_offsets_offset..self.exported_offset),
+            ("exported", self.exported_offset..self.deleted_offset),
+            ("deleted", self.deleted_offset..self.metadata_offsets_offset),
+            ("metadata_offsets", self.metadata_offsets_offset..self.replaces_offset.unwrap_or(self.columns_end)),
+        ];
+        let sections: Vec<_> = sections.into_iter()
+            .chain(self.replaces_offset.map(|start| ("replaces", start..self.columns_end)))
+            .chain(string_table_section(&self.header, self.columns_end, self.data_end))
+            .collect();
+        verify_sections(&self.data, &sections, self.checksums.as_deref())
+    }
+
+    /// Строки, ссылающиеся мимо string table: (индекс, описание проблемы)
+    pub fn invalid_rows(&self) -> Vec<(usize, String)> {
+        let valid = |o: u32| self.strings.contains(&self.data, o);
+        let has_table = !matches!(self.strings, SegmentStrings::None);
+
+        (0..self.node_count).filter_map(|idx| {
+            // type/version: offset как есть; file/name: offset+1 (0 = нет); metadata: 0 = нет
+            let refs = [
+                ("type", self.get_type_offset(idx).filter(|_| has_table)),
+                ("file", self.get_file_id(idx).filter(|&o| o != 0).map(|o| o - 1)),
+                ("name", self.get_name_offset(idx).filter(|&o| o != 0).map(|o| o - 1)),
+                ("version", self.get_version_offset(idx).filter(|_| has_table)),
+                ("metadata", self.get_metadata_offset(idx).filter(|&o| o != 0)),
+            ];
+            refs.into_iter().find_map(|(column, offset)| {
+                offset.filter(|&o| !valid(o))
+                    .map(|o| (idx, format!("{} offset {} does not point to a string", column, o)))
+            })
+        }).collect()
+    }
+
+    /// Собрать NodeRecord из строки сегмента
+    pub fn node_record(&self, idx: usize) -> Option<NodeRecord> {
+        Some(NodeRecord {
+            id: self.get_id(idx)?,
+            node_type: self.get_node_type(idx).map(|s| s.to_string()),
+            file_id: 0,
+            name_offset: 0,
+            version: self.get_version(idx).unwrap_or("main").to_string(),
+            exported: self.get_exported(idx).unwrap_or(false),
+            replaces: self.get_replaces(idx),
+            deleted: self.is_deleted(idx),
+            name: self.get_name(idx).map(|s| s.to_string()),
+            file: self.get_file_path(idx).map(|s| s.to_string()),
+            metadata: self.get_metadata(idx).map(|s| s.to_string()),
+        })
+    }
+
+    /// Заголовок сегмента
+    pub fn header(&self) -> &SegmentHeader {
+        &self.header
+    }
+
+    /// Версия формата, в которой записан файл
+    pub fn format_version(&self) -> u16 {
+        self.header.version
+    }
+
     // Helper: read u128 from potentially unaligned bytes
     fn read_u128_at(&self, offset: usize) -> u128 {
-        let bytes: [u8; 16] = self.mmap[offset..offset + 16].try_into().unwrap();
+        let bytes: [u8; 16] = self.data[offset..offset + 16].try_into().unwrap();
         u128::from_le_bytes(bytes)
     }
 
-    // Helper: read u16 from potentially unaligned bytes
-    fn read_u16_at(&self, offset: usize) -> u16 {
-        let bytes: [u8; 2] = self.mmap[offset..offset + 2].try_into().unwrap();
-        u16::from_le_bytes(bytes)
-    }
-
     // Helper: read u32 from potentially unaligned bytes
     fn read_u32_at(&self, offset: usize) -> u32 {
-        let bytes: [u8; 4] = self.mmap[offset..offset + 4].try_into().unwrap();
+        let bytes: [u8; 4] = self.data[offset..offset + 4].try_into().unwrap();
         u32::from_le_bytes(bytes)
     }
 
@@ -190,7 +578,7 @@ impl NodesSegment {
     fn deleted(&self) -> &[u8] {
         let start = self.deleted_offset;
         let end = start + self.node_count;
-        &self.mmap[start..end]
+        &self.data[start..end]
     }
 
     /// Получить ID ноды по индексу
@@ -247,17 +635,19 @@ impl NodesSegment {
 
     /// Найти индекс ноды по ID (линейный поиск, можно оптимизировать)
     pub fn find_index(&self, id: u128) -> Option<usize> {
-        for idx in 0..self.node_count {
-            if self.get_id(idx) == Some(id) {
-                return Some(idx);
-            }
-        }
-        None
+        (0..self.node_count).find(|&idx| self.get_id(idx) == Some(id))
+    }
+
+    /// Ключ строки в колонках сегмента (offset или id словаря), если строка есть
+    ///
+    /// Позволяет фильтрам сравнивать u32 вместо декодирования строк.
+    pub fn find_string(&self, s: &str) -> Option<u32> {
+        self.strings.find(&self.data, s)
     }
 
     /// Получить строку по offset из string table
     pub fn get_string(&self, offset: u32) -> Option<&str> {
-        self.string_table.as_ref()?.get(offset)
+        self.strings.get(&self.data, offset)
     }
 
     /// Получить file path по file_id (file_id это offset+1 в string table)
@@ -320,120 +710,198 @@ impl NodesSegment {
             return None;
         }
         let offset = self.exported_offset + idx;
-        Some(self.mmap.get(offset).copied().unwrap_or(0) != 0)
+        Some(self.data.get(offset).copied().unwrap_or(0) != 0)
+    }
+
+    /// Какую ноду заменяет (`__local` версия), до v6 не хранится
+    pub fn get_replaces(&self, idx: usize) -> Option<u128> {
+        let start = self.replaces_offset.filter(|_| idx < self.node_count)?;
+        Some(self.read_u128_at(start + idx * std::mem::size_of::<u128>())).filter(|&id| id != 0)
+    }
+}
+
+/// Индекс рёбер сегмента: id ноды -> строки edges.bin (adjacency / reverse_adjacency)
+#[derive(Debug, Default)]
+pub struct EdgeIndex {
+    pub outgoing: HashMap<u128, Vec<usize>>,
+    pub incoming: HashMap<u128, Vec<usize>>,
+}
+
+impl EdgeIndex {
+    /// Построить по сегменту, пропуская удалённые рёбра
+    pub fn build(segment: &EdgesSegment) -> Self {
+        let mut index = Self::default();
+        for idx in 0..segment.edge_count() {
+            if segment.is_deleted(idx) {
+                continue;
+            }
+            if let (Some(src), Some(dst)) = (segment.get_src(idx), segment.get_dst(idx)) {
+                index.insert(idx, src, dst);
+            }
+        }
+        index
+    }
+
+    /// Добавить ребро `idx` (строка edges.bin)
+    pub fn insert(&mut self, idx: usize, src: u128, dst: u128) {
+        self.outgoing.entry(src).or_default().push(idx);
+        self.incoming.entry(dst).or_default().push(idx);
     }
 }
 
 /// Immutable сегмент рёбер (memory-mapped)
 pub struct EdgesSegment {
-    mmap: Mmap,
+    data: SegmentData,
     header: SegmentHeader,
     edge_count: usize,
 
-    // Offsets в mmap
+    // Offsets в data
     src_offset: usize,
     dst_offset: usize,
     edge_type_offsets_offset: usize,  // u32 offsets в StringTable (было etypes u16)
     metadata_offsets_offset: usize,   // u32 offsets в StringTable для edge metadata
     deleted_offset: usize,
+    version_offsets_offset: Option<usize>, // v6+
+    columns_end: usize,
+
+    // Конец данных (начало checksum footer)
+    data_end: usize,
+    checksums: Option<Vec<Checksum>>,
 
-    // String table для edge types и metadata
-    string_table: Option<StringTable>,
+    // Строки для edge types, versions и metadata
+    strings: SegmentStrings,
 }
 
 impl EdgesSegment {
+    /// Открыть существующий сегмент (strings.bin берётся из той же директории)
     pub fn open(path: &Path) -> Result<Self> {
-        let file = File::open(path)?;
-        let mmap = unsafe { Mmap::map(&file)? };
-
-        // Manually parse header from bytes (30 bytes on disk)
-        if mmap.len() < HEADER_SIZE_ON_DISK {
-            return Err(GraphError::InvalidFormat("Файл слишком мал".into()));
-        }
+        Self::open_with_dictionary(path, None)
+    }
 
-        let mut magic = [0u8; 4];
-        magic.copy_from_slice(&mmap[0..4]);
+    /// Открыть сегмент с уже открытым общим словарём
+    pub fn open_with_dictionary(path: &Path, dictionary: Option<Arc<StringDictionary>>) -> Result<Self> {
+        let RawSegment { data, header, checksums, data_end } =
+            open_raw(path, edge_column_widths, |h| h.edge_count)?;
 
-        let version = u16::from_le_bytes([mmap[4], mmap[5]]);
-        let node_count = u64::from_le_bytes(mmap[6..14].try_into().unwrap());
-        let edge_count_u64 = u64::from_le_bytes(mmap[14..22].try_into().unwrap());
-        let string_table_offset = u64::from_le_bytes(mmap[22..30].try_into().unwrap());
+        let (offsets, columns_end) = layout_columns(header.edge_count, edge_column_widths(header.version), data_end)?;
+        let edge_count = header.edge_count as usize;
 
-        let header = SegmentHeader {
-            magic,
-            version,
-            node_count,
-            edge_count: edge_count_u64,
-            string_table_offset,
-        };
-        header.validate()?;
+        let strings = load_strings(path, &data, &header, columns_end, data_end, dictionary)?;
 
-        let edge_count = edge_count_u64 as usize;
-        let mut offset = HEADER_SIZE_ON_DISK;
-
-        let src_offset = offset;
-        offset += edge_count * std::mem::size_of::<u128>();
+        Ok(Self {
+            data,
+            header,
+            edge_count,
+            src_offset: offsets[0],
+            dst_offset: offsets[1],
+            edge_type_offsets_offset: offsets[2],
+            metadata_offsets_offset: offsets[3],
+            deleted_offset: offsets[4],
+            version_offsets_offset: offsets.get(5).copied(),
+            columns_end,
+            data_end,
+            checksums,
+            strings,
+        })
+    }
 
-        let dst_offset = offset;
-        offset += edge_count * std::mem::size_of::<u128>();
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
 
-        // edge_type_offsets: u32 offsets в StringTable (было etypes u16)
-        let edge_type_offsets_offset = offset;
-        offset += edge_count * std::mem::size_of::<u32>();
+    /// Есть ли в файле checksum footer
+    pub fn has_checksums(&self) -> bool {
+        self.checksums.is_some()
+    }
 
-        // metadata_offsets: u32 offsets в StringTable для edge metadata
-        let metadata_offsets_offset = offset;
-        offset += edge_count * std::mem::size_of::<u32>();
+    /// Полная проверка целостности: checksums колонок и string table,
+    /// плюс offsets типов и metadata указывают на начало строки
+    pub fn verify(&self) -> Result<()> {
+        self.verify_checksums()?;
+        match self.invalid_rows().into_iter().next() {
+            Some((idx, problem)) => Err(GraphError::InvalidFormat(format!("Edge #{}: {}", idx, problem))),
+            None => Ok(()),
+        }
+    }
 
-        let deleted_offset = offset;
+    /// Сверить checksums колонок и string table с footer (no-op для файлов без footer)
+    pub fn verify_checksums(&self) -> Result<()> {
+        let sections = [
+            ("src", self.src_offset..self.dst_offset),
+            ("dst", self.dst_offset..self.edge_type_offsets_offset),
+            ("edge_type_offsets", self.edge_type_offsets_offset..self.metadata_offsets_offset),
+            ("metadata_offsets", self.metadata_offsets_offset..self.deleted_offset),
+            ("deleted", self.deleted_offset..self.version_offsets_offset.unwrap_or(self.columns_end)),
+        ];
+        let sections: Vec<_> = sections.into_iter()
+            .chain(self.version_offsets_offset.map(|start| ("version_offsets", start..self.columns_end)))
+            .chain(string_table_section(&self.header, self.columns_end, self.data_end))
+            .collect();
+        verify_sections(&self.data, &sections, self.checksums.as_deref())
+    }
+
+    /// Рёбра, ссылающиеся мимо string table: (индекс, описание проблемы)
+    pub fn invalid_rows(&self) -> Vec<(usize, String)> {
+        let st = &self.strings;
+        if matches!(st, SegmentStrings::None) {
+            return Vec::new();
+        }
 
-        // Загрузить string table если он есть
-        let string_table = if header.string_table_offset > 0
-            && (header.string_table_offset as usize) < mmap.len()
-        {
-            let st_offset = header.string_table_offset as usize;
-            let st_mmap = &mmap[st_offset..];
-            match StringTable::load_from_mmap_slice(st_mmap) {
-                Ok(st) => Some(st),
-                Err(_) => None,
+        (0..self.edge_count).filter_map(|idx| {
+            let type_offset = self.get_edge_type_offset(idx).unwrap_or(0);
+            let metadata_offset = self.get_metadata_offset(idx).unwrap_or(0);
+            let version_offset = self.get_version_offset(idx);
+            // Ребро без типа в пустой таблице хранит 0
+            let type_valid = st.contains(&self.data, type_offset) || (type_offset == 0 && st.is_empty());
+            if !type_valid {
+                Some((idx, format!("type offset {} does not point to a string", type_offset)))
+            } else if metadata_offset != 0 && !st.contains(&self.data, metadata_offset) {
+                Some((idx, format!("metadata offset {} does not point to a string", metadata_offset)))
+            } else {
+                version_offset.filter(|&o| !st.contains(&self.data, o))
+                    .map(|o| (idx, format!("version offset {} does not point to a string", o)))
             }
-        } else {
-            None
-        };
-
-        Ok(Self {
-            mmap,
-            header,
-            edge_count,
-            src_offset,
-            dst_offset,
-            edge_type_offsets_offset,
-            metadata_offsets_offset,
-            deleted_offset,
-            string_table,
+        }).collect()
+    }
+
+    /// Собрать EdgeRecord из строки сегмента
+    pub fn edge_record(&self, idx: usize) -> Option<EdgeRecord> {
+        Some(EdgeRecord {
+            src: self.get_src(idx)?,
+            dst: self.get_dst(idx)?,
+            edge_type: self.get_edge_type(idx).map(|s| s.to_string()),
+            version: self.get_version(idx).unwrap_or("main").to_string(),
+            metadata: self.get_metadata(idx).map(|s| s.to_string()),
+            deleted: self.is_deleted(idx),
         })
     }
 
-    pub fn edge_count(&self) -> usize {
-        self.edge_count
+    /// Заголовок сегмента
+    pub fn header(&self) -> &SegmentHeader {
+        &self.header
+    }
+
+    /// Версия формата, в которой записан файл
+    pub fn format_version(&self) -> u16 {
+        self.header.version
     }
 
     // Helper: read u128 from potentially unaligned bytes
     fn read_u128_at(&self, offset: usize) -> u128 {
-        let bytes: [u8; 16] = self.mmap[offset..offset + 16].try_into().unwrap();
+        let bytes: [u8; 16] = self.data[offset..offset + 16].try_into().unwrap();
         u128::from_le_bytes(bytes)
     }
 
     // Helper: read u32 from potentially unaligned bytes
     fn read_u32_at(&self, offset: usize) -> u32 {
-        let bytes: [u8; 4] = self.mmap[offset..offset + 4].try_into().unwrap();
+        let bytes: [u8; 4] = self.data[offset..offset + 4].try_into().unwrap();
         u32::from_le_bytes(bytes)
     }
 
     fn deleted(&self) -> &[u8] {
         let start = self.deleted_offset;
         let end = start + self.edge_count;
-        &self.mmap[start..end]
+        &self.data[start..end]
     }
 
     pub fn get_src(&self, idx: usize) -> Option<u128> {
@@ -464,7 +932,7 @@ impl EdgesSegment {
     /// Получить тип ребра как строку из StringTable
     pub fn get_edge_type(&self, idx: usize) -> Option<&str> {
         let type_offset = self.get_edge_type_offset(idx)?;
-        self.string_table.as_ref()?.get(type_offset)
+        self.strings.get(&self.data, type_offset)
     }
 
     /// Получить offset metadata ребра в StringTable
@@ -482,13 +950,25 @@ impl EdgesSegment {
         if metadata_offset == 0 {
             return None;  // 0 means no metadata
         }
-        self.string_table.as_ref()?.get(metadata_offset)
+        self.strings.get(&self.data, metadata_offset)
     }
 
     pub fn is_deleted(&self, idx: usize) -> bool {
         self.deleted().get(idx).copied().unwrap_or(0) != 0
     }
 
+    /// Получить offset версии ребра (v6+)
+    pub fn get_version_offset(&self, idx: usize) -> Option<u32> {
+        let start = self.version_offsets_offset.filter(|_| idx < self.edge_count)?;
+        Some(self.read_u32_at(start + idx * std::mem::size_of::<u32>()))
+    }
+
+    /// Версия ребра ("main" / "__local"); до v6 не хранится
+    pub fn get_version(&self, idx: usize) -> Option<&str> {
+        let version_offset = self.get_version_offset(idx)?;
+        self.strings.get(&self.data, version_offset)
+    }
+
     /// Найти все рёбра исходящие из ноды
     pub fn find_outgoing(&self, src_id: u128) -> Vec<usize> {
         (0..self.edge_count())
@@ -1,17 +1,25 @@
 //! String table для хранения файловых путей и имён
+//!
+//! Layout: `[u64 data_len][data][u64 count][u32 offsets...]`.
+//! С format v4 каждая строка в data хранится как `[u32 len][utf8 bytes]`,
+//! offset указывает на префикс длины - чтение O(1) прямо из mmap.
+//! До v4 префикса не было, конец строки - следующий offset.
 
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
 use std::fs::File;
 use std::io::{Write, BufWriter};
-use memmap2::Mmap;
 use crate::error::{GraphError, Result};
 
-/// String table: все строки в одном blob + массив offset'ов
+/// Размер префикса длины строки
+const LEN_PREFIX: usize = 4;
+
+/// String table (builder для writer'а): все строки в одном blob + массив offset'ов
 pub struct StringTable {
     data: Vec<u8>,
     offsets: Vec<u32>,
-    index: HashMap<String, u32>, // String -> offset
+    index: HashMap<String, u32>, // String -> index в offsets
 }
 
 impl StringTable {
@@ -25,15 +33,22 @@ impl StringTable {
 
     /// Добавить строку, вернуть offset
     pub fn intern(&mut self, s: &str) -> u32 {
-        if let Some(&offset) = self.index.get(s) {
-            return offset;
+        let id = self.intern_id(s);
+        self.offsets[id as usize]
+    }
+
+    /// Добавить строку, вернуть её порядковый id (индекс в offsets)
+    pub fn intern_id(&mut self, s: &str) -> u32 {
+        if let Some(&id) = self.index.get(s) {
+            return id;
         }
 
-        let offset = self.data.len() as u32;
+        let id = self.offsets.len() as u32;
+        self.offsets.push(self.data.len() as u32);
+        self.data.extend_from_slice(&(s.len() as u32).to_le_bytes());
         self.data.extend_from_slice(s.as_bytes());
-        self.offsets.push(offset);
-        self.index.insert(s.to_string(), offset);
-        offset
+        self.index.insert(s.to_string(), id);
+        id
     }
 
     /// Alias для intern (для совместимости с writer.rs)
@@ -43,21 +58,21 @@ impl StringTable {
 
     /// Получить строку по offset
     pub fn get(&self, offset: u32) -> Option<&str> {
-        let start = offset as usize;
-
-        // Найти следующий offset для определения длины
-        let next_offset = self.offsets.iter()
-            .find(|&&o| o > offset)
-            .copied()
-            .unwrap_or(self.data.len() as u32);
+        read_prefixed(&self.data, offset as usize)
+    }
 
-        let end = next_offset as usize;
+    /// Является ли offset началом строки в таблице
+    pub fn contains_offset(&self, offset: u32) -> bool {
+        self.offsets.binary_search(&offset).is_ok()
+    }
 
-        if start >= self.data.len() || end > self.data.len() {
-            return None;
-        }
+    /// Количество строк
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
 
-        std::str::from_utf8(&self.data[start..end]).ok()
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
     }
 
     /// Сохранить в файл
@@ -85,79 +100,204 @@ impl StringTable {
 
         Ok(())
     }
+}
 
-    /// Загрузить из mmap
-    pub fn load_from_mmap(mmap: &Mmap) -> Result<Self> {
-        Self::load_from_mmap_slice(&mmap[..])
+impl Default for StringTable {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Загрузить из slice (для sub-mmap)
-    pub fn load_from_mmap_slice(slice: &[u8]) -> Result<Self> {
-        let mut offset = 0;
+/// Прочитать `[u32 len][bytes]` начиная с `start`
+fn read_prefixed(data: &[u8], start: usize) -> Option<&str> {
+    let len = u32::from_le_bytes(data.get(start..start.checked_add(LEN_PREFIX)?)?.try_into().ok()?) as usize;
+    let begin = start + LEN_PREFIX;
+    std::str::from_utf8(data.get(begin..begin.checked_add(len)?)?).ok()
+}
 
-        if slice.len() < 8 {
-            return Err(GraphError::InvalidFormat("String table too small".into()));
-        }
+fn read_u64(bytes: &[u8], pos: usize, what: &str) -> Result<u64> {
+    bytes.get(pos..pos + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| GraphError::InvalidFormat(format!("String table: missing {}", what)))
+}
 
-        // Читаем длину data
-        let data_len = u64::from_le_bytes(
-            slice[offset..offset + 8]
-                .try_into()
-                .map_err(|_| GraphError::InvalidFormat("Неверный размер data".into()))?
-        ) as usize;
-        offset += 8;
+/// Zero-copy представление string table внутри буфера сегмента
+///
+/// Хранит только диапазоны; байты передаются в каждый вызов,
+/// чтобы view жил рядом с mmap в той же структуре.
+#[derive(Debug, Clone)]
+pub struct StringTableView {
+    data: Range<usize>,
+    offsets_start: usize,
+    count: usize,
+    length_prefixed: bool,
+}
 
-        if offset + data_len > slice.len() {
-            return Err(GraphError::InvalidFormat("Invalid data length".into()));
+impl StringTableView {
+    /// Разобрать таблицу в `bytes[range]` и проверить все offsets
+    ///
+    /// `length_prefixed` - layout v4+; иначе legacy (конец = следующий offset).
+    pub fn parse(bytes: &[u8], range: Range<usize>, length_prefixed: bool) -> Result<Self> {
+        let slice = bytes.get(range.clone())
+            .ok_or_else(|| GraphError::InvalidFormat("String table outside of segment".into()))?;
+
+        let data_len = usize::try_from(read_u64(slice, 0, "data length")?).unwrap_or(usize::MAX);
+        let data_end = data_len.checked_add(8).filter(|&end| end <= slice.len())
+            .ok_or_else(|| GraphError::InvalidFormat("Invalid data length".into()))?;
+
+        let count = usize::try_from(read_u64(slice, data_end, "offsets count")?).unwrap_or(usize::MAX);
+        let offsets_start = data_end + 8;
+        if count.checked_mul(4).and_then(|n| n.checked_add(offsets_start)).is_none_or(|end| end > slice.len()) {
+            return Err(GraphError::InvalidFormat("Invalid offsets count".into()));
         }
 
-        // Читаем data
-        let data = slice[offset..offset + data_len].to_vec();
-        offset += data_len;
+        let view = Self {
+            data: range.start + 8..range.start + data_end,
+            offsets_start: range.start + offsets_start,
+            count,
+            length_prefixed,
+        };
 
-        if offset + 8 > slice.len() {
-            return Err(GraphError::InvalidFormat("Missing offsets count".into()));
+        // Offsets по возрастанию; в v4 каждая строка целиком внутри data
+        let data = &bytes[view.data.clone()];
+        let mut prev = 0u32;
+        for i in 0..count {
+            let o = view.offset_at(bytes, i);
+            let in_bounds = if length_prefixed {
+                read_prefixed(data, o as usize).is_some()
+            } else {
+                o as usize <= data.len()
+            };
+            if o < prev || !in_bounds {
+                return Err(GraphError::InvalidFormat(
+                    format!("String table offset {} out of order or beyond data ({} bytes)", o, data.len())
+                ));
+            }
+            prev = o;
         }
 
-        // Читаем количество offsets
-        let offsets_count = u64::from_le_bytes(
-            slice[offset..offset + 8]
-                .try_into()
-                .map_err(|_| GraphError::InvalidFormat("Неверный размер offsets".into()))?
-        ) as usize;
-        offset += 8;
+        Ok(view)
+    }
 
-        if offset + offsets_count * 4 > slice.len() {
-            return Err(GraphError::InvalidFormat("Invalid offsets count".into()));
+    fn offset_at(&self, bytes: &[u8], i: usize) -> u32 {
+        let pos = self.offsets_start + i * 4;
+        u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+    }
+
+    /// Индекс первого offset, для которого `pred` ложен (offsets отсортированы)
+    fn partition_point(&self, bytes: &[u8], pred: impl Fn(u32) -> bool) -> usize {
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(self.offset_at(bytes, mid)) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
         }
+        lo
+    }
 
-        // Читаем offsets
-        let mut offsets = Vec::with_capacity(offsets_count);
-        for _ in 0..offsets_count {
-            let o = u32::from_le_bytes(
-                slice[offset..offset + 4]
-                    .try_into()
-                    .map_err(|_| GraphError::InvalidFormat("Неверный offset".into()))?
-            );
-            offsets.push(o);
-            offset += 4;
+    /// Получить строку по offset: O(1) для v4, O(log n) для legacy layout
+    pub fn get<'a>(&self, bytes: &'a [u8], offset: u32) -> Option<&'a str> {
+        let data = &bytes[self.data.clone()];
+        if self.length_prefixed {
+            return read_prefixed(data, offset as usize);
         }
 
-        // Строим индекс
-        let mut index = HashMap::new();
-        for (i, &offset) in offsets.iter().enumerate() {
-            let next_offset = offsets.get(i + 1).copied().unwrap_or(data.len() as u32);
-            if let Ok(s) = std::str::from_utf8(&data[offset as usize..next_offset as usize]) {
-                index.insert(s.to_string(), offset);
-            }
+        let start = offset as usize;
+        if start >= data.len() {
+            return None;
+        }
+        let next = self.partition_point(bytes, |o| o <= offset);
+        let end = if next < self.count { self.offset_at(bytes, next) as usize } else { data.len() };
+        std::str::from_utf8(data.get(start..end)?).ok()
+    }
+
+    /// Получить строку по порядковому id (индексу в offsets), O(1)
+    pub fn get_by_id<'a>(&self, bytes: &'a [u8], id: u32) -> Option<&'a str> {
+        if id as usize >= self.count {
+            return None;
         }
+        self.get(bytes, self.offset_at(bytes, id as usize))
+    }
+
+    /// Найти id строки (линейный проход, для разрешения фильтров один раз на запрос)
+    pub fn find_id(&self, bytes: &[u8], s: &str) -> Option<u32> {
+        (0..self.count as u32).find(|&id| self.get_by_id(bytes, id) == Some(s))
+    }
+
+    /// Найти offset строки (линейный проход)
+    pub fn find_offset(&self, bytes: &[u8], s: &str) -> Option<u32> {
+        self.find_id(bytes, s).map(|id| self.offset_at(bytes, id as usize))
+    }
+
+    /// Является ли offset началом строки в таблице
+    pub fn contains_offset(&self, bytes: &[u8], offset: u32) -> bool {
+        let idx = self.partition_point(bytes, |o| o < offset);
+        idx < self.count && self.offset_at(bytes, idx) == offset
+    }
 
-        Ok(Self { data, offsets, index })
+    /// Количество строк
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
     }
 }
 
-impl Default for StringTable {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy_table(strings: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for s in strings {
+            offsets.push(data.len() as u32);
+            data.extend_from_slice(s.as_bytes());
+        }
+        let mut out = (data.len() as u64).to_le_bytes().to_vec();
+        out.extend_from_slice(&data);
+        out.extend_from_slice(&(offsets.len() as u64).to_le_bytes());
+        offsets.iter().for_each(|o| out.extend_from_slice(&o.to_le_bytes()));
+        out
+    }
+
+    #[test]
+    fn test_view_roundtrip() {
+        let mut table = StringTable::new();
+        let a = table.intern("FUNCTION");
+        let empty = table.intern("");
+        let b = table.intern("src/index.js");
+        assert_eq!(table.intern("FUNCTION"), a);
+        assert_eq!(table.get(b), Some("src/index.js"));
+
+        // Таблица с отступом внутри буфера, как в сегменте
+        let mut bytes = vec![0xAA; 3];
+        table.write_to(&mut bytes).unwrap();
+        let view = StringTableView::parse(&bytes, 3..bytes.len(), true).unwrap();
+
+        assert_eq!(view.len(), 3);
+        assert_eq!(view.get(&bytes, a), Some("FUNCTION"));
+        assert_eq!(view.get(&bytes, empty), Some(""));
+        assert_eq!(view.get(&bytes, b), Some("src/index.js"));
+        assert!(view.contains_offset(&bytes, b));
+        assert!(!view.contains_offset(&bytes, b + 1));
+        assert_eq!(view.get(&bytes, 10_000), None);
+    }
+
+    #[test]
+    fn test_legacy_layout() {
+        let bytes = legacy_table(&["FUNCTION", "", "main"]);
+        let view = StringTableView::parse(&bytes, 0..bytes.len(), false).unwrap();
+        assert_eq!(view.get(&bytes, 0), Some("FUNCTION"));
+        assert_eq!(view.get(&bytes, 8), Some("main"));
+        assert!(view.contains_offset(&bytes, 8));
+
+        // Тот же blob как v4 - длины не сходятся
+        assert!(StringTableView::parse(&bytes, 0..bytes.len(), true).is_err());
     }
 }
@@ -2,293 +2,227 @@
 
 use std::path::Path;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write, Seek};
-use std::collections::HashMap;
+use std::io::{BufWriter, Read, Write};
 use crate::storage::{NodeRecord, EdgeRecord};
-use crate::storage::segment::{SegmentHeader, MAGIC, FORMAT_VERSION};
-use crate::storage::string_table::StringTable;
+use crate::storage::segment::{SegmentHeader, Checksum, FORMAT_VERSION, MAGIC_COMPRESSED, section_checksum, write_checksum_footer};
+use crate::storage::compression::{Compression, encode_block, write_block};
+use crate::storage::dictionary::{DictionaryBuilder, DICTIONARY_FILE};
+use crate::storage::endpoint::{EndpointRule, default_endpoint_rules};
 use crate::error::Result;
 
 /// Writer для записи сегментов на диск
 pub struct SegmentWriter {
     path: std::path::PathBuf,
+    compression: Compression,
 }
 
 impl SegmentWriter {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         Self {
             path: path.as_ref().to_path_buf(),
+            compression: Compression::None,
         }
     }
 
-    /// Записать nodes segment в файл
+    /// Писать сегменты сжатыми (magic SGRZ)
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Записать nodes.bin и edges.bin с одним словарём
+    ///
+    /// `dictionary` - `DictionaryBuilder::load_or_new` для дописывания strings.bin
+    /// или `DictionaryBuilder::new()` для словаря только из строк этих записей.
+    pub fn write_segments(&self, nodes: &[NodeRecord], edges: &[EdgeRecord], dictionary: &mut DictionaryBuilder) -> Result<()> {
+        self.write_nodes_with(nodes, dictionary)?;
+        self.write_edges_with(edges, dictionary)
+    }
+
+    /// Записать nodes segment в файл (словарь загружается из директории)
     pub fn write_nodes(&self, nodes: &[NodeRecord]) -> Result<()> {
+        self.write_nodes_with(nodes, &mut DictionaryBuilder::load_or_new(&self.path)?)
+    }
+
+    /// Записать edges segment в файл (словарь загружается из директории)
+    pub fn write_edges(&self, edges: &[EdgeRecord]) -> Result<()> {
+        self.write_edges_with(edges, &mut DictionaryBuilder::load_or_new(&self.path)?)
+    }
+
+    fn write_nodes_with(&self, nodes: &[NodeRecord], dictionary: &mut DictionaryBuilder) -> Result<()> {
+        let _span = tracing::debug_span!("write_nodes", count = nodes.len()).entered();
         let nodes_path = self.path.join("nodes.bin");
+        let tmp_path = self.path.join("nodes.bin.tmp");
         let file = OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(&nodes_path)?;
+            .open(&tmp_path)?;
 
         let mut writer = BufWriter::new(file);
 
-        // Построить StringTable из типов нод, имен, путей файлов, версий и metadata
-        let mut string_table = StringTable::new();
-        let mut type_map: HashMap<String, u32> = HashMap::new();
-        let mut file_map: HashMap<String, u32> = HashMap::new();
-        let mut name_map: HashMap<String, u32> = HashMap::new();
-        let mut version_map: HashMap<String, u32> = HashMap::new();
-        let mut metadata_map: HashMap<String, u32> = HashMap::new();
-
-        // Debug: count nodes with metadata
-        let nodes_with_metadata = nodes.iter().filter(|n| n.metadata.is_some()).count();
-        eprintln!("[WRITER] Received {} nodes, {} have metadata", nodes.len(), nodes_with_metadata);
-
-        // Собрать уникальные строки
-        for node in nodes {
-            // Add node_type to string table
-            if let Some(ref node_type) = node.node_type {
-                if !type_map.contains_key(node_type) {
-                    let offset = string_table.add(node_type);
-                    type_map.insert(node_type.clone(), offset);
-                }
-            }
-            if let Some(ref file) = node.file {
-                if !file_map.contains_key(file) {
-                    let id = string_table.add(file);
-                    file_map.insert(file.clone(), id);
-                }
-            }
-            if let Some(ref name) = node.name {
-                if !name_map.contains_key(name) {
-                    let offset = string_table.add(name);
-                    name_map.insert(name.clone(), offset);
-                }
-            }
-            // Add version to string table
-            if !version_map.contains_key(&node.version) {
-                let offset = string_table.add(&node.version);
-                version_map.insert(node.version.clone(), offset);
-            }
-            // Add metadata to string table
-            if let Some(ref metadata) = node.metadata {
-                if !metadata_map.contains_key(metadata) {
-                    let offset = string_table.add(metadata);
-                    metadata_map.insert(metadata.clone(), offset);
-                }
-            }
-        }
-
-        eprintln!("[WRITER] StringTable has {} unique node types, {} unique metadata strings",
-            type_map.len(), metadata_map.len());
-
-        // Создать массивы type_offsets, file_ids, name_offsets, version_offsets, metadata_offsets, exported
-        let mut type_offsets = Vec::with_capacity(nodes.len());
+        // Строки нод идут в общий словарь strings.bin (дописывается, id стабильны)
+        // Создать массивы type_ids, file_ids, name_ids, version_ids, metadata_ids, exported
+        let mut type_ids = Vec::with_capacity(nodes.len());
         let mut file_ids = Vec::with_capacity(nodes.len());
-        let mut name_offsets = Vec::with_capacity(nodes.len());
-        let mut version_offsets = Vec::with_capacity(nodes.len());
-        let mut metadata_offsets = Vec::with_capacity(nodes.len());
+        let mut name_ids = Vec::with_capacity(nodes.len());
+        let mut version_ids = Vec::with_capacity(nodes.len());
+        let mut metadata_ids = Vec::with_capacity(nodes.len());
         let mut exported_flags = Vec::with_capacity(nodes.len());
 
         for node in nodes {
-            let type_offset = node.node_type.as_ref()
-                .and_then(|t| type_map.get(t).copied())
-                .unwrap_or(0);
+            // id 0 - пустая строка, поэтому 0 для metadata означает "нет значения"
+            let type_id = node.node_type.as_ref().map(|t| dictionary.intern(t)).unwrap_or(0);
             // +1 чтобы 0 означал "нет значения" (sentinel)
-            let file_id = node.file.as_ref()
-                .and_then(|f| file_map.get(f).copied())
-                .map(|x| x + 1)  // +1: 0 reserved for None
-                .unwrap_or(0);
-            let name_offset = node.name.as_ref()
-                .and_then(|n| name_map.get(n).copied())
-                .map(|x| x + 1)  // +1: 0 reserved for None
-                .unwrap_or(0);
-            let version_offset = version_map.get(&node.version).copied().unwrap_or(0);
-            let metadata_offset = node.metadata.as_ref()
-                .and_then(|m| metadata_map.get(m).copied())
-                .unwrap_or(0);
-
-            type_offsets.push(type_offset);
+            let file_id = node.file.as_ref().map(|f| dictionary.intern(f) + 1).unwrap_or(0);
+            let name_id = node.name.as_ref().map(|n| dictionary.intern(n) + 1).unwrap_or(0);
+            let version_id = dictionary.intern(&node.version);
+            let metadata_id = node.metadata.as_ref().map(|m| dictionary.intern(m)).unwrap_or(0);
+
+            type_ids.push(type_id);
             file_ids.push(file_id);
-            name_offsets.push(name_offset);
-            version_offsets.push(version_offset);
-            metadata_offsets.push(metadata_offset);
+            name_ids.push(name_id);
+            version_ids.push(version_id);
+            metadata_ids.push(metadata_id);
             exported_flags.push(node.exported);
         }
 
-        // Записываем header (пока с нулевым string_table_offset, обновим позже)
-        let header_offset = writer.stream_position()? as u64;
+        // Словарь пишется раньше сегмента: сегмент не ссылается на несуществующие id
+        dictionary.write(&self.path, self.compression)?;
+
         let mut header = SegmentHeader::new(
             nodes.len() as u64,
             0, // edges count (в другом файле)
-            0, // string table offset (заполним после записи колонок)
+            dictionary.id(),
         );
+        if self.compression.is_enabled() {
+            header.magic = MAGIC_COMPRESSED;
+        }
 
         self.write_header(&mut writer, &header)?;
 
-        // Записываем колоночные массивы
+        // Записываем колоночные массивы (checksum каждой колонки идёт в footer)
+        let mut sections = SectionWriter::new(&mut writer, self.compression);
+
         // 1. IDs
-        for node in nodes {
-            writer.write_all(&node.id.to_le_bytes())?;
-        }
+        sections.write_u128(nodes.iter().flat_map(|n| n.id.to_le_bytes()))?;
 
-        // 2. Type offsets (u32 offsets в StringTable, было kinds u16)
-        for &type_offset in &type_offsets {
-            writer.write_all(&type_offset.to_le_bytes())?;
-        }
+        // 2. Type ids (u32 id в strings.bin, было kinds u16)
+        sections.write(type_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
         // 3. File IDs
-        for &file_id in &file_ids {
-            writer.write_all(&file_id.to_le_bytes())?;
-        }
+        sections.write(file_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
-        // 4. Name offsets
-        for &name_offset in &name_offsets {
-            writer.write_all(&name_offset.to_le_bytes())?;
-        }
+        // 4. Name ids
+        sections.write(name_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
-        // 5. Version offsets (NEW)
-        for &version_offset in &version_offsets {
-            writer.write_all(&version_offset.to_le_bytes())?;
-        }
+        // 5. Version ids
+        sections.write(version_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
-        // 6. Exported flags (NEW)
-        for &exported in &exported_flags {
-            writer.write_all(&[if exported { 1 } else { 0 }])?;
-        }
+        // 6. Exported flags
+        sections.write(exported_flags.iter().map(|&e| e as u8))?;
 
         // 7. Deleted flags
-        for node in nodes {
-            writer.write_all(&[if node.deleted { 1 } else { 0 }])?;
-        }
+        sections.write(nodes.iter().map(|n| n.deleted as u8))?;
 
-        // 8. Metadata offsets (NEW)
-        for &metadata_offset in &metadata_offsets {
-            writer.write_all(&metadata_offset.to_le_bytes())?;
-        }
+        // 8. Metadata ids
+        sections.write(metadata_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
-        // Записываем StringTable
-        let string_table_offset = writer.stream_position()?;
-        string_table.write_to(&mut writer)?;
+        // 9. Replaces (0 = нет значения)
+        sections.write_u128(nodes.iter().flat_map(|n| n.replaces.unwrap_or(0).to_le_bytes()))?;
 
-        // Обновляем header с правильным string_table_offset
-        header.string_table_offset = string_table_offset;
-        writer.seek(std::io::SeekFrom::Start(header_offset))?;
-        self.write_header(&mut writer, &header)?;
+        let checksums = sections.checksums;
+        write_checksum_footer(&mut writer, &checksums)?;
 
-        writer.flush()?;
+        commit_file(writer, &tmp_path, &nodes_path)?;
 
-        tracing::info!("Written {} nodes to {:?} with StringTable at offset {}",
-            nodes.len(), nodes_path, string_table_offset);
+        tracing::info!("Written {} nodes to {:?} (strings.bin {:016x})",
+            nodes.len(), nodes_path, dictionary.id());
         Ok(())
     }
 
-    /// Записать edges segment в файл
-    pub fn write_edges(&self, edges: &[EdgeRecord]) -> Result<()> {
+    fn write_edges_with(&self, edges: &[EdgeRecord], dictionary: &mut DictionaryBuilder) -> Result<()> {
+        let _span = tracing::debug_span!("write_edges", count = edges.len()).entered();
+
+        // Сжатие эффективнее на отсортированных src/dst (повторы дают дельту 0)
+        let sorted: Vec<EdgeRecord>;
+        let edges = if self.compression.is_enabled() {
+            sorted = {
+                let mut edges = edges.to_vec();
+                edges.sort_by_key(|e| (e.src, e.dst));
+                edges
+            };
+            &sorted[..]
+        } else {
+            edges
+        };
         let edges_path = self.path.join("edges.bin");
+        let tmp_path = self.path.join("edges.bin.tmp");
         let file = OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(&edges_path)?;
+            .open(&tmp_path)?;
 
         let mut writer = BufWriter::new(file);
 
-        // Построить StringTable для edge types и metadata
-        let mut string_table = StringTable::new();
-        let mut edge_type_map: HashMap<String, u32> = HashMap::new();
-        let mut metadata_map: HashMap<String, u32> = HashMap::new();
-
-        // Собрать уникальные edge types и metadata
-        for edge in edges {
-            if let Some(ref edge_type) = edge.edge_type {
-                if !edge_type_map.contains_key(edge_type) {
-                    let offset = string_table.add(edge_type);
-                    edge_type_map.insert(edge_type.clone(), offset);
-                }
-            }
-            if let Some(ref metadata) = edge.metadata {
-                if !metadata_map.contains_key(metadata) {
-                    let offset = string_table.add(metadata);
-                    metadata_map.insert(metadata.clone(), offset);
-                }
-            }
-        }
-
-        eprintln!("[WRITER] Writing {} edges with {} unique edge types, {} unique metadata",
-            edges.len(), edge_type_map.len(), metadata_map.len());
-
-        // Создать массивы edge_type_offsets и metadata_offsets
-        let edge_type_offsets: Vec<u32> = edges.iter()
-            .map(|e| {
-                e.edge_type.as_ref()
-                    .and_then(|t| edge_type_map.get(t).copied())
-                    .unwrap_or(0)
-            })
+        // Типы рёбер и metadata идут в тот же strings.bin, что и строки нод
+        let edge_type_ids: Vec<u32> = edges.iter()
+            .map(|e| e.edge_type.as_ref().map(|t| dictionary.intern(t)).unwrap_or(0))
             .collect();
 
-        let metadata_offsets: Vec<u32> = edges.iter()
-            .map(|e| {
-                e.metadata.as_ref()
-                    .and_then(|m| metadata_map.get(m).copied())
-                    .unwrap_or(0)
-            })
+        let metadata_ids: Vec<u32> = edges.iter()
+            .map(|e| e.metadata.as_ref().map(|m| dictionary.intern(m)).unwrap_or(0))
             .collect();
 
-        // Записываем header (пока с нулевым string_table_offset)
-        let header_offset = writer.stream_position()? as u64;
+        let version_ids: Vec<u32> = edges.iter().map(|e| dictionary.intern(&e.version)).collect();
+
+        dictionary.write(&self.path, self.compression)?;
+
         let mut header = SegmentHeader::new(
             0, // nodes count (в другом файле)
             edges.len() as u64,
-            0, // string table offset (заполним позже)
+            dictionary.id(),
         );
+        if self.compression.is_enabled() {
+            header.magic = MAGIC_COMPRESSED;
+        }
 
         self.write_header(&mut writer, &header)?;
 
-        // Записываем колоночные массивы
+        // Записываем колоночные массивы (checksum каждой колонки идёт в footer)
+        let mut sections = SectionWriter::new(&mut writer, self.compression);
+
         // 1. Source IDs
-        for edge in edges {
-            writer.write_all(&edge.src.to_le_bytes())?;
-        }
+        sections.write_u128(edges.iter().flat_map(|e| e.src.to_le_bytes()))?;
 
         // 2. Destination IDs
-        for edge in edges {
-            writer.write_all(&edge.dst.to_le_bytes())?;
-        }
+        sections.write_u128(edges.iter().flat_map(|e| e.dst.to_le_bytes()))?;
 
-        // 3. Edge type offsets (u32 offsets в StringTable, было etype u16)
-        for &edge_type_offset in &edge_type_offsets {
-            writer.write_all(&edge_type_offset.to_le_bytes())?;
-        }
+        // 3. Edge type ids (u32 id в strings.bin, было etype u16)
+        sections.write(edge_type_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
-        // 4. Metadata offsets (u32 offsets в StringTable)
-        for &metadata_offset in &metadata_offsets {
-            writer.write_all(&metadata_offset.to_le_bytes())?;
-        }
+        // 4. Metadata ids (u32 id в strings.bin)
+        sections.write(metadata_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
         // 5. Deleted flags
-        for edge in edges {
-            writer.write_all(&[if edge.deleted { 1 } else { 0 }])?;
-        }
+        sections.write(edges.iter().map(|e| e.deleted as u8))?;
 
-        // Записываем StringTable
-        let string_table_offset = writer.stream_position()?;
-        string_table.write_to(&mut writer)?;
+        // 6. Version ids
+        sections.write(version_ids.iter().flat_map(|o| o.to_le_bytes()))?;
 
-        // Обновляем header с правильным string_table_offset
-        header.string_table_offset = string_table_offset;
-        writer.seek(std::io::SeekFrom::Start(header_offset))?;
-        self.write_header(&mut writer, &header)?;
+        let checksums = sections.checksums;
+        write_checksum_footer(&mut writer, &checksums)?;
 
-        writer.flush()?;
+        commit_file(writer, &tmp_path, &edges_path)?;
 
-        tracing::info!("Written {} edges to {:?} with StringTable at offset {}",
-            edges.len(), edges_path, string_table_offset);
+        tracing::info!("Written {} edges to {:?} (strings.bin {:016x})",
+            edges.len(), edges_path, dictionary.id());
         Ok(())
     }
 
     /// Записать header в writer
-    fn write_header<W: Write>(&self, writer: &mut W, header: &SegmentHeader) -> Result<()> {
+    pub(crate) fn write_header<W: Write>(&self, writer: &mut W, header: &SegmentHeader) -> Result<()> {
         writer.write_all(&header.magic)?;
         writer.write_all(&header.version.to_le_bytes())?;
         writer.write_all(&header.node_count.to_le_bytes())?;
@@ -300,8 +234,156 @@ impl SegmentWriter {
     /// Записать метаданные графа (version, metadata)
     pub fn write_metadata(&self, metadata: &GraphMetadata) -> Result<()> {
         let meta_path = self.path.join("metadata.json");
-        let file = File::create(meta_path)?;
-        serde_json::to_writer_pretty(file, metadata)?;
+        let tmp_path = self.path.join("metadata.json.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut writer, metadata)?;
+        commit_file(writer, &tmp_path, &meta_path)
+    }
+}
+
+/// Дописать временный файл и атомарно подменить им целевой
+///
+/// Старый файл не перезаписывается на месте: открытые mmap и hard link'и
+/// (checkpoint) продолжают видеть прежнее содержимое.
+pub(crate) fn commit_file(writer: BufWriter<File>, tmp_path: &Path, path: &Path) -> Result<()> {
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    std::fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Временная директория compact: новый strings.bin и сегменты
+pub(crate) const COMPACT_TMP_DIR: &str = ".compact-tmp";
+
+/// Маркер в COMPACT_TMP_DIR: все файлы записаны, началась подмена
+pub(crate) const COMPACT_COMMIT_MARKER: &str = "COMMIT";
+
+/// Файлы, которые compact подменяет вместе (общий dictionary_id)
+const COMPACT_FILES: [&str; 3] = [DICTIONARY_FILE, "nodes.bin", "edges.bin"];
+
+/// Compact, прерванный падением процесса
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InterruptedCompaction {
+    /// Новые файлы записаны не все: файлы базы целы, результат выбрасывается
+    Incomplete,
+    /// Подмена началась: часть файлов уже новые, остальные лежат в COMPACT_TMP_DIR
+    Committed,
+}
+
+/// Подменить файлы базы результатом compact (или repair / restore) из `COMPACT_TMP_DIR`
+///
+/// Файлы переименовываются по одному, поэтому сначала пишется маркер:
+/// по нему `recover_compaction` доводит прерванную подмену до конца.
+pub(crate) fn commit_compaction(db_path: &Path) -> Result<()> {
+    mark_compaction_committed(db_path)?;
+    finish_compaction(db_path)
+}
+
+/// Записать маркер: дальше подмена доводится до конца даже после падения
+pub(crate) fn mark_compaction_committed(db_path: &Path) -> Result<()> {
+    File::create(db_path.join(COMPACT_TMP_DIR).join(COMPACT_COMMIT_MARKER))?.sync_all()?;
+    Ok(())
+}
+
+fn finish_compaction(db_path: &Path) -> Result<()> {
+    let tmp_dir = db_path.join(COMPACT_TMP_DIR);
+    for file in COMPACT_FILES {
+        // Уже перенесённых файлов в tmp нет
+        let src = tmp_dir.join(file);
+        if src.exists() {
+            std::fs::rename(&src, db_path.join(file))?;
+        }
+    }
+    std::fs::remove_dir_all(&tmp_dir)?;
+    Ok(())
+}
+
+/// Остался ли в `db_path` незавершённый compact
+pub(crate) fn interrupted_compaction(db_path: &Path) -> Option<InterruptedCompaction> {
+    let tmp_dir = db_path.join(COMPACT_TMP_DIR);
+    if !tmp_dir.is_dir() {
+        None
+    } else if tmp_dir.join(COMPACT_COMMIT_MARKER).exists() {
+        Some(InterruptedCompaction::Committed)
+    } else {
+        Some(InterruptedCompaction::Incomplete)
+    }
+}
+
+/// Путь файла базы с учётом закоммиченного, но не перенесённого compact
+pub(crate) fn compacted_file_path(db_path: &Path, file: &str) -> std::path::PathBuf {
+    let pending = db_path.join(COMPACT_TMP_DIR).join(file);
+    if interrupted_compaction(db_path) == Some(InterruptedCompaction::Committed) && pending.exists() {
+        pending
+    } else {
+        db_path.join(file)
+    }
+}
+
+/// Довести до конца или откатить compact, прерванный падением процесса
+pub(crate) fn recover_compaction(db_path: &Path) -> Result<()> {
+    match interrupted_compaction(db_path) {
+        None => {}
+        Some(InterruptedCompaction::Incomplete) => {
+            tracing::warn!("Discarding incomplete compaction in {:?}", db_path);
+            std::fs::remove_dir_all(db_path.join(COMPACT_TMP_DIR))?;
+        }
+        Some(InterruptedCompaction::Committed) => {
+            tracing::warn!("Finishing interrupted compaction in {:?}", db_path);
+            finish_compaction(db_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Пишет секции сегмента подряд: плоско или блоками (SGRZ), собирая checksums
+pub(crate) struct SectionWriter<'a, W: Write> {
+    writer: &'a mut W,
+    compression: Compression,
+    pub(crate) checksums: Vec<Checksum>,
+}
+
+impl<'a, W: Write> SectionWriter<'a, W> {
+    pub(crate) fn new(writer: &'a mut W, compression: Compression) -> Self {
+        Self { writer, compression, checksums: Vec::new() }
+    }
+
+    /// Записать секцию (колонку)
+    fn write(&mut self, bytes: impl IntoIterator<Item = u8>) -> Result<()> {
+        self.write_section(bytes.into_iter().collect(), false)
+    }
+
+    /// Записать u128 колонку (для сжатия пробуется delta/varint)
+    fn write_u128(&mut self, bytes: impl IntoIterator<Item = u8>) -> Result<()> {
+        self.write_section(bytes.into_iter().collect(), true)
+    }
+
+    /// Скопировать секцию, заранее выгруженную во временный файл (BulkLoader)
+    ///
+    /// Без сжатия данные копируются потоком; для сжатия колонка читается
+    /// целиком, т.к. секция - один блок.
+    pub(crate) fn copy_section<R: Read>(&mut self, mut reader: R, checksum: Checksum, u128_column: bool) -> Result<()> {
+        if self.compression.is_enabled() {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            return self.write_section(bytes, u128_column);
+        }
+        std::io::copy(&mut reader, self.writer)?;
+        self.checksums.push(checksum);
+        Ok(())
+    }
+
+    pub(crate) fn write_section(&mut self, bytes: Vec<u8>, u128_column: bool) -> Result<()> {
+        self.checksums.push(section_checksum(&bytes));
+
+        if self.compression.is_enabled() {
+            let (codec, encoded) = encode_block(&bytes, u128_column);
+            let mut block = Vec::new();
+            write_block(&mut block, codec, bytes.len(), &encoded);
+            self.writer.write_all(&block)?;
+        } else {
+            self.writer.write_all(&bytes)?;
+        }
         Ok(())
     }
 }
@@ -314,6 +396,19 @@ pub struct GraphMetadata {
     pub edge_count: usize,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Правила классификации endpoint'ов (используются в is_endpoint)
+    #[serde(default = "default_endpoint_rules")]
+    pub endpoint_rules: Vec<EndpointRule>,
+    /// Сжатие сегментов, применяется при следующей записи
+    #[serde(default)]
+    pub compression: Compression,
+    /// Версия формата сегментов (metadata.json без поля - база версии 1)
+    #[serde(default = "legacy_format_version")]
+    pub format_version: u16,
+}
+
+fn legacy_format_version() -> u16 {
+    1
 }
 
 impl Default for GraphMetadata {
@@ -329,6 +424,9 @@ impl Default for GraphMetadata {
             edge_count: 0,
             created_at: now,
             updated_at: now,
+            endpoint_rules: default_endpoint_rules(),
+            compression: Compression::None,
+            format_version: FORMAT_VERSION,
         }
     }
 }
@@ -337,7 +435,8 @@ impl Default for GraphMetadata {
 mod tests {
     use super::*;
     use tempfile::TempDir;
-    use crate::storage::segment::NodesSegment;
+    use crate::storage::segment::{NodesSegment, EdgesSegment, HEADER_SIZE_ON_DISK};
+    use crate::storage::StringDictionary;
 
     #[test]
     fn test_write_and_read_nodes() {
@@ -393,5 +492,43 @@ mod tests {
         assert_eq!(segment.get_name(1), Some("MyClass"));
         assert_eq!(segment.get_file_path(0), Some("src/test.js"));
         assert_eq!(segment.get_file_path(1), Some("src/test.js"));
+
+        // Footer с checksums записан и сходится
+        assert!(segment.has_checksums());
+        segment.verify().unwrap();
+    }
+
+    #[test]
+    fn test_edges_checksum_mismatch() {
+        let dir = TempDir::new().unwrap();
+        let writer = SegmentWriter::new(dir.path());
+
+        let edges = vec![EdgeRecord {
+            src: 1,
+            dst: 2,
+            edge_type: Some("CALLS".to_string()),
+            version: "main".to_string(),
+            metadata: Some("{\"argIndex\":0}".to_string()),
+            deleted: false,
+        }];
+        writer.write_edges(&edges).unwrap();
+
+        let edges_path = dir.path().join("edges.bin");
+        EdgesSegment::open(&edges_path).unwrap().verify().unwrap();
+
+        // Строки лежат в общем strings.bin, сегмент ссылается на него по id
+        let segment = EdgesSegment::open(&edges_path).unwrap();
+        let dictionary = StringDictionary::open(dir.path()).unwrap();
+        assert_eq!(segment.header().dictionary_id(), Some(dictionary.id()));
+        assert_eq!(segment.get_edge_type(0), Some("CALLS"));
+        drop(segment);
+
+        // Портим колонку metadata ids: header + src + dst + edge_type_ids
+        let mut bytes = std::fs::read(&edges_path).unwrap();
+        bytes[HEADER_SIZE_ON_DISK + 16 + 16 + 4] ^= 0xFF;
+        std::fs::write(&edges_path, &bytes).unwrap();
+
+        let err = EdgesSegment::open(&edges_path).unwrap().verify().unwrap_err();
+        assert!(err.to_string().contains("metadata_offsets"), "{}", err);
     }
 }
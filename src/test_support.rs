@@ -0,0 +1,44 @@
+//! Общие фикстуры тестов: записи нод/рёбер и движок во временной директории
+//!
+//! Остальные поля задаются через struct update: `NodeRecord { exported: true, ..node(1, "FUNCTION", "f1") }`.
+
+use tempfile::TempDir;
+use crate::graph::{GraphEngine, GraphStore};
+use crate::storage::{EdgeRecord, NodeRecord};
+
+/// Живая нода версии "main" без файла и metadata
+pub(crate) fn node(id: u128, node_type: &str, name: &str) -> NodeRecord {
+    NodeRecord {
+        id,
+        node_type: Some(node_type.to_string()),
+        file_id: 0,
+        name_offset: 0,
+        version: "main".to_string(),
+        exported: false,
+        replaces: None,
+        deleted: false,
+        name: Some(name.to_string()),
+        file: None,
+        metadata: None,
+    }
+}
+
+/// Живое ребро версии "main" без metadata
+pub(crate) fn edge(src: u128, dst: u128, edge_type: &str) -> EdgeRecord {
+    EdgeRecord {
+        src,
+        dst,
+        edge_type: Some(edge_type.to_string()),
+        version: "main".to_string(),
+        metadata: None,
+        deleted: false,
+    }
+}
+
+/// Новая база `g.rfdb` в `dir` с нодами и рёбрами в delta (концы рёбер не проверяются)
+pub(crate) fn test_engine(dir: &TempDir, nodes: Vec<NodeRecord>, edges: Vec<EdgeRecord>) -> GraphEngine {
+    let mut engine = GraphEngine::create(dir.path().join("g.rfdb")).unwrap();
+    engine.add_nodes(nodes);
+    engine.add_edges(edges, true);
+    engine
+}